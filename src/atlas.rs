@@ -0,0 +1,463 @@
+//! Glyph atlases for GPU renderers.
+//!
+//! An `Atlas` contains the coverage masks of a set of glyphs, rendered at a
+//! fixed pixel size with the rasterizer of the [`raster`](../raster/index.html)
+//! module and packed into one or more pages. For every glyph it records where
+//! its mask is stored together with the metrics needed to draw it at a pen
+//! position.
+//!
+//! The output only depends on the font, the set of glyphs, the pixel size and
+//! the options, so atlases can be compared against golden files.
+//!
+//! This module is only available with the `raster` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use harfbuzz_rs::*;
+//! use harfbuzz_rs::atlas::{Atlas, AtlasOptions};
+//!
+//! let path = "testfiles/SourceSansVariable-Roman.ttf";
+//! let face = Face::from_file(path, 0).expect("could not load face");
+//! let font = Font::new(face);
+//!
+//! let buffer = shape(&font, UnicodeBuffer::new().add_str("Hello world"), &[]);
+//! let glyphs = buffer.get_glyph_infos().iter().map(|info| info.codepoint);
+//! let atlas = Atlas::new(&font, glyphs, 24.0, AtlasOptions::default());
+//! assert_eq!(atlas.pages().len(), 1);
+//!
+//! let glyph = font.get_nominal_glyph('H').unwrap();
+//! let entry = atlas.glyph(glyph).unwrap();
+//! assert!(entry.uv.u1 > entry.uv.u0 && entry.advance > 0.0);
+//! ```
+
+use crate::common::{HarfbuzzObject, Owned, Shared};
+use crate::font::{Font, Glyph};
+use crate::raster::{rasterize_glyph, GlyphMask, GrayImage};
+
+/// Options for building an `Atlas`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasOptions {
+    /// The width of every page in pixels.
+    pub page_width: usize,
+    /// The height of every page in pixels.
+    pub page_height: usize,
+    /// The number of empty pixels between neighbouring glyphs, which prevents
+    /// bleeding when sampling the atlas with filtering.
+    pub padding: usize,
+}
+
+impl Default for AtlasOptions {
+    /// Pages of 512 by 512 pixels with a padding of one pixel.
+    fn default() -> AtlasOptions {
+        AtlasOptions {
+            page_width: 512,
+            page_height: 512,
+            padding: 1,
+        }
+    }
+}
+
+/// A rectangle in texture coordinates, which range from 0 to 1 across a page
+/// with `v` pointing downwards.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct UvRect {
+    /// The left edge.
+    pub u0: f32,
+    /// The top edge.
+    pub v0: f32,
+    /// The right edge.
+    pub u1: f32,
+    /// The bottom edge.
+    pub v1: f32,
+}
+
+/// The location and metrics of a glyph in an `Atlas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasGlyph {
+    /// The glyph index in the font.
+    pub glyph: Glyph,
+    /// The index of the page that contains the glyph.
+    pub page: usize,
+    /// The column of the left edge of the glyph's mask on its page.
+    pub x: usize,
+    /// The row of the top edge of the glyph's mask on its page.
+    pub y: usize,
+    /// The width of the glyph's mask in pixels. Zero for glyphs without an
+    /// outline.
+    pub width: usize,
+    /// The height of the glyph's mask in pixels. Zero for glyphs without an
+    /// outline.
+    pub height: usize,
+    /// The glyph's mask in the texture coordinates of its page.
+    pub uv: UvRect,
+    /// The horizontal distance from the pen position to the left edge of the
+    /// mask.
+    pub bearing_x: i32,
+    /// The vertical distance from the baseline up to the top edge of the mask.
+    pub bearing_y: i32,
+    /// The horizontal advance of the glyph in pixels.
+    pub advance: f32,
+}
+
+/// Coverage masks of glyphs packed into pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Atlas {
+    pixel_size: f32,
+    pages: Vec<GrayImage>,
+    glyphs: Vec<AtlasGlyph>,
+}
+
+impl Atlas {
+    /// Renders `glyphs` of `font` at `pixel_size` pixels per em and packs them
+    /// into pages.
+    ///
+    /// Duplicate glyphs are only added once. The masks are packed from the
+    /// tallest to the smallest with a skyline packer, starting a new page
+    /// whenever a mask does not fit into one of the existing pages. A mask
+    /// that is larger than `options` allow gets a page of its own that is
+    /// just large enough.
+    ///
+    /// The glyphs are rendered with their origin on a pixel corner, so a
+    /// renderer should round the pen position to whole pixels.
+    pub fn new(
+        font: &Font<'_>,
+        glyphs: impl IntoIterator<Item = Glyph>,
+        pixel_size: f32,
+        options: AtlasOptions,
+    ) -> Atlas {
+        // The rasterizer uses 64 units per pixel.
+        let sized = font.scaled_sub_font((pixel_size * 64.0).round() as i32);
+
+        let mut glyphs: Vec<Glyph> = glyphs.into_iter().collect();
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        let mut masks: Vec<(Glyph, Option<GlyphMask>)> = glyphs
+            .iter()
+            .map(|&glyph| (glyph, rasterize_glyph(&sized, glyph, 0.0, 0.0)))
+            .collect();
+        let size = |mask: &Option<GlyphMask>| {
+            mask.as_ref()
+                .map_or((0, 0), |mask| (mask.image.width(), mask.image.height()))
+        };
+        // Sorting by glyph last makes the order deterministic.
+        masks.sort_by_key(|(glyph, mask)| {
+            let (width, height) = size(mask);
+            (std::cmp::Reverse(height), std::cmp::Reverse(width), *glyph)
+        });
+
+        let mut pages: Vec<(Skyline, GrayImage)> = Vec::new();
+        let mut entries = Vec::with_capacity(masks.len());
+        for (glyph, mask) in masks {
+            let (width, height) = size(&mask);
+            let mut entry = AtlasGlyph {
+                glyph,
+                page: 0,
+                x: 0,
+                y: 0,
+                width,
+                height,
+                uv: UvRect::default(),
+                bearing_x: 0,
+                bearing_y: 0,
+                advance: sized.get_glyph_h_advance(glyph) as f32 / 64.0,
+            };
+            let mut mask = match mask {
+                Some(mask) => mask,
+                None => {
+                    entries.push(entry);
+                    continue;
+                }
+            };
+            entry.bearing_x = mask.left;
+            entry.bearing_y = -mask.top;
+
+            let (padded_width, padded_height) = (width + options.padding, height + options.padding);
+            let placement = pages.iter_mut().enumerate().find_map(|(index, page)| {
+                page.0
+                    .insert(padded_width, padded_height)
+                    .map(|position| (index, position))
+            });
+            let (page, (x, y)) = placement.unwrap_or_else(|| {
+                let page_width = options.page_width.max(padded_width);
+                let page_height = options.page_height.max(padded_height);
+                let mut skyline = Skyline::new(page_width, page_height);
+                let position = skyline.insert(padded_width, padded_height).unwrap();
+                pages.push((skyline, GrayImage::new(page_width, page_height)));
+                (pages.len() - 1, position)
+            });
+
+            let image = &mut pages[page].1;
+            let (page_width, page_height) = (image.width() as f32, image.height() as f32);
+            mask.left = x as i32;
+            mask.top = y as i32;
+            image.composite(&mask);
+            entry.page = page;
+            entry.x = x;
+            entry.y = y;
+            entry.uv = UvRect {
+                u0: x as f32 / page_width,
+                v0: y as f32 / page_height,
+                u1: (x + width) as f32 / page_width,
+                v1: (y + height) as f32 / page_height,
+            };
+            entries.push(entry);
+        }
+        entries.sort_by_key(|entry| entry.glyph);
+
+        Atlas {
+            pixel_size,
+            pages: pages.into_iter().map(|(_, image)| image).collect(),
+            glyphs: entries,
+        }
+    }
+
+    /// The size of an em in pixels that the glyphs were rendered at.
+    pub fn pixel_size(&self) -> f32 {
+        self.pixel_size
+    }
+
+    /// The pages of the atlas.
+    pub fn pages(&self) -> &[GrayImage] {
+        &self.pages
+    }
+
+    /// All glyphs of the atlas, ordered by glyph index.
+    pub fn glyphs(&self) -> &[AtlasGlyph] {
+        &self.glyphs
+    }
+
+    /// Returns the entry of `glyph` or `None` if the atlas does not contain
+    /// it.
+    pub fn glyph(&self, glyph: Glyph) -> Option<&AtlasGlyph> {
+        self.glyphs
+            .binary_search_by_key(&glyph, |entry| entry.glyph)
+            .ok()
+            .map(|index| &self.glyphs[index])
+    }
+}
+
+impl<'a> Font<'a> {
+    /// Creates a sub-font of `self` with `scale` units per em in both
+    /// directions.
+    pub(crate) fn scaled_sub_font(&self, scale: i32) -> Owned<Font<'a>> {
+        // The sub-font holds a reference to `self`, so it cannot outlive the
+        // data of `self` either.
+        let parent: Shared<Font<'a>> = unsafe { Shared::from_raw_ref(self.as_raw()) };
+        let mut font = Font::create_sub_font(parent);
+        font.set_scale(scale, scale);
+        font
+    }
+}
+
+/// Packs rectangles into a page by tracking the top edge of the occupied area
+/// of every column.
+#[derive(Debug, Clone)]
+struct Skyline {
+    width: usize,
+    height: usize,
+    /// The segments of the skyline from left to right as `(x, y, width)`,
+    /// where `y` is the first free row.
+    segments: Vec<(usize, usize, usize)>,
+}
+
+impl Skyline {
+    fn new(width: usize, height: usize) -> Skyline {
+        Skyline {
+            width,
+            height,
+            segments: vec![(0, 0, width)],
+        }
+    }
+
+    /// Returns the row at which a rectangle of `width` by `height` would be
+    /// placed if its left edge was at the start of the segment `index`.
+    fn fit(&self, index: usize, width: usize, height: usize) -> Option<usize> {
+        let x = self.segments[index].0;
+        if x + width > self.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut covered = 0;
+        for &(_, segment_y, segment_width) in &self.segments[index..] {
+            if covered >= width {
+                break;
+            }
+            y = y.max(segment_y);
+            covered += segment_width;
+        }
+        if y + height > self.height {
+            None
+        } else {
+            Some(y)
+        }
+    }
+
+    /// Places a rectangle of `width` by `height` as low as possible, preferring
+    /// the narrowest segment and then the leftmost one. Returns the position
+    /// of its top left corner or `None` if it does not fit.
+    fn insert(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (_, _, index, y) = (0..self.segments.len())
+            .filter_map(|index| {
+                self.fit(index, width, height)
+                    .map(|y| (y + height, self.segments[index].2, index, y))
+            })
+            .min()?;
+        let x = self.segments[index].0;
+        self.segments.insert(index, (x, y + height, width));
+
+        // Cut the segments below the new one.
+        let end = x + width;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let (segment_x, _, segment_width) = self.segments[next];
+            if segment_x >= end {
+                break;
+            }
+            if segment_x + segment_width <= end {
+                self.segments.remove(next);
+            } else {
+                self.segments[next].0 = end;
+                self.segments[next].2 = segment_x + segment_width - end;
+                break;
+            }
+        }
+
+        // Merge neighbouring segments of the same height.
+        for i in (1..self.segments.len()).rev() {
+            if self.segments[i - 1].1 == self.segments[i].1 {
+                self.segments[i - 1].2 += self.segments[i].2;
+                self.segments.remove(i);
+            }
+        }
+        Some((x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{font, shaped};
+
+    fn glyphs(font: &Font<'_>, text: &str) -> Vec<Glyph> {
+        shaped(font, text)
+            .get_glyph_infos()
+            .iter()
+            .map(|info| info.codepoint)
+            .collect()
+    }
+
+    fn overlaps(a: &AtlasGlyph, b: &AtlasGlyph) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.width
+            && b.x < a.x + a.width
+            && a.y < b.y + b.height
+            && b.y < a.y + a.height
+    }
+
+    #[test]
+    fn test_skyline() {
+        let mut skyline = Skyline::new(10, 10);
+        assert_eq!(skyline.insert(4, 3), Some((0, 0)));
+        assert_eq!(skyline.insert(4, 2), Some((4, 0)));
+        assert_eq!(skyline.insert(2, 5), Some((8, 0)));
+        // the lowest position is on top of the second rectangle
+        assert_eq!(skyline.insert(4, 2), Some((4, 2)));
+        assert_eq!(skyline.segments, vec![(0, 3, 4), (4, 4, 4), (8, 5, 2)]);
+        assert_eq!(skyline.insert(10, 5), Some((0, 5)));
+        assert_eq!(skyline.insert(1, 1), None);
+        assert_eq!(skyline.insert(11, 1), None);
+    }
+
+    #[test]
+    fn test_atlas() {
+        let font = font();
+        let text = "Hello, world!";
+        let atlas = Atlas::new(&font, glyphs(&font, text), 32.0, AtlasOptions::default());
+        assert_eq!(atlas.pixel_size(), 32.0);
+        assert_eq!(atlas.pages().len(), 1);
+        let mut unique = glyphs(&font, text);
+        unique.sort();
+        unique.dedup();
+        let entries: Vec<Glyph> = atlas.glyphs().iter().map(|entry| entry.glyph).collect();
+        assert_eq!(entries, unique);
+
+        let sized = font.scaled_sub_font(32 * 64);
+        let page = &atlas.pages()[0];
+        for entry in atlas.glyphs() {
+            assert!(atlas
+                .glyphs()
+                .iter()
+                .all(|other| other == entry || !overlaps(entry, other)));
+            assert_eq!(
+                entry.advance,
+                sized.get_glyph_h_advance(entry.glyph) as f32 / 64.0
+            );
+            let mask = match rasterize_glyph(&sized, entry.glyph, 0.0, 0.0) {
+                Some(mask) => mask,
+                None => {
+                    assert_eq!((entry.width, entry.height), (0, 0));
+                    continue;
+                }
+            };
+            assert_eq!((entry.bearing_x, entry.bearing_y), (mask.left, -mask.top));
+            assert_eq!(entry.uv.u0, entry.x as f32 / 512.0);
+            assert_eq!(entry.uv.v1, (entry.y + entry.height) as f32 / 512.0);
+            for y in 0..entry.height {
+                for x in 0..entry.width {
+                    assert_eq!(page.pixel(entry.x + x, entry.y + y), mask.image.pixel(x, y));
+                }
+            }
+        }
+        let space = font.get_nominal_glyph(' ').unwrap();
+        assert_eq!(atlas.glyph(space).unwrap().width, 0);
+        assert!(atlas.glyph(space).unwrap().advance > 0.0);
+        assert_eq!(atlas.glyph(font.get_nominal_glyph('x').unwrap()), None);
+
+        // the output is deterministic
+        let mut reversed = glyphs(&font, text);
+        reversed.reverse();
+        let again = Atlas::new(&font, reversed, 32.0, AtlasOptions::default());
+        assert_eq!(again, atlas);
+    }
+
+    #[test]
+    fn test_multiple_pages() {
+        let font = font();
+        let options = AtlasOptions {
+            page_width: 40,
+            page_height: 40,
+            padding: 2,
+        };
+        let atlas = Atlas::new(&font, glyphs(&font, "ABCDEFGH"), 32.0, options);
+        assert!(atlas.pages().len() > 1);
+        for entry in atlas.glyphs() {
+            let page = &atlas.pages()[entry.page];
+            assert!(entry.x + entry.width <= page.width());
+            assert!(entry.y + entry.height <= page.height());
+            for other in atlas.glyphs() {
+                assert!(other == entry || !overlaps(entry, other));
+            }
+        }
+
+        // glyphs larger than a page get a page of their own
+        let options = AtlasOptions {
+            page_width: 8,
+            page_height: 8,
+            padding: 0,
+        };
+        let atlas = Atlas::new(&font, glyphs(&font, "W"), 32.0, options);
+        let entry = &atlas.glyphs()[0];
+        let page = &atlas.pages()[0];
+        assert_eq!((page.width(), page.height()), (entry.width, entry.height));
+        assert_eq!(
+            entry.uv,
+            UvRect {
+                u0: 0.0,
+                v0: 0.0,
+                u1: 1.0,
+                v1: 1.0
+            }
+        );
+    }
+}
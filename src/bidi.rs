@@ -0,0 +1,364 @@
+//! A simplified implementation of the Unicode Bidirectional Algorithm
+//! ([UAX #9](https://www.unicode.org/reports/tr9/)).
+//!
+//! Explicit embeddings, overrides and isolates are not supported. The
+//! bidirectional class of a character is derived from its script and general
+//! category, with tables for numbers and the most common separators.
+
+use std::ops::Range;
+
+use harfbuzz_bindings::{
+    hb_unicode_funcs_get_default, hb_unicode_general_category, hb_unicode_script, HB_SCRIPT_ARABIC,
+    HB_SCRIPT_COMMON, HB_SCRIPT_INHERITED, HB_SCRIPT_SYRIAC, HB_SCRIPT_THAANA, HB_SCRIPT_UNKNOWN,
+    HB_UNICODE_GENERAL_CATEGORY_CONTROL, HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK,
+    HB_UNICODE_GENERAL_CATEGORY_FORMAT, HB_UNICODE_GENERAL_CATEGORY_LINE_SEPARATOR,
+    HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK, HB_UNICODE_GENERAL_CATEGORY_PARAGRAPH_SEPARATOR,
+    HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR,
+};
+
+use crate::common::{Direction, Script};
+
+/// Bidirectional character types of UAX #9 that are distinguished by this
+/// module.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum BidiClass {
+    L,
+    R,
+    AL,
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    B,
+    S,
+    WS,
+    ON,
+}
+
+pub(crate) fn bidi_class(c: char) -> BidiClass {
+    use self::BidiClass::*;
+    match c {
+        '\n' | '\r' | '\u{1C}'..='\u{1E}' | '\u{85}' | '\u{2029}' => return B,
+        '\t' | '\u{0B}' | '\u{1F}' => return S,
+        ' ' | '\u{0C}' | '\u{2028}' => return WS,
+        '\u{200E}' => return L,
+        '\u{200F}' => return R,
+        '\u{061C}' => return AL,
+        '0'..='9'
+        | '\u{B2}'
+        | '\u{B3}'
+        | '\u{B9}'
+        | '\u{06F0}'..='\u{06F9}'
+        | '\u{2070}'..='\u{2079}'
+        | '\u{2080}'..='\u{2089}'
+        | '\u{FF10}'..='\u{FF19}' => return EN,
+        '+' | '-' | '\u{207A}' | '\u{207B}' | '\u{208A}' | '\u{208B}' | '\u{2212}' => return ES,
+        '#'
+        | '$'
+        | '%'
+        | '\u{A2}'..='\u{A5}'
+        | '\u{B0}'
+        | '\u{B1}'
+        | '\u{2030}'..='\u{2034}'
+        | '\u{20A0}'..='\u{20CF}' => return ET,
+        '\u{0600}'..='\u{0605}' | '\u{0660}'..='\u{0669}' | '\u{066B}' | '\u{066C}' => return AN,
+        ',' | '.' | '/' | ':' | '\u{A0}' | '\u{060C}' | '\u{202F}' | '\u{2044}' => return CS,
+        _ => {}
+    }
+    let funcs = unsafe { hb_unicode_funcs_get_default() };
+    match unsafe { hb_unicode_general_category(funcs, c as u32) } {
+        HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK
+        | HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK
+        | HB_UNICODE_GENERAL_CATEGORY_FORMAT
+        | HB_UNICODE_GENERAL_CATEGORY_CONTROL => return NSM,
+        HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR
+        | HB_UNICODE_GENERAL_CATEGORY_LINE_SEPARATOR => return WS,
+        HB_UNICODE_GENERAL_CATEGORY_PARAGRAPH_SEPARATOR => return B,
+        _ => {}
+    }
+    let script = Script(unsafe { hb_unicode_script(funcs, c as u32) });
+    match script.0 {
+        HB_SCRIPT_ARABIC | HB_SCRIPT_SYRIAC | HB_SCRIPT_THAANA => AL,
+        HB_SCRIPT_COMMON | HB_SCRIPT_INHERITED | HB_SCRIPT_UNKNOWN => ON,
+        _ if script.horizontal_direction() == Direction::Rtl => R,
+        _ => L,
+    }
+}
+
+/// The resolved embedding levels of a paragraph of text.
+#[derive(Debug, Clone)]
+pub(crate) struct BidiParagraph {
+    /// The embedding level of the paragraph: 0 if it is left-to-right and 1
+    /// if it is right-to-left.
+    pub(crate) level: u8,
+    // The original class and the resolved level of each byte of the text.
+    classes: Vec<BidiClass>,
+    levels: Vec<u8>,
+}
+
+impl BidiParagraph {
+    /// Resolves the embedding levels of `text`.
+    ///
+    /// If `direction` is neither `Ltr` nor `Rtl` the direction of the
+    /// paragraph is taken from its first strong character (rules P2 and P3).
+    pub(crate) fn new(text: &str, direction: Direction) -> BidiParagraph {
+        use self::BidiClass::*;
+        let original: Vec<BidiClass> = text.chars().map(bidi_class).collect();
+        let level = match direction {
+            Direction::Ltr => 0,
+            Direction::Rtl => 1,
+            _ => match original.iter().find(|&&class| [L, R, AL].contains(&class)) {
+                Some(R) | Some(AL) => 1,
+                _ => 0,
+            },
+        };
+        let sos = if level % 2 == 0 { L } else { R };
+        let len = original.len();
+        let mut types = original.clone();
+
+        // W1: non-spacing marks take the type of the previous character.
+        let mut previous = sos;
+        for class in &mut types {
+            if *class == NSM {
+                *class = previous;
+            }
+            previous = *class;
+        }
+        // W2 and W3: numbers after Arabic letters are Arabic numbers.
+        let mut last_strong = sos;
+        for class in &mut types {
+            match *class {
+                L | R | AL => last_strong = *class,
+                EN if last_strong == AL => *class = AN,
+                _ => {}
+            }
+            if *class == AL {
+                *class = R;
+            }
+        }
+        // W4: single separators between numbers of the same type.
+        for i in 1..len.saturating_sub(1) {
+            let (before, after) = (types[i - 1], types[i + 1]);
+            if types[i] == ES && before == EN && after == EN {
+                types[i] = EN;
+            } else if types[i] == CS && before == after && (before == EN || before == AN) {
+                types[i] = before;
+            }
+        }
+        // W5: terminators adjacent to European numbers.
+        let mut i = 0;
+        while i < len {
+            let start = i;
+            while i < len && types[i] == ET {
+                i += 1;
+            }
+            if start == i {
+                i += 1;
+                continue;
+            }
+            if (start > 0 && types[start - 1] == EN) || (i < len && types[i] == EN) {
+                types[start..i].iter_mut().for_each(|class| *class = EN);
+            }
+        }
+        // W6 and W7
+        let mut last_strong = sos;
+        for class in &mut types {
+            match *class {
+                ES | ET | CS => *class = ON,
+                L | R => last_strong = *class,
+                EN if last_strong == L => *class = L,
+                _ => {}
+            }
+        }
+        // N1 and N2: neutrals between characters of the same direction take
+        // that direction, all others the direction of the paragraph.
+        let strong = |class: BidiClass| match class {
+            L => Some(L),
+            R | EN | AN => Some(R),
+            _ => None,
+        };
+        let mut i = 0;
+        while i < len {
+            let start = i;
+            while i < len && strong(types[i]).is_none() {
+                i += 1;
+            }
+            if start == i {
+                i += 1;
+                continue;
+            }
+            let before = if start == 0 {
+                sos
+            } else {
+                strong(types[start - 1]).unwrap()
+            };
+            let after = if i == len {
+                sos
+            } else {
+                strong(types[i]).unwrap()
+            };
+            let resolved = if before == after { before } else { sos };
+            types[start..i]
+                .iter_mut()
+                .for_each(|class| *class = resolved);
+        }
+        // I1 and I2
+        let mut char_levels: Vec<u8> = types
+            .iter()
+            .map(|&class| match (level % 2, class) {
+                (0, R) => level + 1,
+                (0, EN) | (0, AN) => level + 2,
+                (1, L) | (1, EN) | (1, AN) => level + 1,
+                _ => level,
+            })
+            .collect();
+        // L1: separators and the whitespace before them.
+        let mut reset = false;
+        for (char_level, &class) in char_levels.iter_mut().zip(&original).rev() {
+            match class {
+                B | S => reset = true,
+                WS if reset => {}
+                _ => reset = false,
+            }
+            if reset {
+                *char_level = level;
+            }
+        }
+
+        let mut classes = Vec::with_capacity(text.len());
+        let mut levels = Vec::with_capacity(text.len());
+        for ((c, &class), &char_level) in text.chars().zip(&original).zip(&char_levels) {
+            for _ in 0..c.len_utf8() {
+                classes.push(class);
+                levels.push(char_level);
+            }
+        }
+        BidiParagraph {
+            level,
+            classes,
+            levels,
+        }
+    }
+
+    /// The direction of the paragraph.
+    pub(crate) fn direction(&self) -> Direction {
+        level_direction(self.level)
+    }
+
+    /// The resolved embedding levels of the bytes of the text.
+    pub(crate) fn levels(&self) -> &[u8] {
+        &self.levels
+    }
+
+    /// The embedding levels of the bytes in `line` with the whitespace at the
+    /// end of the line reset to the paragraph level (rule L1).
+    pub(crate) fn line_levels(&self, line: Range<usize>) -> Vec<u8> {
+        use self::BidiClass::*;
+        let mut levels = self.levels[line.clone()].to_vec();
+        for (level, class) in levels.iter_mut().zip(&self.classes[line]).rev() {
+            if ![WS, S, B].contains(class) {
+                break;
+            }
+            *level = self.level;
+        }
+        levels
+    }
+}
+
+/// The direction of text with the embedding level `level`.
+pub(crate) fn level_direction(level: u8) -> Direction {
+    if level % 2 == 0 {
+        Direction::Ltr
+    } else {
+        Direction::Rtl
+    }
+}
+
+/// Returns the indices of items with the given embedding levels in visual
+/// order, from left to right (rule L2).
+pub(crate) fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let highest = levels.iter().cloned().max().unwrap_or(0);
+    let lowest_odd = levels.iter().cloned().min().unwrap_or(0) | 1;
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            if start == i {
+                i += 1;
+            } else {
+                order[start..i].reverse();
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_levels(text: &str, direction: Direction) -> Vec<u8> {
+        let paragraph = BidiParagraph::new(text, direction);
+        text.char_indices()
+            .map(|(offset, _)| paragraph.levels()[offset])
+            .collect()
+    }
+
+    #[test]
+    fn test_bidi_classes() {
+        use super::BidiClass::*;
+        let text = "a\u{5D0}\u{628}1\u{661} -,\u{301}";
+        let classes: Vec<_> = text.chars().map(bidi_class).collect();
+        assert_eq!(classes, vec![L, R, AL, EN, AN, WS, ES, CS, NSM]);
+    }
+
+    #[test]
+    fn test_resolve_levels() {
+        let text = "ab \u{5D0}\u{5D1} cd";
+        assert_eq!(
+            char_levels(text, Direction::Ltr),
+            vec![0, 0, 0, 1, 1, 0, 0, 0]
+        );
+        assert_eq!(
+            char_levels(text, Direction::Rtl),
+            vec![2, 2, 1, 1, 1, 1, 2, 2]
+        );
+        assert_eq!(BidiParagraph::new(text, Direction::Invalid).level, 0);
+        assert_eq!(BidiParagraph::new("1 \u{5D0}", Direction::Invalid).level, 1);
+
+        // European numbers after Hebrew, Arabic numbers after Arabic letters
+        assert_eq!(
+            char_levels("\u{5D0} 1.5", Direction::Ltr),
+            vec![1, 1, 2, 2, 2]
+        );
+        assert_eq!(char_levels("\u{628} 12", Direction::Ltr), vec![1, 1, 2, 2]);
+        assert_eq!(char_levels("a 1.5", Direction::Rtl), vec![2, 2, 2, 2, 2]);
+        assert_eq!(
+            char_levels("\u{5D0}\t\u{5D0}", Direction::Ltr),
+            vec![1, 0, 1]
+        );
+    }
+
+    #[test]
+    fn test_line_levels() {
+        let text = "\u{5D0} \u{5D1} ";
+        let paragraph = BidiParagraph::new(text, Direction::Ltr);
+        assert_eq!(paragraph.line_levels(0..3), vec![1, 1, 0]);
+        assert_eq!(paragraph.line_levels(3..6), vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn test_visual_order() {
+        assert_eq!(visual_order(&[0, 0, 1, 1, 0]), vec![0, 1, 3, 2, 4]);
+        assert_eq!(visual_order(&[1, 2, 2, 1]), vec![3, 1, 2, 0]);
+        assert_eq!(visual_order(&[2, 1, 0]), vec![1, 0, 2]);
+        assert_eq!(visual_order(&[]), Vec::<usize>::new());
+    }
+}
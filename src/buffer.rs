@@ -6,28 +6,40 @@ use std::os::raw::c_uint;
 use std::ptr::NonNull;
 
 use harfbuzz_bindings::{
-    hb_buffer_add, hb_buffer_add_utf8, hb_buffer_append, hb_buffer_clear_contents,
-    HB_BUFFER_CLUSTER_LEVEL_CHARACTERS, HB_BUFFER_CLUSTER_LEVEL_MONOTONE_CHARACTERS, HB_BUFFER_CLUSTER_LEVEL_MONOTONE_GRAPHEMES, hb_buffer_cluster_level_t,
-    HB_BUFFER_CONTENT_TYPE_GLYPHS, hb_buffer_content_type_t, HB_BUFFER_CONTENT_TYPE_UNICODE,
-    hb_buffer_create, hb_buffer_destroy, hb_buffer_get_cluster_level,
-    hb_buffer_get_content_type, hb_buffer_get_direction, hb_buffer_get_empty,
-    hb_buffer_get_glyph_infos, hb_buffer_get_glyph_positions, hb_buffer_get_language,
-    hb_buffer_get_length, hb_buffer_get_script, hb_buffer_get_segment_properties, hb_buffer_guess_segment_properties,
-    hb_buffer_pre_allocate, hb_buffer_reference, hb_buffer_reverse,
-    hb_buffer_reverse_range, HB_BUFFER_SERIALIZE_FLAG_GLYPH_EXTENTS, HB_BUFFER_SERIALIZE_FLAG_GLYPH_FLAGS, HB_BUFFER_SERIALIZE_FLAG_NO_ADVANCES,
-    HB_BUFFER_SERIALIZE_FLAG_NO_CLUSTERS, HB_BUFFER_SERIALIZE_FLAG_NO_GLYPH_NAMES, HB_BUFFER_SERIALIZE_FLAG_NO_POSITIONS, HB_BUFFER_SERIALIZE_FORMAT_JSON,
-    hb_buffer_serialize_format_t, HB_BUFFER_SERIALIZE_FORMAT_TEXT, hb_buffer_serialize_glyphs, hb_buffer_set_cluster_level,
-    hb_buffer_set_content_type, hb_buffer_set_direction, hb_buffer_set_language,
-    hb_buffer_set_script, hb_buffer_t,
-    HB_GLYPH_FLAG_UNSAFE_TO_BREAK, hb_glyph_flags_t,
-    hb_glyph_info_get_glyph_flags, hb_glyph_info_t,
-    hb_mask_t, hb_script_from_iso15924_tag,
-    hb_script_t, hb_script_to_iso15924_tag,
-    hb_segment_properties_t, hb_var_int_t,
+    hb_buffer_add, hb_buffer_add_codepoints, hb_buffer_add_latin1, hb_buffer_add_utf16,
+    hb_buffer_add_utf32, hb_buffer_add_utf8, hb_buffer_append, hb_buffer_clear_contents,
+    hb_buffer_cluster_level_t, hb_buffer_content_type_t, hb_buffer_create, hb_buffer_destroy,
+    hb_buffer_flags_t, hb_buffer_get_cluster_level, hb_buffer_get_content_type,
+    hb_buffer_get_direction, hb_buffer_get_empty, hb_buffer_get_flags, hb_buffer_get_glyph_infos,
+    hb_buffer_get_glyph_positions, hb_buffer_get_invisible_glyph, hb_buffer_get_language,
+    hb_buffer_get_length, hb_buffer_get_not_found_glyph, hb_buffer_get_replacement_codepoint,
+    hb_buffer_get_script, hb_buffer_get_segment_properties, hb_buffer_get_unicode_funcs,
+    hb_buffer_guess_segment_properties, hb_buffer_pre_allocate, hb_buffer_reference,
+    hb_buffer_reverse, hb_buffer_reverse_range, hb_buffer_serialize_format_t,
+    hb_buffer_serialize_glyphs, hb_buffer_set_cluster_level, hb_buffer_set_content_type,
+    hb_buffer_set_direction, hb_buffer_set_flags, hb_buffer_set_invisible_glyph,
+    hb_buffer_set_language, hb_buffer_set_not_found_glyph, hb_buffer_set_replacement_codepoint,
+    hb_buffer_set_script, hb_buffer_set_segment_properties, hb_buffer_set_unicode_funcs,
+    hb_buffer_t, hb_glyph_flags_t, hb_glyph_info_get_glyph_flags, hb_glyph_info_t, hb_mask_t,
+    hb_script_from_iso15924_tag, hb_script_t, hb_script_to_iso15924_tag, hb_segment_properties_t,
+    hb_var_int_t, HB_BUFFER_CLUSTER_LEVEL_CHARACTERS, HB_BUFFER_CLUSTER_LEVEL_MONOTONE_CHARACTERS,
+    HB_BUFFER_CLUSTER_LEVEL_MONOTONE_GRAPHEMES, HB_BUFFER_CONTENT_TYPE_GLYPHS,
+    HB_BUFFER_CONTENT_TYPE_UNICODE, HB_BUFFER_FLAG_BOT, HB_BUFFER_FLAG_DO_NOT_INSERT_DOTTED_CIRCLE,
+    HB_BUFFER_FLAG_EOT, HB_BUFFER_FLAG_PRESERVE_DEFAULT_IGNORABLES,
+    HB_BUFFER_FLAG_PRODUCE_SAFE_TO_INSERT_TATWEEL, HB_BUFFER_FLAG_PRODUCE_UNSAFE_TO_CONCAT,
+    HB_BUFFER_FLAG_REMOVE_DEFAULT_IGNORABLES, HB_BUFFER_FLAG_VERIFY,
+    HB_BUFFER_SERIALIZE_FLAG_GLYPH_EXTENTS, HB_BUFFER_SERIALIZE_FLAG_GLYPH_FLAGS,
+    HB_BUFFER_SERIALIZE_FLAG_NO_ADVANCES, HB_BUFFER_SERIALIZE_FLAG_NO_CLUSTERS,
+    HB_BUFFER_SERIALIZE_FLAG_NO_GLYPH_NAMES, HB_BUFFER_SERIALIZE_FLAG_NO_POSITIONS,
+    HB_BUFFER_SERIALIZE_FORMAT_JSON, HB_BUFFER_SERIALIZE_FORMAT_TEXT,
+    HB_GLYPH_FLAG_SAFE_TO_INSERT_TATWEEL, HB_GLYPH_FLAG_UNSAFE_TO_BREAK,
+    HB_GLYPH_FLAG_UNSAFE_TO_CONCAT,
 };
 
-use crate::common::{Direction, HarfbuzzObject, Language, Owned, Script, Tag};
-use crate::font::Position;
+use crate::common::{Direction, HarfbuzzObject, Language, Owned, Script, Shared, Tag};
+use crate::font::{Glyph, Position};
+use crate::unicode_funcs::UnicodeFuncs;
+use crate::Feature;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[repr(C)]
@@ -128,6 +140,27 @@ impl GlyphFlags {
     pub fn unsafe_to_break(&self) -> bool {
         self.0 & HB_GLYPH_FLAG_UNSAFE_TO_BREAK == HB_GLYPH_FLAG_UNSAFE_TO_BREAK
     }
+
+    /// If `true`, indicates that if input text is changed on one side of the
+    /// beginning of the cluster this glyph is part of, then the shaping
+    /// results for the other side might change. Glyphs that are unsafe to
+    /// break are always unsafe to concat as well.
+    ///
+    /// When this returns `false` the text on both sides of the cluster start
+    /// can be shaped separately and the results concatenated, provided the
+    /// other side is shaped with enough context. This flag is only produced
+    /// if the buffer was shaped with `BufferFlags::PRODUCE_UNSAFE_TO_CONCAT`.
+    pub fn unsafe_to_concat(&self) -> bool {
+        self.0 & HB_GLYPH_FLAG_UNSAFE_TO_CONCAT == HB_GLYPH_FLAG_UNSAFE_TO_CONCAT
+    }
+
+    /// If `true`, indicates that it is safe to insert a U+0640 TATWEEL
+    /// character before this cluster for elongation. This flag is only
+    /// produced if the buffer was shaped with
+    /// `BufferFlags::PRODUCE_SAFE_TO_INSERT_TATWEEL`.
+    pub fn safe_to_insert_tatweel(&self) -> bool {
+        self.0 & HB_GLYPH_FLAG_SAFE_TO_INSERT_TATWEEL == HB_GLYPH_FLAG_SAFE_TO_INSERT_TATWEEL
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -192,6 +225,35 @@ impl Default for ClusterLevel {
     }
 }
 
+bitflags! {
+    /// Flags that control how the contents of a buffer are shaped.
+    #[derive(Default)]
+    pub struct BufferFlags: hb_buffer_flags_t {
+        /// The buffer contents are at the beginning of the text (e.g. a
+        /// paragraph). Allows special handling of the beginning of text, like
+        /// inserting a dotted circle before a leading combining mark.
+        const BOT = HB_BUFFER_FLAG_BOT;
+        /// The buffer contents are at the end of the text.
+        const EOT = HB_BUFFER_FLAG_EOT;
+        /// Keep default ignorable characters (e.g. ZWJ or variation
+        /// selectors) visible instead of hiding them.
+        const PRESERVE_DEFAULT_IGNORABLES = HB_BUFFER_FLAG_PRESERVE_DEFAULT_IGNORABLES;
+        /// Remove default ignorable characters from the output instead of
+        /// replacing them with the invisible glyph.
+        const REMOVE_DEFAULT_IGNORABLES = HB_BUFFER_FLAG_REMOVE_DEFAULT_IGNORABLES;
+        /// Do not insert a dotted circle glyph before broken character
+        /// sequences.
+        const DO_NOT_INSERT_DOTTED_CIRCLE = HB_BUFFER_FLAG_DO_NOT_INSERT_DOTTED_CIRCLE;
+        /// Let HarfBuzz verify the produced glyph flags after shaping. This
+        /// is slow and only meant for debugging.
+        const VERIFY = HB_BUFFER_FLAG_VERIFY;
+        /// Compute `GlyphFlags::unsafe_to_concat()` during shaping.
+        const PRODUCE_UNSAFE_TO_CONCAT = HB_BUFFER_FLAG_PRODUCE_UNSAFE_TO_CONCAT;
+        /// Compute `GlyphFlags::safe_to_insert_tatweel()` during shaping.
+        const PRODUCE_SAFE_TO_INSERT_TATWEEL = HB_BUFFER_FLAG_PRODUCE_SAFE_TO_INSERT_TATWEEL;
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct GenericBuffer {
     raw: NonNull<hb_buffer_t>,
@@ -224,19 +286,106 @@ impl GenericBuffer {
     }
 
     pub(crate) fn add_str_item(&mut self, string: &str, item_start: usize, item_len: usize) {
-        assert!(item_start + item_len <= string.len());
-        let utf8_ptr = string.as_ptr() as *const _;
+        self.add_utf8_item(string.as_bytes(), item_start, item_len)
+    }
+
+    /// Adds UTF-8 encoded text to the buffer. Invalid sequences are added as
+    /// the buffer's replacement codepoint.
+    pub(crate) fn add_utf8_item(&mut self, bytes: &[u8], item_start: usize, item_len: usize) {
+        assert!(item_start + item_len <= bytes.len());
+        let utf8_ptr = bytes.as_ptr() as *const _;
         unsafe {
             hb_buffer_add_utf8(
                 self.as_raw(),
                 utf8_ptr,
-                string.len() as os::raw::c_int,
+                bytes.len() as os::raw::c_int,
+                item_start as os::raw::c_uint,
+                item_len as os::raw::c_int,
+            );
+        }
+    }
+
+    /// Adds UTF-16 encoded text to the buffer. Unpaired surrogates are added
+    /// as the buffer's replacement codepoint.
+    pub(crate) fn add_utf16_item(&mut self, text: &[u16], item_start: usize, item_len: usize) {
+        assert!(item_start + item_len <= text.len());
+        unsafe {
+            hb_buffer_add_utf16(
+                self.as_raw(),
+                text.as_ptr(),
+                text.len() as os::raw::c_int,
+                item_start as os::raw::c_uint,
+                item_len as os::raw::c_int,
+            );
+        }
+    }
+
+    /// Adds UTF-32 encoded text to the buffer. Invalid codepoints are added as
+    /// the buffer's replacement codepoint.
+    pub(crate) fn add_utf32_item(&mut self, text: &[u32], item_start: usize, item_len: usize) {
+        assert!(item_start + item_len <= text.len());
+        unsafe {
+            hb_buffer_add_utf32(
+                self.as_raw(),
+                text.as_ptr(),
+                text.len() as os::raw::c_int,
+                item_start as os::raw::c_uint,
+                item_len as os::raw::c_int,
+            );
+        }
+    }
+
+    /// Adds codepoints to the buffer without validating them.
+    pub(crate) fn add_codepoints_item(&mut self, text: &[u32], item_start: usize, item_len: usize) {
+        assert!(item_start + item_len <= text.len());
+        unsafe {
+            hb_buffer_add_codepoints(
+                self.as_raw(),
+                text.as_ptr(),
+                text.len() as os::raw::c_int,
+                item_start as os::raw::c_uint,
+                item_len as os::raw::c_int,
+            );
+        }
+    }
+
+    /// Adds Latin-1 (ISO-8859-1) encoded text to the buffer.
+    pub(crate) fn add_latin1_item(&mut self, text: &[u8], item_start: usize, item_len: usize) {
+        assert!(item_start + item_len <= text.len());
+        unsafe {
+            hb_buffer_add_latin1(
+                self.as_raw(),
+                text.as_ptr(),
+                text.len() as os::raw::c_int,
                 item_start as os::raw::c_uint,
                 item_len as os::raw::c_int,
             );
         }
     }
 
+    // HarfBuzz has no setter for the buffer context. Instead the context is
+    // taken from the text surrounding the added item, so adding an empty item
+    // at the end (or start) of the context text sets the pre- (or post-)
+    // context without adding any codepoints. Like any added item, this also
+    // replaces the post-context with the (empty) text following the item, and
+    // HarfBuzz has no getter to restore it.
+
+    pub(crate) fn set_pre_context_str(&mut self, context: &str) {
+        self.add_str_item(context, context.len(), 0)
+    }
+
+    pub(crate) fn set_post_context_str(&mut self, context: &str) {
+        self.add_str_item(context, 0, 0)
+    }
+
+    pub(crate) fn set_pre_context_codepoints(&mut self, context: &[u32]) {
+        self.add_codepoints_item(context, context.len(), 0)
+    }
+
+    pub(crate) fn set_post_context_codepoints(&mut self, context: &[u32]) {
+        self.add_codepoints_item(context, 0, 0)
+    }
+
     pub(crate) fn append(&mut self, source: &GenericBuffer, start: c_uint, end: c_uint) {
         unsafe {
             hb_buffer_append(self.as_raw(), source.as_raw(), start, end);
@@ -285,6 +434,11 @@ impl GenericBuffer {
         }
     }
 
+    pub(crate) fn set_segment_properties(&mut self, props: SegmentProperties) {
+        let raw = props.into_raw();
+        unsafe { hb_buffer_set_segment_properties(self.as_raw(), &raw) }
+    }
+
     pub(crate) fn set_cluster_level(&mut self, cluster_level: ClusterLevel) {
         unsafe { hb_buffer_set_cluster_level(self.as_raw(), cluster_level.into_raw()) }
     }
@@ -293,6 +447,46 @@ impl GenericBuffer {
         ClusterLevel::from_raw(unsafe { hb_buffer_get_cluster_level(self.as_raw()) })
     }
 
+    pub(crate) fn set_flags(&mut self, flags: BufferFlags) {
+        unsafe { hb_buffer_set_flags(self.as_raw(), flags.bits()) }
+    }
+
+    pub(crate) fn get_flags(&self) -> BufferFlags {
+        BufferFlags::from_bits_truncate(unsafe { hb_buffer_get_flags(self.as_raw()) })
+    }
+
+    pub(crate) fn set_unicode_funcs(&mut self, funcs: &UnicodeFuncs) {
+        unsafe { hb_buffer_set_unicode_funcs(self.as_raw(), funcs.as_raw()) }
+    }
+
+    pub(crate) fn get_unicode_funcs(&self) -> Shared<UnicodeFuncs> {
+        unsafe { Shared::from_raw_ref(hb_buffer_get_unicode_funcs(self.as_raw())) }
+    }
+
+    pub(crate) fn set_replacement_codepoint(&mut self, replacement: u32) {
+        unsafe { hb_buffer_set_replacement_codepoint(self.as_raw(), replacement) }
+    }
+
+    pub(crate) fn get_replacement_codepoint(&self) -> u32 {
+        unsafe { hb_buffer_get_replacement_codepoint(self.as_raw()) }
+    }
+
+    pub(crate) fn set_invisible_glyph(&mut self, invisible: Glyph) {
+        unsafe { hb_buffer_set_invisible_glyph(self.as_raw(), invisible) }
+    }
+
+    pub(crate) fn get_invisible_glyph(&self) -> Glyph {
+        unsafe { hb_buffer_get_invisible_glyph(self.as_raw()) }
+    }
+
+    pub(crate) fn set_not_found_glyph(&mut self, not_found: Glyph) {
+        unsafe { hb_buffer_set_not_found_glyph(self.as_raw(), not_found) }
+    }
+
+    pub(crate) fn get_not_found_glyph(&self) -> Glyph {
+        unsafe { hb_buffer_get_not_found_glyph(self.as_raw()) }
+    }
+
     pub(crate) fn pre_allocate(&mut self, size: usize) {
         let size = size.min(std::os::raw::c_uint::max_value() as usize);
         unsafe { hb_buffer_pre_allocate(self.as_raw(), size as _) };
@@ -318,6 +512,31 @@ impl GenericBuffer {
         }
     }
 
+    pub(crate) fn get_glyph_positions_mut(&mut self) -> &mut [GlyphPosition] {
+        unsafe {
+            let mut length: u32 = 0;
+            let glyph_pos = hb_buffer_get_glyph_positions(self.as_raw(), &mut length as *mut u32);
+            std::slice::from_raw_parts_mut(glyph_pos as *mut _, length as usize)
+        }
+    }
+
+    pub(crate) fn get_glyph_infos_mut(&mut self) -> &mut [GlyphInfo] {
+        unsafe {
+            let mut length: u32 = 0;
+            let glyph_infos = hb_buffer_get_glyph_infos(self.as_raw(), &mut length as *mut u32);
+            std::slice::from_raw_parts_mut(glyph_infos as *mut _, length as usize)
+        }
+    }
+
+    /// Returns the glyph infos and positions at the same time.
+    pub(crate) fn get_glyphs_mut(&mut self) -> (&mut [GlyphInfo], &mut [GlyphPosition]) {
+        // The infos and positions live in two separate allocations, so handing
+        // out mutable references to both of them is fine.
+        let positions = self.get_glyph_positions_mut() as *mut [GlyphPosition];
+        let infos = self.get_glyph_infos_mut();
+        (infos, unsafe { &mut *positions })
+    }
+
     /// Reverse the `Buffer`'s contents.
     pub(crate) fn reverse(&mut self) {
         unsafe { hb_buffer_reverse(self.as_raw()) };
@@ -482,6 +701,67 @@ impl TypedBuffer {
     }
 }
 
+/// Returns the offset of `item` in `context` in units of `T`.
+///
+/// Panics if `item` is not a subslice of `context`.
+fn item_offset<T>(context: &[T], item: &[T]) -> usize {
+    const PANIC_MSG: &str = "`item` must be a substring of `context`";
+    let byte_offset =
+        usize::checked_sub(item.as_ptr() as _, context.as_ptr() as _).expect(PANIC_MSG);
+    let offset = byte_offset / std::mem::size_of::<T>();
+    assert!(offset + item.len() <= context.len(), "{}", PANIC_MSG);
+    offset
+}
+
+/// Converts a UTF-8 byte offset into `text` (e.g. a cluster value of a buffer
+/// filled using `add_str`) into the equivalent offset in UTF-16 code units.
+///
+/// Offsets inside of a character are rounded down to the start of that
+/// character. Offsets past the end map to the end of the text.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::utf8_to_utf16_offset;
+///
+/// let text = "ä😍b";
+/// assert_eq!(utf8_to_utf16_offset(text, 2), 1);
+/// assert_eq!(utf8_to_utf16_offset(text, 6), 3);
+/// ```
+pub fn utf8_to_utf16_offset(text: &str, utf8_offset: usize) -> usize {
+    text.char_indices()
+        .take_while(|&(index, c)| index + c.len_utf8() <= utf8_offset)
+        .map(|(_, c)| c.len_utf16())
+        .sum()
+}
+
+/// Converts an offset in UTF-16 code units (e.g. a cluster value of a buffer
+/// filled using `add_utf16`) into the equivalent UTF-8 byte offset in `text`.
+///
+/// Offsets pointing to the second half of a surrogate pair are rounded down
+/// to the start of that character. Offsets past the end map to the end of
+/// the text.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::utf16_to_utf8_offset;
+///
+/// let text = "ä😍b";
+/// assert_eq!(utf16_to_utf8_offset(text, 1), 2);
+/// assert_eq!(utf16_to_utf8_offset(text, 3), 6);
+/// ```
+pub fn utf16_to_utf8_offset(text: &str, utf16_offset: usize) -> usize {
+    let mut utf16_index = 0;
+    for (index, c) in text.char_indices() {
+        utf16_index += c.len_utf16();
+        if utf16_index > utf16_offset {
+            return index;
+        }
+    }
+    text.len()
+}
+
 /// A `UnicodeBuffer` can be filled with unicode text and corresponding cluster
 /// indices.
 ///
@@ -590,6 +870,28 @@ impl UnicodeBuffer {
         self
     }
 
+    /// Add UTF-8 encoded `bytes` to the `Buffer`'s array of codepoints.
+    ///
+    /// Unlike `add_str` the input does not have to be valid UTF-8. Every
+    /// invalid byte sequence is added as the buffer's replacement codepoint
+    /// (see `set_replacement_codepoint`), with the cluster value of its
+    /// first byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::UnicodeBuffer;
+    ///
+    /// let buffer = UnicodeBuffer::new()
+    ///     .set_replacement_codepoint('?' as u32)
+    ///     .add_utf8_lossy(b"Hello\xffWorld");
+    /// assert_eq!(buffer.string_lossy(), "Hello?World");
+    /// ```
+    pub fn add_utf8_lossy(mut self, bytes: &[u8]) -> UnicodeBuffer {
+        self.0.add_utf8_item(bytes, 0, bytes.len());
+        self
+    }
+
     /// Add a string item to the buffer, providing context.
     ///
     /// Only the `item` string gets added to the buffer and will be shaped.
@@ -623,14 +925,162 @@ impl UnicodeBuffer {
     /// assert_eq!(buffer.string_lossy(), "World");
     /// ```
     pub fn add_str_item(mut self, context: &str, item: &str) -> UnicodeBuffer {
-        const PANIC_MSG: &str = "`item` must be a substring of `context`";
-        let offset =
-            usize::checked_sub(item.as_ptr() as _, context.as_ptr() as _).expect(PANIC_MSG);
-        assert!(offset + item.len() <= context.len(), "{}", PANIC_MSG);
+        let offset = item_offset(context.as_bytes(), item.as_bytes());
         self.0.add_str_item(context, offset, item.len());
         self
     }
 
+    /// Set the text preceding the contents of the buffer.
+    ///
+    /// Like the `context` argument of `add_str_item` this gives the shaper
+    /// information about the surrounding text (e.g. for Arabic joining), but
+    /// the context does not need to be part of the same string as the shaped
+    /// text. Only the last few characters of `context` are used.
+    ///
+    /// The pre-context can only be set while the buffer is empty, i.e. it has
+    /// to be set before any text is added. Note that setting the pre-context
+    /// also clears the post-context, just like adding text does, so it has to
+    /// be called before `set_post_context`.
+    ///
+    /// # Examples
+    ///
+    /// Shape a chunk of text whose surrounding text lives somewhere else.
+    ///
+    /// ```
+    /// use harfbuzz_rs::UnicodeBuffer;
+    ///
+    /// let chunks = ["Hello ", "World", "!"];
+    /// let buffer = UnicodeBuffer::new()
+    ///     .set_pre_context(chunks[0])
+    ///     .add_str(chunks[1])
+    ///     .set_post_context(chunks[2]);
+    /// assert_eq!(buffer.string_lossy(), "World");
+    /// ```
+    pub fn set_pre_context(mut self, context: &str) -> UnicodeBuffer {
+        self.0.set_pre_context_str(context);
+        self
+    }
+
+    /// Set the text following the contents of the buffer.
+    ///
+    /// Only the first few characters of `context` are used. Adding text to
+    /// the buffer replaces the post-context, so this has to be called after
+    /// all text was added.
+    pub fn set_post_context(mut self, context: &str) -> UnicodeBuffer {
+        self.0.set_post_context_str(context);
+        self
+    }
+
+    /// Set the codepoints preceding the contents of the buffer.
+    ///
+    /// See `set_pre_context` for details.
+    pub fn set_pre_context_codepoints(mut self, context: &[u32]) -> UnicodeBuffer {
+        self.0.set_pre_context_codepoints(context);
+        self
+    }
+
+    /// Set the codepoints following the contents of the buffer.
+    ///
+    /// See `set_post_context` for details.
+    pub fn set_post_context_codepoints(mut self, context: &[u32]) -> UnicodeBuffer {
+        self.0.set_post_context_codepoints(context);
+        self
+    }
+
+    /// Add UTF-16 encoded text to the buffer.
+    ///
+    /// The cluster values of the added codepoints are offsets into `text` in
+    /// UTF-16 code units. Unpaired surrogates are added as the buffer's
+    /// replacement codepoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::UnicodeBuffer;
+    ///
+    /// let text: Vec<u16> = "a😍b".encode_utf16().collect();
+    /// let buffer = UnicodeBuffer::new().add_utf16(&text);
+    /// assert_eq!(buffer.string_lossy(), "a😍b");
+    /// ```
+    pub fn add_utf16(mut self, text: &[u16]) -> UnicodeBuffer {
+        self.0.add_utf16_item(text, 0, text.len());
+        self
+    }
+
+    /// Add a UTF-16 encoded item to the buffer, providing context.
+    ///
+    /// This is the UTF-16 equivalent of `add_str_item`. Cluster values are
+    /// offsets into `context` in UTF-16 code units.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `item` is not a subslice of `context`.
+    pub fn add_utf16_item(mut self, context: &[u16], item: &[u16]) -> UnicodeBuffer {
+        let offset = item_offset(context, item);
+        self.0.add_utf16_item(context, offset, item.len());
+        self
+    }
+
+    /// Add UTF-32 encoded text to the buffer.
+    ///
+    /// The cluster values of the added codepoints are indices into `text`.
+    /// Values that are not valid unicode scalar values are added as the
+    /// buffer's replacement codepoint.
+    pub fn add_utf32(mut self, text: &[u32]) -> UnicodeBuffer {
+        self.0.add_utf32_item(text, 0, text.len());
+        self
+    }
+
+    /// Add a UTF-32 encoded item to the buffer, providing context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `item` is not a subslice of `context`.
+    pub fn add_utf32_item(mut self, context: &[u32], item: &[u32]) -> UnicodeBuffer {
+        let offset = item_offset(context, item);
+        self.0.add_utf32_item(context, offset, item.len());
+        self
+    }
+
+    /// Add raw codepoints to the buffer.
+    ///
+    /// Like `add_utf32` but the codepoints are not validated, which allows
+    /// passing through values like lone surrogates unchanged. The cluster
+    /// values are indices into `codepoints`.
+    pub fn add_codepoints(mut self, codepoints: &[u32]) -> UnicodeBuffer {
+        self.0.add_codepoints_item(codepoints, 0, codepoints.len());
+        self
+    }
+
+    /// Add Latin-1 (ISO-8859-1) encoded text to the buffer.
+    ///
+    /// The cluster values of the added codepoints are byte offsets into
+    /// `text`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::UnicodeBuffer;
+    ///
+    /// let buffer = UnicodeBuffer::new().add_latin1(b"caf\xe9");
+    /// assert_eq!(buffer.string_lossy(), "café");
+    /// ```
+    pub fn add_latin1(mut self, text: &[u8]) -> UnicodeBuffer {
+        self.0.add_latin1_item(text, 0, text.len());
+        self
+    }
+
+    /// Add a Latin-1 encoded item to the buffer, providing context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `item` is not a subslice of `context`.
+    pub fn add_latin1_item(mut self, context: &[u8], item: &[u8]) -> UnicodeBuffer {
+        let offset = item_offset(context, item);
+        self.0.add_latin1_item(context, offset, item.len());
+        self
+    }
+
     /// Append codepoints from another `UnicodeBuffer` to the end of `self`.
     ///
     /// # Examples
@@ -761,6 +1211,89 @@ impl UnicodeBuffer {
         self.0.get_cluster_level()
     }
 
+    /// Set the flags that control how the buffer is shaped.
+    ///
+    /// When shaping a piece of a larger text, set `BufferFlags::BOT` and
+    /// `BufferFlags::EOT` if the piece is at the start or end of that text
+    /// respectively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::{BufferFlags, UnicodeBuffer};
+    ///
+    /// let buffer = UnicodeBuffer::new()
+    ///     .add_str("Hello")
+    ///     .set_flags(BufferFlags::BOT | BufferFlags::EOT);
+    /// assert_eq!(buffer.get_flags(), BufferFlags::BOT | BufferFlags::EOT);
+    /// ```
+    pub fn set_flags(mut self, flags: BufferFlags) -> UnicodeBuffer {
+        self.0.set_flags(flags);
+        self
+    }
+
+    /// Retrieve the flags of the buffer.
+    pub fn get_flags(&self) -> BufferFlags {
+        self.0.get_flags()
+    }
+
+    /// Set the unicode funcs that provide the character properties for
+    /// guessing the segment properties and for shaping the buffer.
+    ///
+    /// The unicode funcs should be set before calling
+    /// `guess_segment_properties`. See `unicode_funcs::UnicodeFuncsTrait` for
+    /// an example.
+    pub fn set_unicode_funcs(mut self, funcs: &UnicodeFuncs) -> UnicodeBuffer {
+        self.0.set_unicode_funcs(funcs);
+        self
+    }
+
+    /// Returns the unicode funcs of the buffer.
+    pub fn get_unicode_funcs(&self) -> Shared<UnicodeFuncs> {
+        self.0.get_unicode_funcs()
+    }
+
+    /// Set the codepoint that replaces invalid input when adding text to the
+    /// buffer. The default is U+FFFD REPLACEMENT CHARACTER.
+    ///
+    /// This only affects text that is added after calling this method.
+    pub fn set_replacement_codepoint(mut self, replacement: u32) -> UnicodeBuffer {
+        self.0.set_replacement_codepoint(replacement);
+        self
+    }
+
+    /// Retrieve the codepoint that replaces invalid input.
+    pub fn get_replacement_codepoint(&self) -> u32 {
+        self.0.get_replacement_codepoint()
+    }
+
+    /// Set the glyph that replaces invisible characters (default ignorables
+    /// like ZERO WIDTH JOINER) in the shaping result.
+    ///
+    /// The default value of `0` means the font's space glyph is used, or the
+    /// characters are removed if the font has no space glyph.
+    pub fn set_invisible_glyph(mut self, invisible: Glyph) -> UnicodeBuffer {
+        self.0.set_invisible_glyph(invisible);
+        self
+    }
+
+    /// Retrieve the glyph that replaces invisible characters.
+    pub fn get_invisible_glyph(&self) -> Glyph {
+        self.0.get_invisible_glyph()
+    }
+
+    /// Set the glyph that replaces characters not supported by the font in
+    /// the shaping result. The default is glyph `0` (`.notdef`).
+    pub fn set_not_found_glyph(mut self, not_found: Glyph) -> UnicodeBuffer {
+        self.0.set_not_found_glyph(not_found);
+        self
+    }
+
+    /// Retrieve the glyph that replaces characters not supported by the font.
+    pub fn get_not_found_glyph(&self) -> Glyph {
+        self.0.get_not_found_glyph()
+    }
+
     /// Pre-allocate the buffer to hold a string at least `size` codepoints.
     pub fn pre_allocate(&mut self, size: usize) {
         self.0.pre_allocate(size)
@@ -793,6 +1326,7 @@ impl std::fmt::Debug for UnicodeBuffer {
             .field("language", &self.get_language())
             .field("script", &self.get_script())
             .field("cluster_level", &self.get_cluster_level())
+            .field("flags", &self.get_flags())
             .finish()
     }
 }
@@ -857,6 +1391,120 @@ impl GlyphBuffer {
         self.0.get_glyph_infos()
     }
 
+    /// Get the glyph positions for modification.
+    ///
+    /// # Examples
+    ///
+    /// Add 50 units of letter-spacing after every glyph.
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    /// let mut buffer = shape(&font, UnicodeBuffer::new().add_str("Hello"), &[]);
+    ///
+    /// for position in buffer.get_glyph_positions_mut() {
+    ///     position.x_advance += 50;
+    /// }
+    /// ```
+    pub fn get_glyph_positions_mut(&mut self) -> &mut [GlyphPosition] {
+        self.0.get_glyph_positions_mut()
+    }
+
+    /// Get the glyph infos for modification.
+    pub fn get_glyph_infos_mut(&mut self) -> &mut [GlyphInfo] {
+        self.0.get_glyph_infos_mut()
+    }
+
+    /// Returns an iterator over the glyph infos and their corresponding
+    /// positions that allows modifying both.
+    ///
+    /// # Examples
+    ///
+    /// Widen the spaces of a line for justification.
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    /// let space = font.get_nominal_glyph(' ').unwrap();
+    /// let mut buffer = shape(&font, UnicodeBuffer::new().add_str("a b c"), &[]);
+    ///
+    /// for (info, position) in buffer.glyphs_mut() {
+    ///     if info.codepoint == space {
+    ///         position.x_advance += 100;
+    ///     }
+    /// }
+    /// ```
+    pub fn glyphs_mut(&mut self) -> GlyphsMut<'_> {
+        let (infos, positions) = self.0.get_glyphs_mut();
+        GlyphsMut {
+            iter: infos.iter_mut().zip(positions.iter_mut()),
+        }
+    }
+
+    /// Returns an iterator over the clusters of the shaped text.
+    ///
+    /// Every `ClusterSpan` is a minimal group of glyphs that maps to a
+    /// contiguous range of the original text and vice versa. `text_len` is the
+    /// length of the shaped text in the code units cluster values refer to
+    /// (e.g. bytes when the buffer was filled using `add_str`); it determines
+    /// where the last cluster of the text ends.
+    ///
+    /// Spans are returned in glyph order, i.e. in visual order. For
+    /// right-to-left text the first span contains the end of the text. Glyphs
+    /// that were reordered relative to the text (which can happen with
+    /// `ClusterLevel::Characters`) are kept in the same span as the glyphs
+    /// they were moved across.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    ///
+    /// let text = "after";
+    /// let buffer = shape(&font, UnicodeBuffer::new().add_str(text), &[]);
+    /// let spans: Vec<_> = buffer.clusters(text.len()).collect();
+    ///
+    /// // "ft" is rendered using a ligature
+    /// assert_eq!(spans.len(), 4);
+    /// assert_eq!(spans[1].text_range, 1..3);
+    /// assert_eq!(spans[1].glyph_range, 1..2);
+    /// ```
+    pub fn clusters(&self, text_len: usize) -> Clusters<'_> {
+        let infos = self.get_glyph_infos();
+        let direction = self.0.get_direction();
+        let (backward, vertical) = (direction.is_backward(), direction.is_vertical());
+        // For every glyph the smallest (largest if backward) cluster of the
+        // glyph and all glyphs following it.
+        let mut following: Vec<u32> = infos.iter().map(|info| info.cluster).collect();
+        for i in (1..following.len()).rev() {
+            following[i - 1] = if backward {
+                following[i - 1].max(following[i])
+            } else {
+                following[i - 1].min(following[i])
+            };
+        }
+        Clusters {
+            infos,
+            positions: self.get_glyph_positions(),
+            following,
+            backward,
+            vertical,
+            text_len,
+            next_glyph: 0,
+            previous_start: None,
+        }
+    }
+
     /// Reverse the `Buffer`'s contents.
     pub fn reverse(&mut self) {
         self.0.reverse()
@@ -874,6 +1522,283 @@ impl GlyphBuffer {
         UnicodeBuffer::from_generic(self.0)
     }
 
+    /// Splits the buffer into the glyphs before and after the text position
+    /// `cluster`, if it is safe to break the text there.
+    ///
+    /// The first buffer contains the glyphs of the text before `cluster`, the
+    /// second one the glyphs of the text after it, each in visual order.
+    /// Returns `None` if `cluster` is not the start of a cluster or if the
+    /// text must be reshaped when broken at `cluster` (see
+    /// `GlyphFlags::unsafe_to_break`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    /// let buffer = shape(&font, UnicodeBuffer::new().add_str("Hello World"), &[]);
+    ///
+    /// let (hello, world) = buffer.split_at_safe(6).unwrap();
+    /// assert_eq!(hello.len(), 6);
+    /// assert_eq!(world.len(), 5);
+    /// assert_eq!(world.get_glyph_infos()[0].cluster, 6);
+    /// ```
+    pub fn split_at_safe(&self, cluster: u32) -> Option<(GlyphBuffer, GlyphBuffer)> {
+        let backward = self.is_backward();
+        let infos = self.get_glyph_infos();
+        let index = safe_split_index(infos, cluster, backward, |flags| flags.unsafe_to_break())?;
+        let first = self.copy_glyphs(0..index);
+        let second = self.copy_glyphs(index..infos.len());
+        if backward {
+            Some((second, first))
+        } else {
+            Some((first, second))
+        }
+    }
+
+    /// Returns the result of shaping `text[range]`, reusing as much of this
+    /// buffer as possible.
+    ///
+    /// `self` must be the result of shaping all of `text` (e.g. a paragraph)
+    /// using `font` and `features`, such that its cluster values are byte
+    /// offsets into `text`. The returned buffer is the same as the result of
+    /// shaping `range` (e.g. a line of the paragraph) with `text` as context:
+    ///
+    /// ```ignore
+    /// let buffer = UnicodeBuffer::new().add_str_item(text, &text[range]);
+    /// shape(font, buffer, features)
+    /// ```
+    ///
+    /// Only the glyphs at the edges of `range` are reshaped, up to the first
+    /// cluster where the text can be split without changing the result. This
+    /// requires `self` to be shaped with `BufferFlags::PRODUCE_UNSAFE_TO_CONCAT`,
+    /// otherwise the whole range is reshaped. Segment properties, cluster level
+    /// and flags are taken from `self`.
+    ///
+    /// Use `reshape_range_verified` to compare the result to shaping the
+    /// whole range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or does not lie on character
+    /// boundaries of `text`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    ///
+    /// let text = "A paragraph of text";
+    /// let buffer = UnicodeBuffer::new()
+    ///     .add_str(text)
+    ///     .set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+    /// let paragraph = shape(&font, buffer, &[]);
+    ///
+    /// let line = paragraph.reshape_range(&font, text, 2..11, &[]);
+    /// assert_eq!(line.len(), 9);
+    /// assert_eq!(line.get_glyph_infos()[0].cluster, 2);
+    /// ```
+    pub fn reshape_range(
+        &self,
+        font: &crate::Font<'_>,
+        text: &str,
+        range: std::ops::Range<usize>,
+        features: &[Feature],
+    ) -> GlyphBuffer {
+        assert!(text.is_char_boundary(range.start) && text.is_char_boundary(range.end));
+        assert!(range.start <= range.end);
+        let backward = self.is_backward();
+        let infos = self.get_glyph_infos();
+        if !self
+            .0
+            .get_flags()
+            .contains(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT)
+        {
+            return self.shape_item(font, text, range, features);
+        }
+        let is_unsafe = |flags: GlyphFlags| flags.unsafe_to_break() || flags.unsafe_to_concat();
+        // The index of the glyph at `cluster` if the paragraph can be split
+        // there. The ends of the paragraph are always safe, but an offset
+        // inside its first or last cluster is not.
+        let paragraph_split = |cluster: usize| {
+            let index = partition_index(infos, cluster as u32, backward)?;
+            let is_cluster_start = cluster == 0
+                || cluster == text.len()
+                || infos.iter().any(|info| info.cluster as usize == cluster);
+            if is_cluster_start && (index == 0 || index == infos.len()) {
+                Some(index)
+            } else {
+                safe_split_index(infos, cluster as u32, backward, is_unsafe)
+            }
+        };
+        let mut cluster_starts: Vec<usize> = infos
+            .iter()
+            .map(|info| info.cluster as usize)
+            .filter(|cluster| range.contains(cluster))
+            .chain(vec![range.start, range.end])
+            .collect();
+        cluster_starts.sort();
+        cluster_starts.dedup();
+
+        // Find the start of the reusable glyphs and shape the text before it.
+        // The shaped text extends one character past that start, so that the
+        // flags can be checked at the position where the glyphs are joined.
+        let mut head = None;
+        for &start in &cluster_starts {
+            let split_index = match paragraph_split(start) {
+                Some(split_index) => split_index,
+                None => continue,
+            };
+            if start == range.start {
+                head = Some((start, split_index, None));
+                break;
+            }
+            let next_char = text[start..].chars().next().map_or(0, char::len_utf8);
+            let run = self.shape_item(font, text, range.start..start + next_char, features);
+            let run_infos = run.get_glyph_infos();
+            if let Some(index) = safe_split_index(run_infos, start as u32, backward, is_unsafe) {
+                let glyphs = if backward { index..run.len() } else { 0..index };
+                head = Some((start, split_index, Some((run, glyphs))));
+                break;
+            }
+        }
+        let (middle_start, start_index, head) = match head {
+            Some(head) => head,
+            None => return self.shape_item(font, text, range, features),
+        };
+
+        // Same for the end of the reusable glyphs and the text after it.
+        let mut tail = None;
+        for &end in cluster_starts
+            .iter()
+            .rev()
+            .take_while(|&&end| end >= middle_start)
+        {
+            let split_index = match paragraph_split(end) {
+                Some(split_index) => split_index,
+                None => continue,
+            };
+            if end == range.end {
+                tail = Some((split_index, None));
+                break;
+            }
+            let previous_char = text[..end].chars().next_back().map_or(0, char::len_utf8);
+            let run = self.shape_item(font, text, end - previous_char..range.end, features);
+            let run_infos = run.get_glyph_infos();
+            if let Some(index) = safe_split_index(run_infos, end as u32, backward, is_unsafe) {
+                let glyphs = if backward { 0..index } else { index..run.len() };
+                tail = Some((split_index, Some((run, glyphs))));
+                break;
+            }
+        }
+        let (end_index, tail) = match tail {
+            Some(tail) => tail,
+            None => return self.shape_item(font, text, range, features),
+        };
+
+        let middle = if backward {
+            end_index..start_index
+        } else {
+            start_index..end_index
+        };
+        let mut pieces: Vec<(&GenericBuffer, std::ops::Range<usize>)> = Vec::new();
+        if let Some((run, glyphs)) = &head {
+            pieces.push((&*run.0, glyphs.clone()));
+        }
+        pieces.push((&*self.0, middle));
+        if let Some((run, glyphs)) = &tail {
+            pieces.push((&*run.0, glyphs.clone()));
+        }
+        if backward {
+            pieces.reverse();
+        }
+        let mut line = self.empty_like();
+        line.set_content_type(HB_BUFFER_CONTENT_TYPE_GLYPHS);
+        for (buffer, glyphs) in pieces {
+            line.append(buffer, glyphs.start as c_uint, glyphs.end as c_uint);
+        }
+        GlyphBuffer(line)
+    }
+
+    /// Like `reshape_range`, but also shapes the whole range from scratch and
+    /// compares the results. This is slow and only meant for debugging.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or does not lie on character
+    /// boundaries of `text`, or if the results differ.
+    pub fn reshape_range_verified(
+        &self,
+        font: &crate::Font<'_>,
+        text: &str,
+        range: std::ops::Range<usize>,
+        features: &[Feature],
+    ) -> GlyphBuffer {
+        let line = self.reshape_range(font, text, range.clone(), features);
+        let reference = self.shape_item(font, text, range.clone(), features);
+        let same_glyphs = line.len() == reference.len()
+            && line
+                .get_glyph_infos()
+                .iter()
+                .zip(reference.get_glyph_infos())
+                .all(|(a, b)| (a.codepoint, a.cluster) == (b.codepoint, b.cluster))
+            && line
+                .get_glyph_positions()
+                .iter()
+                .zip(reference.get_glyph_positions())
+                .all(|(a, b)| {
+                    (a.x_advance, a.y_advance, a.x_offset, a.y_offset)
+                        == (b.x_advance, b.y_advance, b.x_offset, b.y_offset)
+                });
+        assert!(
+            same_glyphs,
+            "reshaping {:?} differs from shaping it from scratch:\n{:?}\n{:?}",
+            range, line, reference
+        );
+        line
+    }
+
+    fn is_backward(&self) -> bool {
+        self.0.get_direction().is_backward()
+    }
+
+    /// Creates an empty buffer with the same settings as `self`.
+    fn empty_like(&self) -> Owned<GenericBuffer> {
+        let mut buffer = GenericBuffer::new();
+        buffer.set_segment_properties(self.0.get_segment_properties());
+        buffer.set_cluster_level(self.0.get_cluster_level());
+        buffer.set_flags(self.0.get_flags());
+        buffer.set_replacement_codepoint(self.0.get_replacement_codepoint());
+        buffer.set_invisible_glyph(self.0.get_invisible_glyph());
+        buffer.set_not_found_glyph(self.0.get_not_found_glyph());
+        buffer
+    }
+
+    fn copy_glyphs(&self, glyphs: std::ops::Range<usize>) -> GlyphBuffer {
+        let mut buffer = self.empty_like();
+        buffer.set_content_type(HB_BUFFER_CONTENT_TYPE_GLYPHS);
+        buffer.append(&self.0, glyphs.start as c_uint, glyphs.end as c_uint);
+        GlyphBuffer(buffer)
+    }
+
+    fn shape_item(
+        &self,
+        font: &crate::Font<'_>,
+        text: &str,
+        item: std::ops::Range<usize>,
+        features: &[Feature],
+    ) -> GlyphBuffer {
+        let buffer = UnicodeBuffer::from_generic(self.empty_like()).add_str_item(text, &text[item]);
+        crate::shape(font, buffer, features)
+    }
+
     /// Returns a serializer that allows the contents of the buffer to be
     /// converted into a human or machine readable representation.
     ///
@@ -930,6 +1855,160 @@ impl GlyphBuffer {
     }
 }
 
+/// An iterator over mutable references to the glyph infos and positions of a
+/// `GlyphBuffer`.
+///
+/// You get an iterator of this type from the `.glyphs_mut()` method on
+/// `GlyphBuffer`.
+#[derive(Debug)]
+pub struct GlyphsMut<'a> {
+    iter:
+        std::iter::Zip<std::slice::IterMut<'a, GlyphInfo>, std::slice::IterMut<'a, GlyphPosition>>,
+}
+
+impl<'a> Iterator for GlyphsMut<'a> {
+    type Item = (&'a mut GlyphInfo, &'a mut GlyphPosition);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for GlyphsMut<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl<'a> ExactSizeIterator for GlyphsMut<'a> {}
+
+/// Returns the index of the first glyph (in glyph order) that belongs to the
+/// text after `cluster`, if the glyphs of the text before and after `cluster`
+/// are not interleaved.
+fn partition_index(infos: &[GlyphInfo], cluster: u32, backward: bool) -> Option<usize> {
+    // in backward buffers the text after `cluster` comes first
+    let is_second = |info: &GlyphInfo| (info.cluster >= cluster) != backward;
+    let index = infos
+        .iter()
+        .position(is_second)
+        .unwrap_or_else(|| infos.len());
+    if infos[index..].iter().all(is_second) {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// Returns the index where the glyphs can be split at the cluster starting at
+/// `cluster`, if there is such a cluster and none of its glyphs are unsafe
+/// according to `is_unsafe`.
+fn safe_split_index(
+    infos: &[GlyphInfo],
+    cluster: u32,
+    backward: bool,
+    is_unsafe: impl Fn(GlyphFlags) -> bool,
+) -> Option<usize> {
+    let mut cluster_glyphs = infos
+        .iter()
+        .filter(|info| info.cluster == cluster)
+        .peekable();
+    cluster_glyphs.peek()?;
+    if cluster_glyphs.any(|info| is_unsafe(info.glyph_flags())) {
+        return None;
+    }
+    partition_index(infos, cluster, backward)
+}
+
+/// A group of glyphs and the part of the text they represent.
+///
+/// See `GlyphBuffer::clusters` for details.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClusterSpan {
+    /// The range of the text, in the code units used for the cluster values.
+    pub text_range: std::ops::Range<usize>,
+    /// The range of the glyphs in the `GlyphBuffer`.
+    pub glyph_range: std::ops::Range<usize>,
+    /// The sum of the advances of the glyphs in the direction of the text.
+    pub advance: Position,
+}
+
+/// An iterator over the clusters of a `GlyphBuffer`.
+///
+/// You get an iterator of this type from the `.clusters()` method on
+/// `GlyphBuffer`.
+#[derive(Debug, Clone)]
+pub struct Clusters<'a> {
+    infos: &'a [GlyphInfo],
+    positions: &'a [GlyphPosition],
+    following: Vec<u32>,
+    backward: bool,
+    vertical: bool,
+    text_len: usize,
+    next_glyph: usize,
+    // The text start of the previously returned span.
+    previous_start: Option<u32>,
+}
+
+impl<'a> Iterator for Clusters<'a> {
+    type Item = ClusterSpan;
+
+    fn next(&mut self) -> Option<ClusterSpan> {
+        let start = self.next_glyph;
+        if start >= self.infos.len() {
+            return None;
+        }
+        let mut min_cluster = u32::max_value();
+        let mut max_cluster = 0;
+        let mut end = start;
+        // Extend the span until all clusters of the span come before (after
+        // if backward) the clusters of all remaining glyphs.
+        loop {
+            let cluster = self.infos[end].cluster;
+            min_cluster = min_cluster.min(cluster);
+            max_cluster = max_cluster.max(cluster);
+            end += 1;
+            let at_boundary = match self.following.get(end) {
+                None => true,
+                Some(&next) if self.backward => min_cluster > next,
+                Some(&next) => max_cluster < next,
+            };
+            if at_boundary {
+                break;
+            }
+        }
+
+        // The span ends where the logically next span starts.
+        let next_start = if self.backward {
+            self.previous_start
+        } else {
+            self.following.get(end).cloned()
+        };
+        let text_end = next_start.map_or(self.text_len, |next| next as usize);
+        let advance = self.positions[start..end]
+            .iter()
+            .map(|pos| {
+                if self.vertical {
+                    pos.y_advance
+                } else {
+                    pos.x_advance
+                }
+            })
+            .sum();
+
+        self.next_glyph = end;
+        self.previous_start = Some(min_cluster);
+        Some(ClusterSpan {
+            text_range: min_cluster as usize..text_end,
+            glyph_range: start..end,
+            advance,
+        })
+    }
+}
+
 impl fmt::Debug for GlyphBuffer {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("GlyphBuffer")
@@ -952,10 +2031,13 @@ impl fmt::Display for GlyphBuffer {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Range;
+
     use harfbuzz_bindings::hb_glyph_position_t;
 
-    use crate::{Face, Font, shape};
+    use crate::test_util::identity_font;
     use crate::tests::assert_memory_layout_equal;
+    use crate::{shape, Face, Font};
 
     use super::*;
 
@@ -991,6 +2073,459 @@ mod tests {
         UnicodeBuffer::new().add_str_item(&string[4..], &string[0..5]);
     }
 
+    #[test]
+    fn test_buffer_flags() {
+        let buffer = UnicodeBuffer::new();
+        assert_eq!(buffer.get_flags(), BufferFlags::empty());
+
+        let flags = BufferFlags::BOT | BufferFlags::EOT | BufferFlags::PRODUCE_UNSAFE_TO_CONCAT;
+        let buffer = buffer.add_str("Test").set_flags(flags);
+        assert_eq!(buffer.get_flags(), flags);
+
+        // clearing the contents keeps the flags
+        let buffer = buffer.clear_contents();
+        assert_eq!(buffer.get_flags(), flags);
+    }
+
+    #[test]
+    fn test_add_utf8_lossy() {
+        let buffer = UnicodeBuffer::new().add_utf8_lossy(b"a\xffb\x80");
+        assert_eq!(buffer.string_lossy(), "a\u{FFFD}b\u{FFFD}");
+        let clusters: Vec<_> = buffer
+            .0
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.cluster)
+            .collect();
+        assert_eq!(clusters, vec![0, 1, 2, 3]);
+
+        let buffer = buffer
+            .clear_contents()
+            .set_replacement_codepoint('?' as u32)
+            .add_utf8_lossy("ä".as_bytes())
+            .add_utf8_lossy(&[0x80]);
+        assert_eq!(buffer.get_replacement_codepoint(), '?' as u32);
+        assert_eq!(buffer.string_lossy(), "ä?");
+    }
+
+    #[test]
+    fn test_arabic_joining_across_chunks() {
+        const BEH: &str = "\u{0628}";
+        const ISOLATED: u32 = 0xFE8F;
+        const FINAL: u32 = 0xFE90;
+        const INITIAL: u32 = 0xFE91;
+        const MEDIAL: u32 = 0xFE92;
+
+        // Arabic text shaped with the identity font goes through HarfBuzz's
+        // fallback shaping, so the output glyphs are the presentation forms of
+        // the joining forms.
+        let font = identity_font();
+        let shape_chunk = |buffer: UnicodeBuffer| -> Vec<u32> {
+            let output = shape(&font, buffer.guess_segment_properties(), &[]);
+            output
+                .get_glyph_infos()
+                .iter()
+                .map(|info| info.codepoint)
+                .collect()
+        };
+        let with_context = |pre: &str, chunk: &str, post: &str| {
+            shape_chunk(
+                UnicodeBuffer::new()
+                    .set_pre_context(pre)
+                    .add_str(chunk)
+                    .set_post_context(post),
+            )
+        };
+
+        assert_eq!(with_context("", BEH, ""), vec![ISOLATED]);
+        assert_eq!(with_context(BEH, BEH, ""), vec![FINAL]);
+        assert_eq!(with_context("", BEH, BEH), vec![INITIAL]);
+        assert_eq!(with_context(BEH, BEH, BEH), vec![MEDIAL]);
+
+        // glyphs are in visual (right-to-left) order
+        let chunk = [BEH, BEH].concat();
+        assert_eq!(with_context(BEH, &chunk, "."), vec![FINAL, MEDIAL]);
+
+        let beh = [0x0628];
+        let buffer = UnicodeBuffer::new()
+            .set_pre_context_codepoints(&beh)
+            .add_str(BEH)
+            .set_post_context_codepoints(&beh);
+        assert_eq!(shape_chunk(buffer), vec![MEDIAL]);
+    }
+
+    fn shape_with_level(font: &Font<'_>, text: &str, level: ClusterLevel) -> GlyphBuffer {
+        let buffer = UnicodeBuffer::new()
+            .add_str(text)
+            .set_cluster_level(level)
+            .guess_segment_properties();
+        shape(font, buffer, &[])
+    }
+
+    fn spans(buffer: &GlyphBuffer, text_len: usize) -> Vec<(Range<usize>, Range<usize>)> {
+        buffer
+            .clusters(text_len)
+            .map(|span| (span.text_range, span.glyph_range))
+            .collect()
+    }
+
+    #[test]
+    fn test_clusters_latin_ligatures() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+
+        // "fft" is a ligature in Source Sans
+        let text = "offtake";
+        let glyph_buffer = shape_with_level(&font, text, ClusterLevel::MonotoneGraphemes);
+        assert_eq!(
+            spans(&glyph_buffer, text.len()),
+            vec![
+                (0..1, 0..1),
+                (1..4, 1..2),
+                (4..5, 2..3),
+                (5..6, 3..4),
+                (6..7, 4..5)
+            ]
+        );
+        let advances: Vec<_> = glyph_buffer
+            .clusters(text.len())
+            .map(|s| s.advance)
+            .collect();
+        let positions = glyph_buffer.get_glyph_positions();
+        assert!(advances
+            .iter()
+            .zip(positions)
+            .all(|(a, pos)| *a == pos.x_advance));
+
+        // the ligature stays in one span at every cluster level
+        for &level in &[ClusterLevel::MonotoneCharacters, ClusterLevel::Characters] {
+            let glyph_buffer = shape_with_level(&font, text, level);
+            assert_eq!(spans(&glyph_buffer, text.len())[1], (1..4, 1..2));
+        }
+    }
+
+    #[test]
+    fn test_clusters_arabic() {
+        let font = identity_font();
+
+        // beh, lam, alef: lam-alef forms a ligature
+        let text = "\u{0628}\u{0644}\u{0627}";
+        for &level in &[
+            ClusterLevel::MonotoneGraphemes,
+            ClusterLevel::MonotoneCharacters,
+            ClusterLevel::Characters,
+        ] {
+            let glyph_buffer = shape_with_level(&font, text, level);
+            let infos = glyph_buffer.get_glyph_infos();
+            let glyphs: Vec<_> = infos.iter().map(|i| i.codepoint).collect();
+            assert_eq!(glyphs, vec![0xFEFC, 0xFE91]);
+            // spans are in visual order, so the end of the text comes first
+            assert_eq!(
+                spans(&glyph_buffer, text.len()),
+                vec![(2..6, 0..1), (0..2, 1..2)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_clusters_devanagari() {
+        let font = identity_font();
+
+        // ka, vowel sign i: the pre-base matra gets reordered before the ka
+        let text = "\u{0915}\u{093F}\u{0916}";
+        let glyph_buffer = shape_with_level(&font, text, ClusterLevel::MonotoneGraphemes);
+        let glyphs: Vec<_> = glyph_buffer
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.codepoint)
+            .collect();
+        assert_eq!(glyphs, vec![0x093F, 0x0915, 0x0916]);
+        assert_eq!(
+            spans(&glyph_buffer, text.len()),
+            vec![(0..6, 0..2), (6..9, 2..3)]
+        );
+
+        // clusters are not merged, but the reordered glyphs share a span
+        let glyph_buffer = shape_with_level(&font, text, ClusterLevel::Characters);
+        let clusters: Vec<_> = glyph_buffer
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.cluster)
+            .collect();
+        assert_eq!(clusters, vec![3, 0, 6]);
+        assert_eq!(
+            spans(&glyph_buffer, text.len()),
+            vec![(0..6, 0..2), (6..9, 2..3)]
+        );
+    }
+
+    #[test]
+    fn test_clusters_empty() {
+        let glyph_buffer = GlyphBuffer(GenericBuffer::new());
+        assert_eq!(glyph_buffer.clusters(0).count(), 0);
+    }
+
+    fn glyphs(buffer: &GlyphBuffer) -> Vec<(u32, u32, Position, Position)> {
+        buffer
+            .get_glyph_infos()
+            .iter()
+            .zip(buffer.get_glyph_positions())
+            .map(|(info, pos)| (info.codepoint, info.cluster, pos.x_advance, pos.x_offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_at_safe() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+        let glyph_buffer = shape(&font, UnicodeBuffer::new().add_str("after AVA"), &[]);
+
+        let (first, second) = glyph_buffer.split_at_safe(6).unwrap();
+        assert_eq!(first.len() + second.len(), glyph_buffer.len());
+        let all = glyphs(&glyph_buffer);
+        assert_eq!(glyphs(&first), all[..first.len()]);
+        assert_eq!(glyphs(&second), all[first.len()..]);
+
+        // inside of the "ft" ligature
+        assert!(glyph_buffer.split_at_safe(2).is_none());
+        // between a kerning pair
+        assert!(glyph_buffer.split_at_safe(7).is_none());
+
+        let (empty, everything) = glyph_buffer.split_at_safe(0).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(glyphs(&everything), all);
+    }
+
+    #[test]
+    fn test_split_at_safe_rtl() {
+        let font = identity_font();
+        // two words, the space between them is a safe break
+        let text = "\u{0628}\u{0628} \u{0628}\u{0628}";
+        let buffer = UnicodeBuffer::new()
+            .add_str(text)
+            .guess_segment_properties();
+        let glyph_buffer = shape(&font, buffer, &[]);
+
+        let (first, second) = glyph_buffer.split_at_safe(4).unwrap();
+        let clusters = |buffer: &GlyphBuffer| -> Vec<u32> {
+            buffer.get_glyph_infos().iter().map(|i| i.cluster).collect()
+        };
+        assert_eq!(clusters(&first), vec![2, 0]);
+        assert_eq!(clusters(&second), vec![7, 5, 4]);
+        // between two joining letters
+        assert!(glyph_buffer.split_at_safe(7).is_none());
+    }
+
+    fn check_reshape_ranges(font: &Font<'_>, text: &str, direction: Direction) {
+        let buffer = UnicodeBuffer::new()
+            .add_str(text)
+            .set_direction(direction)
+            .guess_segment_properties()
+            .set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+        let paragraph = shape(font, buffer, &[]);
+        let boundaries: Vec<_> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(text.len()))
+            .collect();
+        for (i, &start) in boundaries.iter().enumerate() {
+            for &end in &boundaries[i..] {
+                // panics if the result differs from shaping the range
+                let line = paragraph.reshape_range_verified(font, text, start..end, &[]);
+                let expected = UnicodeBuffer::new()
+                    .add_str_item(text, &text[start..end])
+                    .set_direction(direction)
+                    .guess_segment_properties();
+                let expected = shape(font, expected, &[]);
+                assert_eq!(glyphs(&line), glyphs(&expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_reshape_range() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+        check_reshape_ranges(&font, "The AVAV offtake, after all.", Direction::Ltr);
+        // ranges starting or ending inside the trailing "ft" ligature
+        check_reshape_ranges(&font, "aft", Direction::Ltr);
+    }
+
+    #[test]
+    fn test_reshape_range_rtl() {
+        let font = identity_font();
+        let text = "\u{0628}\u{0628}\u{0628} \u{0628}\u{0644}\u{0627}\u{0628}";
+        check_reshape_ranges(&font, text, Direction::Rtl);
+        // ranges starting or ending inside the trailing lam-alef ligature
+        check_reshape_ranges(&font, "\u{0628}\u{0644}\u{0627}", Direction::Rtl);
+    }
+
+    #[test]
+    fn test_reshape_range_without_concat_flags() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+        let text = "Hello AVAV";
+        let paragraph = shape(&font, UnicodeBuffer::new().add_str(text), &[]);
+        let line = paragraph.reshape_range(&font, text, 4..8, &[]);
+        let expected = shape(
+            &font,
+            UnicodeBuffer::new().add_str_item(text, &text[4..8]),
+            &[],
+        );
+        assert_eq!(glyphs(&line), glyphs(&expected));
+    }
+
+    #[test]
+    fn test_utf16_utf32_latin1_clusters() {
+        let text = "a😍b";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let buffer = UnicodeBuffer::new().add_utf16_item(&utf16, &utf16[1..]);
+        assert_eq!(buffer.string_lossy(), "😍b");
+        let clusters: Vec<_> = buffer
+            .0
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.cluster)
+            .collect();
+        assert_eq!(clusters, vec![1, 3]);
+
+        let buffer = buffer.clear_contents().add_utf16(&[0x61, 0xD800, 0x62]);
+        assert_eq!(buffer.string_lossy(), "a\u{FFFD}b");
+
+        let utf32: Vec<u32> = text.chars().map(|c| c as u32).collect();
+        let buffer = buffer.clear_contents().add_utf32_item(&utf32, &utf32[2..]);
+        assert_eq!(buffer.string_lossy(), "b");
+        assert_eq!(buffer.0.get_glyph_infos()[0].cluster, 2);
+
+        let buffer = buffer.clear_contents().add_utf32(&[0x61, 0xD800]);
+        assert_eq!(buffer.codepoints().collect::<Vec<_>>(), vec![0x61, 0xFFFD]);
+        let buffer = buffer.clear_contents().add_codepoints(&[0x61, 0xD800]);
+        assert_eq!(buffer.codepoints().collect::<Vec<_>>(), vec![0x61, 0xD800]);
+
+        let latin1 = b"\xe0 la";
+        let buffer = buffer
+            .clear_contents()
+            .add_latin1_item(latin1, &latin1[2..]);
+        assert_eq!(buffer.string_lossy(), "la");
+        assert_eq!(buffer.0.get_glyph_infos()[0].cluster, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be a substring of")]
+    fn test_utf16_item_not_subslice() {
+        let text = [0x61u16, 0x62, 0x63];
+        UnicodeBuffer::new().add_utf16_item(&text[1..], &text[..2]);
+    }
+
+    #[test]
+    fn test_offset_conversion() {
+        let text = "aä😍b";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        for (index, c) in text.char_indices() {
+            let utf16_offset = utf8_to_utf16_offset(text, index);
+            assert_eq!(utf16_offset, text[..index].encode_utf16().count());
+            assert_eq!(utf16_to_utf8_offset(text, utf16_offset), index);
+            assert_eq!(
+                utf8_to_utf16_offset(text, index + c.len_utf8() - 1),
+                utf16_offset
+            );
+        }
+        assert_eq!(utf8_to_utf16_offset(text, text.len()), utf16.len());
+        assert_eq!(utf16_to_utf8_offset(text, utf16.len()), text.len());
+        // the second half of the surrogate pair belongs to the emoji
+        assert_eq!(utf16_to_utf8_offset(text, 3), 3);
+    }
+
+    #[test]
+    fn test_invisible_and_not_found_glyphs() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+        let space = font.get_nominal_glyph(' ').unwrap();
+
+        let buffer = UnicodeBuffer::new().add_str("a\u{2060}\u{10FFFD}");
+        assert_eq!(buffer.get_invisible_glyph(), 0);
+        assert_eq!(buffer.get_not_found_glyph(), 0);
+        let glyph_buffer = shape(&font, buffer, &[]);
+        let glyphs: Vec<_> = glyph_buffer
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.codepoint)
+            .collect();
+        assert_eq!(glyphs[1..], [space, 0]);
+
+        let buffer = glyph_buffer
+            .clear()
+            .set_invisible_glyph(3)
+            .set_not_found_glyph(4)
+            .add_str("a\u{2060}\u{10FFFD}");
+        assert_eq!(buffer.get_invisible_glyph(), 3);
+        assert_eq!(buffer.get_not_found_glyph(), 4);
+        let glyph_buffer = shape(&font, buffer, &[]);
+        let glyphs: Vec<_> = glyph_buffer
+            .get_glyph_infos()
+            .iter()
+            .map(|i| i.codepoint)
+            .collect();
+        assert_eq!(glyphs[1..], [3, 4]);
+    }
+
+    #[test]
+    fn test_unsafe_to_concat_flags() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+
+        let buffer = UnicodeBuffer::new()
+            .add_str("Kerning AVAV office")
+            .set_flags(BufferFlags::BOT | BufferFlags::EOT | BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+        let glyph_buffer = shape(&font, buffer, &[]);
+        let infos = glyph_buffer.get_glyph_infos();
+        assert!(infos
+            .iter()
+            .any(|info| info.glyph_flags().unsafe_to_concat()));
+        for info in infos {
+            let flags = info.glyph_flags();
+            assert!(!flags.unsafe_to_break() || flags.unsafe_to_concat());
+        }
+    }
+
+    #[test]
+    fn test_mutable_glyph_access() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        let font = Font::new(face);
+        let mut glyph_buffer = shape(&font, UnicodeBuffer::new().add_str("Hello"), &[]);
+        let original: Vec<_> = glyph_buffer
+            .get_glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .collect();
+
+        for position in glyph_buffer.get_glyph_positions_mut() {
+            position.x_advance += 10;
+        }
+        for info in glyph_buffer.get_glyph_infos_mut() {
+            info.cluster *= 2;
+        }
+        assert_eq!(glyph_buffer.glyphs_mut().len(), 5);
+        for (info, position) in glyph_buffer.glyphs_mut().rev() {
+            position.y_offset = info.cluster as Position;
+        }
+
+        let positions = glyph_buffer.get_glyph_positions();
+        let infos = glyph_buffer.get_glyph_infos();
+        for (i, (info, position)) in infos.iter().zip(positions).enumerate() {
+            assert_eq!(info.cluster, 2 * i as u32);
+            assert_eq!(position.x_advance, original[i] + 10);
+            assert_eq!(position.y_offset, 2 * i as Position);
+        }
+    }
+
     #[test]
     fn test_glyph_buffer_serialization_single_char() {
         let path = "testfiles/SourceSansVariable-Roman.ttf";
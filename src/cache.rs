@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::os::raw::c_uint;
+use std::rc::Rc;
+
+use harfbuzz_bindings::HB_BUFFER_CONTENT_TYPE_GLYPHS;
+
+use crate::buffer::{
+    BufferFlags, GenericBuffer, GlyphBuffer, GlyphInfo, SegmentProperties, UnicodeBuffer,
+};
+use crate::font::{Font, FontIdentity};
+use crate::{shape, Feature};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    font: FontIdentity,
+    text: String,
+    props: SegmentProperties,
+    // The tag, value, start and end of each feature.
+    features: Vec<(u32, u32, usize, usize)>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    glyphs: Rc<GlyphBuffer>,
+    last_used: u64,
+}
+
+/// A cache of shaping results that holds a bounded number of entries.
+///
+/// Results are keyed by the identity of the font (see [`Font::identity()`]),
+/// which includes its scale and variations, the shaped text, its segment
+/// properties and the features. When the cache is full the least recently
+/// used entry is removed.
+///
+/// Use [`shape_cached`] to shape longer texts using cached results for each
+/// of their words.
+#[derive(Debug)]
+pub struct ShapeCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CacheEntry>,
+    // The keys of all entries by the time they were last used.
+    recency: BTreeMap<u64, CacheKey>,
+    clock: u64,
+}
+
+impl ShapeCache {
+    /// Creates an empty cache that holds at most `capacity` entries.
+    pub fn new(capacity: usize) -> ShapeCache {
+        ShapeCache {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// The maximum number of entries in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Shapes `text` as a single item or returns the cached result of doing
+    /// so.
+    ///
+    /// The cluster values of the result are byte offsets into `text` and the
+    /// ranges of `features` are relative to `text`. The text is shaped with
+    /// `BufferFlags::PRODUCE_UNSAFE_TO_CONCAT`.
+    pub fn shape(
+        &mut self,
+        font: &Font<'_>,
+        text: &str,
+        props: SegmentProperties,
+        features: &[Feature],
+    ) -> Rc<GlyphBuffer> {
+        let key = CacheKey {
+            font: font.identity(),
+            text: text.to_owned(),
+            props,
+            features: features
+                .iter()
+                .map(|feature| {
+                    (
+                        feature.tag().0,
+                        feature.value(),
+                        feature.start(),
+                        feature.end(),
+                    )
+                })
+                .collect(),
+        };
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = self.clock;
+            self.recency.insert(self.clock, key);
+            return entry.glyphs.clone();
+        }
+
+        let mut buffer = UnicodeBuffer::new()
+            .add_str(text)
+            .set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+        buffer.0.set_segment_properties(props);
+        let glyphs = Rc::new(shape(font, buffer, features));
+        if self.capacity == 0 {
+            return glyphs;
+        }
+        while self.entries.len() >= self.capacity {
+            let oldest = *self.recency.keys().next().unwrap();
+            let oldest_key = self.recency.remove(&oldest).unwrap();
+            self.entries.remove(&oldest_key);
+        }
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                glyphs: glyphs.clone(),
+                last_used: self.clock,
+            },
+        );
+        glyphs
+    }
+}
+
+/// Shapes `text` like `shape` does, reusing the results of shaping its words
+/// from `cache`.
+///
+/// The text is split after every sequence of spaces and the pieces are shaped
+/// separately using `ShapeCache::shape`. The glyphs of adjacent pieces are
+/// only concatenated if none of the glyphs at their boundary have the
+/// `unsafe_to_concat` flag. Otherwise the pieces are shaped together, so the
+/// result is the same as shaping the whole text at once.
+///
+/// The segment properties are guessed from the whole text.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+/// let mut cache = ShapeCache::new(1000);
+///
+/// let output = shape_cached(&mut cache, &font, "to be or not to be", &[]);
+/// assert_eq!(output.len(), 18);
+/// ```
+pub fn shape_cached(
+    cache: &mut ShapeCache,
+    font: &Font<'_>,
+    text: &str,
+    features: &[Feature],
+) -> GlyphBuffer {
+    let props = UnicodeBuffer::new()
+        .add_str(text)
+        .guess_segment_properties()
+        .get_segment_properties();
+    let mut shape_piece = |range: Range<usize>| {
+        let features: Vec<Feature> = features
+            .iter()
+            .filter(|feature| feature.start() < range.end && feature.end() > range.start)
+            .map(|feature| {
+                let start = feature.start().max(range.start) - range.start;
+                let end = feature.end().min(range.end) - range.start;
+                Feature::new(feature.tag(), feature.value(), start..end)
+            })
+            .collect();
+        cache.shape(font, &text[range], props, &features)
+    };
+
+    let mut pieces: Vec<(Range<usize>, Rc<GlyphBuffer>)> = Vec::new();
+    for word in words(text) {
+        let glyphs = shape_piece(word.clone());
+        match pieces.last_mut() {
+            Some((range, piece)) if !can_concat(piece, &glyphs) => {
+                range.end = word.end;
+                *piece = shape_piece(range.clone());
+            }
+            _ => pieces.push((word, glyphs)),
+        }
+    }
+
+    if props.direction.is_backward() {
+        pieces.reverse();
+    }
+    let mut buffer = GenericBuffer::new();
+    buffer.set_segment_properties(props);
+    buffer.set_flags(BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+    buffer.set_content_type(HB_BUFFER_CONTENT_TYPE_GLYPHS);
+    for (range, glyphs) in pieces {
+        let first = buffer.len();
+        buffer.append(&glyphs.0, 0, glyphs.len() as c_uint);
+        for info in &mut buffer.get_glyph_infos_mut()[first..] {
+            info.cluster += range.start as u32;
+        }
+    }
+    GlyphBuffer(buffer)
+}
+
+/// Splits `text` after every sequence of spaces.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+    for (offset, c) in text.char_indices() {
+        if previous == Some(' ') && c != ' ' {
+            words.push(start..offset);
+            start = offset;
+        }
+        previous = Some(c);
+    }
+    if start < text.len() {
+        words.push(start..text.len());
+    }
+    words
+}
+
+/// Whether the glyphs of two adjacent pieces of text that were shaped
+/// separately can be concatenated.
+fn can_concat(first: &GlyphBuffer, second: &GlyphBuffer) -> bool {
+    let unsafe_at = |infos: &[GlyphInfo], cluster: u32| {
+        infos
+            .iter()
+            .filter(|info| info.cluster == cluster)
+            .any(|info| info.glyph_flags().unsafe_to_concat())
+    };
+    let first_infos = first.get_glyph_infos();
+    let second_infos = second.get_glyph_infos();
+    let last_cluster = first_infos.iter().map(|info| info.cluster).max();
+    let first_cluster = second_infos.iter().map(|info| info.cluster).min();
+    match (last_cluster, first_cluster) {
+        (Some(last), Some(first)) => {
+            !unsafe_at(first_infos, last) && !unsafe_at(second_infos, first)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::Glyph;
+    use crate::test_util::{font, identity_font};
+    use crate::Position;
+
+    fn glyphs(buffer: &GlyphBuffer) -> Vec<(Glyph, u32, Position)> {
+        let positions = buffer.get_glyph_positions();
+        buffer
+            .get_glyph_infos()
+            .iter()
+            .zip(positions)
+            .map(|(info, position)| (info.codepoint, info.cluster, position.x_advance))
+            .collect()
+    }
+
+    fn check_shape_cached(
+        cache: &mut ShapeCache,
+        font: &Font<'_>,
+        text: &str,
+        features: &[Feature],
+    ) {
+        let expected = shape(font, UnicodeBuffer::new().add_str(text), features);
+        let output = shape_cached(cache, font, text, features);
+        assert_eq!(glyphs(&output), glyphs(&expected), "{:?}", text);
+    }
+
+    #[test]
+    fn test_shape_cached() {
+        let font = font();
+        let mut cache = ShapeCache::new(100);
+        let text = "the cat the cat the";
+        check_shape_cached(&mut cache, &font, text, &[]);
+        // "the ", "cat " and "the"
+        assert_eq!(cache.len(), 3);
+        check_shape_cached(&mut cache, &font, text, &[]);
+        assert_eq!(cache.len(), 3);
+
+        let features = [Feature::new(b"liga", 0, 9..)];
+        check_shape_cached(&mut cache, &font, "off  off offtake", &features);
+        check_shape_cached(
+            &mut cache,
+            &font,
+            "\u{5D0}\u{5D1} \u{5D2} \u{5D0}\u{5D1}",
+            &[],
+        );
+        check_shape_cached(&mut cache, &font, "", &[]);
+
+        // HarfBuzz does not allow concatenating Arabic words in all cases.
+        let arabic = identity_font();
+        check_shape_cached(&mut cache, &arabic, "\u{628}\u{628} \u{628}\u{628}", &[]);
+
+        let len = cache.len();
+        let mut scaled = font();
+        scaled.set_scale(100, 100);
+        check_shape_cached(&mut cache, &scaled, text, &[]);
+        assert_eq!(cache.len(), len + 3);
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_removed() {
+        let font = font();
+        let props = UnicodeBuffer::new()
+            .add_str("a")
+            .guess_segment_properties()
+            .get_segment_properties();
+        let mut cache = ShapeCache::new(2);
+        let a = cache.shape(&font, "a", props, &[]);
+        let b = cache.shape(&font, "b", props, &[]);
+        assert!(Rc::ptr_eq(&cache.shape(&font, "a", props, &[]), &a));
+        cache.shape(&font, "c", props, &[]);
+        assert_eq!(cache.len(), 2);
+        assert!(Rc::ptr_eq(&cache.shape(&font, "a", props, &[]), &a));
+        assert!(!Rc::ptr_eq(&cache.shape(&font, "b", props, &[]), &b));
+
+        let mut uncached = ShapeCache::new(0);
+        uncached.shape(&font, "a", props, &[]);
+        assert!(uncached.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! Caret positions and hit testing for shaped text.
+//!
+//! The caret can be placed at every grapheme boundary of the text, including
+//! the boundaries inside of ligatures. The advance of a ligature glyph is
+//! split at the ligature carets from the font's `GDEF` table or, if the font
+//! has none for the glyph, evenly between the graphemes that formed it.
+//!
+//! Positions are computed from the glyph advances of each cluster, so they
+//! are not affected by glyph offsets like those of marks or of cursively
+//! attached glyphs. Only horizontal text is supported.
+//!
+//! # Examples
+//!
+//! ```
+//! use harfbuzz_rs::*;
+//!
+//! let path = "testfiles/SourceSansVariable-Roman.ttf";
+//! let face = Face::from_file(path, 0).expect("could not load face");
+//! let font = Font::new(face);
+//!
+//! let text = "after";
+//! let buffer = shape(&font, UnicodeBuffer::new().add_str(text), &[]);
+//!
+//! // the caret between "f" and "t" lies inside the "ft" ligature
+//! let x = buffer.caret_x(&font, text, 2).unwrap();
+//! let hit = buffer.hit_test(&font, text, x + 1).unwrap();
+//! assert_eq!(hit.text_range, 2..3);
+//! assert!(!hit.trailing);
+//! ```
+
+use std::ops::Range;
+use std::os::raw::c_uint;
+
+use harfbuzz_bindings::hb_ot_layout_get_ligature_carets;
+
+use crate::buffer::GlyphBuffer;
+use crate::common::{Direction, HarfbuzzObject};
+use crate::font::{Font, Position};
+use crate::grapheme::graphemes;
+
+/// The result of hit testing a position on a line of shaped text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CaretHit {
+    /// The grapheme at the position, as a range of the text.
+    pub text_range: Range<usize>,
+    /// Whether the position is on the trailing half of the grapheme, that is
+    /// its right half in left-to-right and its left half in right-to-left
+    /// text.
+    pub trailing: bool,
+}
+
+impl CaretHit {
+    /// The byte offset of the grapheme's cluster.
+    pub fn cluster(&self) -> usize {
+        self.text_range.start
+    }
+
+    /// The offset in the text at which the caret is placed for this hit.
+    pub fn offset(&self) -> usize {
+        if self.trailing {
+            self.text_range.end
+        } else {
+            self.text_range.start
+        }
+    }
+}
+
+/// The part of a line that shows a single grapheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CaretPiece {
+    pub(crate) text_range: Range<usize>,
+    pub(crate) left: Position,
+    pub(crate) right: Position,
+}
+
+impl GlyphBuffer {
+    /// Returns the grapheme at the horizontal position `x` and whether `x` is
+    /// on its leading or trailing half.
+    ///
+    /// `text` is the text the buffer was shaped from, so that the cluster
+    /// values are byte offsets into `text` and the buffer ends with its last
+    /// character. The first glyph starts at `x = 0`. Positions before the
+    /// start or after the end of the line hit the grapheme at that end.
+    /// Returns `None` if the buffer is empty.
+    pub fn hit_test(&self, font: &Font<'_>, text: &str, x: Position) -> Option<CaretHit> {
+        let pieces = self.caret_pieces(font, text);
+        let piece = pieces
+            .iter()
+            .find(|piece| x < piece.right)
+            .or_else(|| pieces.last())?;
+        let in_right_half = 2 * x >= piece.left + piece.right;
+        Some(CaretHit {
+            text_range: piece.text_range.clone(),
+            trailing: in_right_half != self.0.get_direction().is_backward(),
+        })
+    }
+
+    /// Returns the horizontal position of the caret at the byte offset
+    /// `offset` of `text`.
+    ///
+    /// See `hit_test` for the requirements on `text`. Returns `None` if
+    /// `offset` is not a grapheme boundary of the shaped text.
+    pub fn caret_x(&self, font: &Font<'_>, text: &str, offset: usize) -> Option<Position> {
+        let pieces = self.caret_pieces(font, text);
+        let backward = self.0.get_direction().is_backward();
+        let leading = |piece: &CaretPiece| if backward { piece.right } else { piece.left };
+        let trailing = |piece: &CaretPiece| if backward { piece.left } else { piece.right };
+        pieces
+            .iter()
+            .find(|piece| piece.text_range.start == offset)
+            .map(leading)
+            .or_else(|| {
+                pieces
+                    .iter()
+                    .find(|piece| piece.text_range.end == offset)
+                    .map(trailing)
+            })
+    }
+
+    /// Splits the line into the parts showing each grapheme, from left to
+    /// right.
+    pub(crate) fn caret_pieces(&self, font: &Font<'_>, text: &str) -> Vec<CaretPiece> {
+        let direction = self.0.get_direction();
+        let backward = direction.is_backward();
+        let infos = self.get_glyph_infos();
+        let mut pieces = Vec::new();
+        let boundaries: Vec<usize> = graphemes(text).map(|grapheme| grapheme.start).collect();
+        let mut x = 0;
+        for span in self.clusters(text.len()) {
+            let (left, right) = (x, x + span.advance);
+            x = right;
+            let starts = grapheme_starts(&boundaries, span.text_range.clone());
+            if starts.len() <= 1 {
+                pieces.push(CaretPiece {
+                    text_range: span.text_range,
+                    left,
+                    right,
+                });
+                continue;
+            }
+
+            // The positions between the graphemes from left to right.
+            let mut carets = Vec::new();
+            if span.glyph_range.len() == 1 {
+                let glyph = infos[span.glyph_range.start].codepoint;
+                carets = ligature_carets(font, direction, glyph);
+                carets.sort();
+                carets.iter_mut().for_each(|caret| *caret += left);
+            }
+            if carets.len() != starts.len() - 1 {
+                let count = starts.len() as Position;
+                carets = (1..count)
+                    .map(|i| left + span.advance * i / count)
+                    .collect();
+            }
+            let mut edges = vec![left];
+            edges.extend(carets);
+            edges.push(right);
+            if backward {
+                edges.reverse();
+            }
+
+            let mut span_pieces: Vec<CaretPiece> = (0..starts.len())
+                .map(|i| {
+                    let end = starts.get(i + 1).cloned().unwrap_or(span.text_range.end);
+                    let (a, b) = (edges[i], edges[i + 1]);
+                    CaretPiece {
+                        text_range: starts[i]..end,
+                        left: a.min(b),
+                        right: a.max(b),
+                    }
+                })
+                .collect();
+            if backward {
+                span_pieces.reverse();
+            }
+            pieces.extend(span_pieces);
+        }
+        pieces
+    }
+}
+
+/// Returns the ligature caret positions of `glyph` from the font's `GDEF`
+/// table, relative to the glyph's origin.
+fn ligature_carets(font: &Font<'_>, direction: Direction, glyph: u32) -> Vec<Position> {
+    let get_carets = |count: *mut c_uint, carets: *mut Position| unsafe {
+        hb_ot_layout_get_ligature_carets(font.as_raw(), direction.to_raw(), glyph, 0, count, carets)
+    };
+    let mut count = get_carets(std::ptr::null_mut(), std::ptr::null_mut());
+    let mut carets = vec![0; count as usize];
+    get_carets(&mut count, carets.as_mut_ptr());
+    carets.truncate(count as usize);
+    carets
+}
+
+/// Returns the start offsets of the graphemes in `range` of `text`, where
+/// `boundaries` are the grapheme boundaries of `text`. The range always starts
+/// a grapheme, even if the clusters split a grapheme of the text.
+fn grapheme_starts(boundaries: &[usize], range: Range<usize>) -> Vec<usize> {
+    let mut starts = vec![range.start];
+    starts.extend(
+        boundaries
+            .iter()
+            .cloned()
+            .filter(|&offset| range.start < offset && offset < range.end),
+    );
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{font, shaped};
+    use crate::Position;
+
+    #[test]
+    fn test_left_to_right() {
+        let font = font();
+        let text = "abc";
+        let buffer = shaped(&font, text);
+        let advances: Vec<_> = buffer
+            .get_glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .collect();
+        let total = advances.iter().sum();
+        assert_eq!(buffer.caret_x(&font, text, 0), Some(0));
+        assert_eq!(buffer.caret_x(&font, text, 1), Some(advances[0]));
+        assert_eq!(buffer.caret_x(&font, text, 3), Some(total));
+        assert_eq!(buffer.caret_x(&font, text, 4), None);
+
+        let hit = buffer.hit_test(&font, text, 1).unwrap();
+        assert_eq!((hit.text_range, hit.trailing), (0..1, false));
+        let hit = buffer.hit_test(&font, text, advances[0] - 1).unwrap();
+        assert_eq!((hit.cluster(), hit.offset()), (0, 1));
+        let hit = buffer.hit_test(&font, text, total + 100).unwrap();
+        assert_eq!(hit.offset(), 3);
+        assert_eq!(buffer.hit_test(&font, text, -100).unwrap().offset(), 0);
+
+        assert_eq!(shaped(&font, "").hit_test(&font, "", 0), None);
+    }
+
+    #[test]
+    fn test_ligatures_and_marks() {
+        let font = font();
+        let text = "after";
+        let buffer = shaped(&font, text);
+        let before = buffer.caret_x(&font, text, 1).unwrap();
+        let inside = buffer.caret_x(&font, text, 2).unwrap();
+        let after = buffer.caret_x(&font, text, 3).unwrap();
+        assert!(before < inside && inside < after);
+        let hit = buffer.hit_test(&font, text, inside - 1).unwrap();
+        assert_eq!((hit.text_range, hit.trailing), (1..2, true));
+
+        // the caret cannot be placed between a base and its mark
+        let text = "e\u{301}x";
+        let buffer = shaped(&font, text);
+        assert_eq!(buffer.caret_x(&font, text, 1), None);
+        assert!(buffer.caret_x(&font, text, 3).is_some());
+    }
+
+    #[test]
+    fn test_right_to_left() {
+        let font = font();
+        let text = "\u{5D0}\u{5D1}\u{5D2}";
+        let buffer = shaped(&font, text);
+        let advances: Vec<_> = buffer
+            .get_glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .collect();
+        let total: Position = advances.iter().sum();
+        assert_eq!(buffer.caret_x(&font, text, 0), Some(total));
+        assert_eq!(buffer.caret_x(&font, text, 2), Some(total - advances[2]));
+        assert_eq!(buffer.caret_x(&font, text, 6), Some(0));
+
+        let hit = buffer.hit_test(&font, text, total - 1).unwrap();
+        assert_eq!((hit.text_range, hit.trailing), (0..2, false));
+        let hit = buffer.hit_test(&font, text, 1).unwrap();
+        assert_eq!((hit.text_range, hit.trailing), (4..6, true));
+    }
+}
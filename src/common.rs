@@ -13,6 +13,8 @@ use harfbuzz_bindings::{
     hb_script_t, hb_script_to_iso15924_tag, hb_tag_from_string, hb_tag_t, hb_tag_to_string,
 };
 
+use crate::font::Position;
+
 /// A type to represent 4-byte SFNT tags.
 ///
 /// The easiest way to create a tag is by using its `From<&[u8; 4]>` impl:
@@ -188,6 +190,17 @@ impl Direction {
             _ => Direction::Invalid,
         }
     }
+
+    /// Returns `true` if text is set vertically, i.e. for `Ttb` and `Btt`.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Direction::Ttb | Direction::Btt)
+    }
+
+    /// Returns `true` if the glyphs of shaped text are in reverse text order,
+    /// i.e. for `Rtl` and `Btt`.
+    pub fn is_backward(self) -> bool {
+        matches!(self, Direction::Rtl | Direction::Btt)
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -253,6 +266,39 @@ impl Script {
     }
 }
 
+/// An axis-aligned rectangle in the coordinate system of HarfBuzz, where y
+/// grows upwards.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// The x coordinate of the left edge.
+    pub x: Position,
+    /// The y coordinate of the bottom edge.
+    pub y: Position,
+    pub width: Position,
+    pub height: Position,
+}
+
+impl Rect {
+    pub fn new(x: Position, y: Position, width: Position, height: Position) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The x coordinate of the right edge.
+    pub fn right(&self) -> Position {
+        self.x + self.width
+    }
+
+    /// The y coordinate of the top edge.
+    pub fn top(&self) -> Position {
+        self.y + self.height
+    }
+}
+
 /// A trait which is implemented for all harffbuzz wrapper structs. It exposes
 /// common functionality for converting from and to the underlying raw harfbuzz
 /// pointers that are useful for ffi.
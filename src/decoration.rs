@@ -0,0 +1,411 @@
+use harfbuzz_bindings::{
+    hb_ot_metrics_get_position_with_fallback, hb_ot_metrics_tag_t,
+    HB_OT_METRICS_TAG_STRIKEOUT_OFFSET, HB_OT_METRICS_TAG_STRIKEOUT_SIZE,
+    HB_OT_METRICS_TAG_UNDERLINE_OFFSET, HB_OT_METRICS_TAG_UNDERLINE_SIZE,
+};
+
+use crate::buffer::GlyphBuffer;
+use crate::common::{HarfbuzzObject, Rect};
+use crate::draw::DrawFuncs;
+use crate::font::{Font, Position};
+
+/// The kinds of lines that can be drawn along a text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DecorationKind {
+    Underline,
+    Strikeout,
+    Overline,
+}
+
+/// Returns the rectangle of a decoration line of `kind` along the glyphs of
+/// `glyphs`.
+///
+/// The position and thickness of underlines come from the font's `post`
+/// table, those of strikeouts from its `OS/2` table. Overlines are as thick
+/// as underlines and end at the font's ascender. HarfBuzz derives fallback
+/// values for fonts without these tables.
+///
+/// The rectangle uses the same coordinates as the glyph positions of
+/// `glyphs`: the first glyph starts at `x = 0` and the baseline is at
+/// `y = 0`. The buffer must contain horizontal text. An empty buffer has no
+/// decorations.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+///
+/// let glyphs = shape(&font, UnicodeBuffer::new().add_str("Hello"), &[]);
+/// let underline = decorations(&font, &glyphs, DecorationKind::Underline);
+/// assert_eq!(underline.len(), 1);
+/// assert!(underline[0].top() <= 0);
+/// ```
+pub fn decorations(font: &Font<'_>, glyphs: &GlyphBuffer, kind: DecorationKind) -> Vec<Rect> {
+    let advance: Position = glyphs
+        .get_glyph_positions()
+        .iter()
+        .map(|position| position.x_advance)
+        .sum();
+    if glyphs.is_empty() || advance <= 0 {
+        return Vec::new();
+    }
+    let (y, height) = band(font, kind);
+    vec![Rect::new(0, y, advance, height)]
+}
+
+/// Returns the rectangles of a decoration line of `kind` that leaves gaps
+/// where glyphs cross it, like descenders do with underlines.
+///
+/// The line is placed like by `decorations`. The ink of every glyph is taken
+/// from its outline or, for glyphs without one, from its extents. Where it
+/// overlaps the line, the line is interrupted with a clearance of its
+/// thickness on both sides.
+pub fn decorations_skip_ink(
+    font: &Font<'_>,
+    glyphs: &GlyphBuffer,
+    kind: DecorationKind,
+) -> Vec<Rect> {
+    let line = match decorations(font, glyphs, kind).pop() {
+        Some(line) => line,
+        None => return Vec::new(),
+    };
+    let clearance = line.height as f32;
+    let (y_min, y_max) = (line.y as f32, line.top() as f32);
+
+    let mut gaps: Vec<(f32, f32)> = Vec::new();
+    let mut pen = 0;
+    for (info, position) in glyphs
+        .get_glyph_infos()
+        .iter()
+        .zip(glyphs.get_glyph_positions())
+    {
+        let x = (pen + position.x_offset) as f32;
+        let y = position.y_offset as f32;
+        pen += position.x_advance;
+        let extents = match font.get_glyph_extents(info.codepoint) {
+            Some(extents) => extents,
+            None => continue,
+        };
+        let top = y + extents.y_bearing as f32;
+        let bottom = top + extents.height as f32;
+        if extents.width == 0 || bottom >= y_max || top <= y_min {
+            continue;
+        }
+        let mut ink = InkCollector::new(y_min - y, y_max - y);
+        font.draw_glyph(info.codepoint, &mut ink);
+        if ink.started {
+            gaps.extend(
+                ink.spans()
+                    .into_iter()
+                    .map(|(left, right)| (x + left, x + right)),
+            );
+        } else {
+            let left = x + extents.x_bearing as f32;
+            gaps.push((left, left + extents.width as f32));
+        }
+    }
+
+    gaps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let mut rects = Vec::new();
+    let mut start = line.x as f32;
+    for (left, right) in gaps {
+        let (left, right) = (left - clearance, right + clearance);
+        if left > start {
+            rects.push((start, left.min(line.right() as f32)));
+        }
+        start = start.max(right);
+    }
+    if start < line.right() as f32 {
+        rects.push((start, line.right() as f32));
+    }
+    rects
+        .into_iter()
+        .map(|(left, right)| (left.round() as Position, right.round() as Position))
+        .filter(|(left, right)| left < right)
+        .map(|(left, right)| Rect::new(left, line.y, right - left, line.height))
+        .collect()
+}
+
+/// The bottom edge and height of a decoration line.
+fn band(font: &Font<'_>, kind: DecorationKind) -> (Position, Position) {
+    let metric = |tag: hb_ot_metrics_tag_t| {
+        let mut position = 0;
+        unsafe { hb_ot_metrics_get_position_with_fallback(font.as_raw(), tag, &mut position) };
+        position
+    };
+    // The offsets are those of the top edge of the line.
+    let (top, thickness) = match kind {
+        DecorationKind::Underline => (
+            metric(HB_OT_METRICS_TAG_UNDERLINE_OFFSET),
+            metric(HB_OT_METRICS_TAG_UNDERLINE_SIZE),
+        ),
+        DecorationKind::Strikeout => (
+            metric(HB_OT_METRICS_TAG_STRIKEOUT_OFFSET),
+            metric(HB_OT_METRICS_TAG_STRIKEOUT_SIZE),
+        ),
+        DecorationKind::Overline => (
+            font.get_font_h_extents().unwrap_or_default().ascender,
+            metric(HB_OT_METRICS_TAG_UNDERLINE_SIZE),
+        ),
+    };
+    (top - thickness, thickness)
+}
+
+/// Collects the horizontal ranges in which a glyph has ink between two
+/// heights.
+struct InkCollector {
+    y_min: f32,
+    y_max: f32,
+    start: (f32, f32),
+    current: (f32, f32),
+    started: bool,
+    // The parts of the outline between the heights.
+    ranges: Vec<(f32, f32)>,
+    // The line segments of the outline that reach between the heights.
+    segments: Vec<((f32, f32), (f32, f32))>,
+}
+
+impl InkCollector {
+    // The number of line segments a curve is split into.
+    const CURVE_STEPS: usize = 8;
+    // The number of intervals between the heights at which the filled parts
+    // of the outline are determined.
+    const SCANLINE_STEPS: usize = 4;
+
+    fn new(y_min: f32, y_max: f32) -> InkCollector {
+        InkCollector {
+            y_min,
+            y_max,
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+            started: false,
+            ranges: Vec::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    fn add_segment(&mut self, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        self.current = (x, y);
+        if y0.max(y) < self.y_min || y0.min(y) > self.y_max {
+            return;
+        }
+        self.segments.push(((x0, y0), (x, y)));
+        if y == y0 {
+            self.ranges.push((x0.min(x), x0.max(x)));
+            return;
+        }
+        // The ends of the part of the segment between y_min and y_max.
+        let x_at = |target: f32| {
+            let target = target.max(self.y_min).min(self.y_max);
+            x0 + (x - x0) * (target - y0) / (y - y0)
+        };
+        let (xa, xb) = (x_at(y0), x_at(y));
+        self.ranges.push((xa.min(xb), xa.max(xb)));
+    }
+
+    fn close_contour(&mut self) {
+        if self.current != self.start {
+            let (x, y) = self.start;
+            self.add_segment(x, y);
+        }
+    }
+
+    /// The horizontal ranges with ink between the heights: the parts of the
+    /// outline itself and the filled spans on several scanlines, using the
+    /// nonzero winding rule.
+    fn spans(mut self) -> Vec<(f32, f32)> {
+        self.close_contour();
+        let mut spans = self.ranges;
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        for step in 0..=Self::SCANLINE_STEPS {
+            let t = step as f32 / Self::SCANLINE_STEPS as f32;
+            let y = self.y_min + t * (self.y_max - self.y_min);
+            crossings.clear();
+            for &((x0, y0), (x1, y1)) in &self.segments {
+                if (y0 <= y) != (y1 <= y) {
+                    let x = x0 + (x1 - x0) * (y - y0) / (y1 - y0);
+                    crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let mut winding = 0;
+            let mut left = 0.0;
+            for &(x, direction) in &crossings {
+                if winding == 0 {
+                    left = x;
+                }
+                winding += direction;
+                if winding == 0 {
+                    spans.push((left, x));
+                }
+            }
+        }
+        spans
+    }
+}
+
+impl DrawFuncs for InkCollector {
+    fn move_to(&mut self, x: f32, y: f32) {
+        if self.started {
+            self.close_contour();
+        }
+        self.started = true;
+        self.start = (x, y);
+        self.current = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.add_segment(x, y);
+    }
+
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        for step in 1..=Self::CURVE_STEPS {
+            let t = step as f32 / Self::CURVE_STEPS as f32;
+            let u = 1.0 - t;
+            self.add_segment(
+                u * u * x0 + 2.0 * u * t * control_x + t * t * x,
+                u * u * y0 + 2.0 * u * t * control_y + t * t * y,
+            );
+        }
+    }
+
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) {
+        let (x0, y0) = self.current;
+        for step in 1..=Self::CURVE_STEPS {
+            let t = step as f32 / Self::CURVE_STEPS as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.add_segment(
+                a * x0 + b * control1_x + c * control2_x + d * x,
+                a * y0 + b * control1_y + c * control2_y + d * y,
+            );
+        }
+    }
+
+    fn close_path(&mut self) {
+        self.close_contour();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::{Glyph, GlyphExtents};
+    use crate::font_funcs::FontFuncs;
+    use crate::test_util::{font, font_with_funcs, shaped};
+
+    #[test]
+    fn test_decorations() {
+        let font = font();
+        let glyphs = shaped(&font, "Hxg");
+        let advance: Position = glyphs
+            .get_glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .sum();
+
+        let underline = decorations(&font, &glyphs, DecorationKind::Underline);
+        assert_eq!(underline.len(), 1);
+        assert_eq!((underline[0].x, underline[0].width), (0, advance));
+        assert!(underline[0].height > 0);
+        assert!(underline[0].top() <= 0);
+
+        let strikeout = decorations(&font, &glyphs, DecorationKind::Strikeout)[0];
+        assert!(strikeout.y > 0);
+        assert!(strikeout.top() < font.get_font_h_extents().unwrap().ascender);
+
+        let overline = decorations(&font, &glyphs, DecorationKind::Overline)[0];
+        assert_eq!(overline.top(), font.get_font_h_extents().unwrap().ascender);
+        assert_eq!(overline.height, underline[0].height);
+
+        let empty = shaped(&font, "");
+        assert!(decorations(&font, &empty, DecorationKind::Underline).is_empty());
+    }
+
+    #[test]
+    fn test_skip_ink() {
+        let font = font();
+        let glyphs = shaped(&font, "xgx");
+        let line = decorations(&font, &glyphs, DecorationKind::Underline)[0];
+        let pieces = decorations_skip_ink(&font, &glyphs, DecorationKind::Underline);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].x, 0);
+        assert_eq!(pieces[1].right(), line.right());
+        // the gap lies around the descender of "g"
+        let positions = glyphs.get_glyph_positions();
+        let g_start = positions[0].x_advance;
+        let g_end = g_start + positions[1].x_advance;
+        assert!(pieces[0].right() > 0 && pieces[0].right() < g_end);
+        assert!(pieces[1].x > g_start && pieces[1].x < line.right());
+        for piece in &pieces {
+            assert_eq!((piece.y, piece.height), (line.y, line.height));
+        }
+
+        // lower case letters without ascenders do not reach the overline
+        let overline = decorations_skip_ink(&font, &shaped(&font, "xx"), DecorationKind::Overline);
+        assert_eq!(overline.len(), 1);
+    }
+
+    // Every glyph is a wide rectangle reaching below the baseline, like a
+    // bold descender.
+    struct StemFont;
+
+    impl FontFuncs for StemFont {
+        fn get_nominal_glyph(&self, _: &Font<'_>, _: char) -> Option<Glyph> {
+            Some(1)
+        }
+
+        fn get_glyph_h_advance(&self, _: &Font<'_>, _: Glyph) -> Position {
+            1000
+        }
+
+        fn get_glyph_extents(&self, _: &Font<'_>, _: Glyph) -> Option<GlyphExtents> {
+            Some(GlyphExtents {
+                x_bearing: 100,
+                y_bearing: 500,
+                width: 800,
+                height: -800,
+            })
+        }
+
+        fn draw_glyph(&self, _: &Font<'_>, _: Glyph, draw: &mut dyn DrawFuncs) {
+            draw.move_to(100.0, -300.0);
+            draw.line_to(900.0, -300.0);
+            draw.line_to(900.0, 500.0);
+            draw.line_to(100.0, 500.0);
+            draw.close_path();
+        }
+    }
+
+    #[test]
+    fn test_skip_ink_wide_descender() {
+        let font = font_with_funcs(StemFont);
+        let glyphs = shaped(&font, "p");
+        let line = decorations(&font, &glyphs, DecorationKind::Underline)[0];
+        assert!(line.y > -300 && line.top() < 500);
+
+        // the line is interrupted for the whole width of the stem, not only
+        // around its edges
+        let pieces = decorations_skip_ink(&font, &glyphs, DecorationKind::Underline);
+        let edges: Vec<_> = pieces
+            .iter()
+            .map(|piece| (piece.x, piece.right()))
+            .collect();
+        let clearance = line.height;
+        assert_eq!(edges, vec![(0, 100 - clearance), (900 + clearance, 1000)]);
+    }
+}
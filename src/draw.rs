@@ -0,0 +1,339 @@
+use std;
+use std::any::Any;
+use std::os::raw::c_void;
+use std::panic;
+
+use harfbuzz_bindings::{
+    hb_draw_close_path, hb_draw_cubic_to, hb_draw_funcs_create, hb_draw_funcs_destroy,
+    hb_draw_funcs_make_immutable, hb_draw_funcs_set_close_path_func,
+    hb_draw_funcs_set_cubic_to_func, hb_draw_funcs_set_line_to_func,
+    hb_draw_funcs_set_move_to_func, hb_draw_funcs_set_quadratic_to_func, hb_draw_funcs_t,
+    hb_draw_line_to, hb_draw_move_to, hb_draw_quadratic_to, hb_draw_state_t, hb_font_draw_glyph,
+};
+
+use crate::common::HarfbuzzObject;
+use crate::font::{Font, Glyph};
+
+/// A receiver of glyph outlines.
+///
+/// Implement this trait to extract the outline of a glyph using
+/// [`Font::draw_glyph()`]. Coordinates are in font units scaled by the font's
+/// scale, with the y-axis pointing upwards.
+///
+/// Every contour starts with a `move_to` and ends with a `close_path`.
+///
+/// # Examples
+///
+/// Count the contours of a glyph:
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// #[derive(Default)]
+/// struct ContourCounter(usize);
+///
+/// impl DrawFuncs for ContourCounter {
+///     fn move_to(&mut self, _: f32, _: f32) {}
+///     fn line_to(&mut self, _: f32, _: f32) {}
+///     fn quadratic_to(&mut self, _: f32, _: f32, _: f32, _: f32) {}
+///     fn cubic_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {}
+///     fn close_path(&mut self) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+///
+/// let mut counter = ContourCounter::default();
+/// font.draw_glyph(font.get_nominal_glyph('o').unwrap(), &mut counter);
+/// assert_eq!(counter.0, 2);
+/// ```
+pub trait DrawFuncs {
+    /// Starts a new contour at `(x, y)`.
+    fn move_to(&mut self, x: f32, y: f32);
+    /// Draws a straight line from the current point to `(x, y)`.
+    fn line_to(&mut self, x: f32, y: f32);
+    /// Draws a quadratic Bézier curve from the current point to `(x, y)`.
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32);
+    /// Draws a cubic Bézier curve from the current point to `(x, y)`.
+    #[allow(clippy::too_many_arguments)]
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    );
+    /// Closes the current contour.
+    fn close_path(&mut self);
+}
+
+struct DrawData<'a> {
+    funcs: &'a mut dyn DrawFuncs,
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl<'a> DrawData<'a> {
+    // Runs `f` unless an earlier callback panicked. A panic is stored so that
+    // it can be resumed once control is back on the Rust side.
+    fn call(draw_data: *mut c_void, f: impl FnOnce(&mut dyn DrawFuncs)) {
+        let data = unsafe { &mut *(draw_data as *mut DrawData<'_>) };
+        if data.panic.is_some() {
+            return;
+        }
+        let funcs = &mut *data.funcs;
+        if let Err(err) = panic::catch_unwind(panic::AssertUnwindSafe(|| f(funcs))) {
+            data.panic = Some(err);
+        }
+    }
+}
+
+extern "C" fn rust_move_to(
+    _: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    _: *mut hb_draw_state_t,
+    x: f32,
+    y: f32,
+    _: *mut c_void,
+) {
+    DrawData::call(draw_data, |funcs| funcs.move_to(x, y));
+}
+
+extern "C" fn rust_line_to(
+    _: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    _: *mut hb_draw_state_t,
+    x: f32,
+    y: f32,
+    _: *mut c_void,
+) {
+    DrawData::call(draw_data, |funcs| funcs.line_to(x, y));
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn rust_quadratic_to(
+    _: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    _: *mut hb_draw_state_t,
+    control_x: f32,
+    control_y: f32,
+    x: f32,
+    y: f32,
+    _: *mut c_void,
+) {
+    DrawData::call(draw_data, |funcs| {
+        funcs.quadratic_to(control_x, control_y, x, y)
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn rust_cubic_to(
+    _: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    _: *mut hb_draw_state_t,
+    control1_x: f32,
+    control1_y: f32,
+    control2_x: f32,
+    control2_y: f32,
+    x: f32,
+    y: f32,
+    _: *mut c_void,
+) {
+    DrawData::call(draw_data, |funcs| {
+        funcs.cubic_to(control1_x, control1_y, control2_x, control2_y, x, y)
+    });
+}
+
+extern "C" fn rust_close_path(
+    _: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    _: *mut hb_draw_state_t,
+    _: *mut c_void,
+) {
+    DrawData::call(draw_data, |funcs| funcs.close_path());
+}
+
+/// Draws the outline of `glyph` into `funcs`.
+///
+/// Panics raised by `funcs` are propagated once HarfBuzz returns.
+pub(crate) fn draw_glyph(font: &Font<'_>, glyph: Glyph, funcs: &mut dyn DrawFuncs) {
+    let mut data = DrawData { funcs, panic: None };
+    unsafe {
+        let draw_funcs = hb_draw_funcs_create();
+        hb_draw_funcs_set_move_to_func(draw_funcs, Some(rust_move_to), std::ptr::null_mut(), None);
+        hb_draw_funcs_set_line_to_func(draw_funcs, Some(rust_line_to), std::ptr::null_mut(), None);
+        hb_draw_funcs_set_quadratic_to_func(
+            draw_funcs,
+            Some(rust_quadratic_to),
+            std::ptr::null_mut(),
+            None,
+        );
+        hb_draw_funcs_set_cubic_to_func(
+            draw_funcs,
+            Some(rust_cubic_to),
+            std::ptr::null_mut(),
+            None,
+        );
+        hb_draw_funcs_set_close_path_func(
+            draw_funcs,
+            Some(rust_close_path),
+            std::ptr::null_mut(),
+            None,
+        );
+        hb_draw_funcs_make_immutable(draw_funcs);
+        hb_font_draw_glyph(
+            font.as_raw(),
+            glyph,
+            draw_funcs,
+            &mut data as *mut DrawData<'_> as *mut c_void,
+        );
+        hb_draw_funcs_destroy(draw_funcs);
+    }
+    if let Some(err) = data.panic {
+        panic::resume_unwind(err);
+    }
+}
+
+/// Forwards drawing commands to raw HarfBuzz draw funcs.
+///
+/// This is handed to `FontFuncs::draw_glyph` implementations. Open contours
+/// are closed when it is dropped.
+pub(crate) struct RawDraw {
+    funcs: *mut hb_draw_funcs_t,
+    data: *mut c_void,
+    state: hb_draw_state_t,
+}
+
+impl RawDraw {
+    pub(crate) fn new(funcs: *mut hb_draw_funcs_t, data: *mut c_void) -> RawDraw {
+        RawDraw {
+            funcs,
+            data,
+            state: unsafe { std::mem::zeroed() },
+        }
+    }
+}
+
+impl DrawFuncs for RawDraw {
+    fn move_to(&mut self, x: f32, y: f32) {
+        unsafe { hb_draw_move_to(self.funcs, self.data, &mut self.state, x, y) }
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        unsafe { hb_draw_line_to(self.funcs, self.data, &mut self.state, x, y) }
+    }
+
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) {
+        unsafe {
+            hb_draw_quadratic_to(
+                self.funcs,
+                self.data,
+                &mut self.state,
+                control_x,
+                control_y,
+                x,
+                y,
+            )
+        }
+    }
+
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) {
+        unsafe {
+            hb_draw_cubic_to(
+                self.funcs,
+                self.data,
+                &mut self.state,
+                control1_x,
+                control1_y,
+                control2_x,
+                control2_y,
+                x,
+                y,
+            )
+        }
+    }
+
+    fn close_path(&mut self) {
+        unsafe { hb_draw_close_path(self.funcs, self.data, &mut self.state) }
+    }
+}
+
+impl Drop for RawDraw {
+    fn drop(&mut self) {
+        if self.state.path_open != 0 {
+            self.close_path();
+        }
+    }
+}
+
+/// Scales all coordinates before passing them on. Used to forward outlines
+/// from a parent font with a different scale.
+pub(crate) struct ScaledDraw<'a> {
+    inner: &'a mut dyn DrawFuncs,
+    x_scale: f32,
+    y_scale: f32,
+}
+
+impl<'a> ScaledDraw<'a> {
+    pub(crate) fn new(inner: &'a mut dyn DrawFuncs, x_scale: f32, y_scale: f32) -> Self {
+        ScaledDraw {
+            inner,
+            x_scale,
+            y_scale,
+        }
+    }
+}
+
+impl<'a> DrawFuncs for ScaledDraw<'a> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.inner.move_to(x * self.x_scale, y * self.y_scale)
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.inner.line_to(x * self.x_scale, y * self.y_scale)
+    }
+
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) {
+        self.inner.quadratic_to(
+            control_x * self.x_scale,
+            control_y * self.y_scale,
+            x * self.x_scale,
+            y * self.y_scale,
+        )
+    }
+
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) {
+        self.inner.cubic_to(
+            control1_x * self.x_scale,
+            control1_y * self.y_scale,
+            control2_x * self.x_scale,
+            control2_y * self.y_scale,
+            x * self.x_scale,
+            y * self.y_scale,
+        )
+    }
+
+    fn close_path(&mut self) {
+        self.inner.close_path()
+    }
+}
@@ -1,18 +1,23 @@
 use std;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::path::Path;
 use std::ptr::NonNull;
 
 use harfbuzz_bindings::{
-    hb_blob_t, hb_face_create, hb_face_create_for_tables, hb_face_destroy, hb_face_get_empty,
-    hb_face_get_glyph_count, hb_face_get_index, hb_face_get_upem, hb_face_reference,
-    hb_face_reference_blob, hb_face_reference_table, hb_face_set_glyph_count, hb_face_set_upem,
-    hb_face_t, hb_tag_t,
+    hb_blob_t, hb_face_collect_nominal_glyph_mapping, hb_face_collect_unicodes,
+    hb_face_collect_variation_selectors, hb_face_collect_variation_unicodes, hb_face_create,
+    hb_face_create_for_tables, hb_face_destroy, hb_face_get_empty, hb_face_get_glyph_count,
+    hb_face_get_index, hb_face_get_upem, hb_face_reference, hb_face_reference_blob,
+    hb_face_reference_table, hb_face_set_glyph_count, hb_face_set_upem, hb_face_t, hb_map_create,
+    hb_map_destroy, hb_map_get, hb_tag_t,
 };
 
 use crate::blob::Blob;
 use crate::common::{HarfbuzzObject, Owned, Shared, Tag};
+use crate::font::Glyph;
+use crate::set::Set;
 
 /// A wrapper around `hb_face_t`.
 ///
@@ -143,6 +148,74 @@ impl<'a> Face<'a> {
         unsafe { hb_face_get_glyph_count(self.as_raw()) }
     }
 
+    /// Returns the set of all unicode codepoints covered by the face's
+    /// character map.
+    ///
+    /// This is a cheap way to check whether a face supports some text before
+    /// shaping it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::Face;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("could not load face");
+    ///
+    /// let unicodes = face.collect_unicodes();
+    /// assert!(unicodes.contains('A' as u32));
+    /// ```
+    pub fn collect_unicodes(&self) -> Owned<Set> {
+        let mut set = Set::new();
+        unsafe { hb_face_collect_unicodes(self.as_raw(), set.as_raw_mut()) };
+        set
+    }
+
+    /// Returns the nominal mapping from unicode codepoints to glyphs as found
+    /// in the face's character map.
+    ///
+    /// Variation selector sequences are not part of this mapping. Use
+    /// [`Self::variation_selectors()`] and [`Self::variation_unicodes()`] to
+    /// query those.
+    pub fn nominal_glyph_mapping(&self) -> BTreeMap<char, Glyph> {
+        let mut unicodes = Set::new();
+        unsafe {
+            let mapping = hb_map_create();
+            hb_face_collect_nominal_glyph_mapping(self.as_raw(), mapping, unicodes.as_raw_mut());
+            let result = unicodes
+                .iter()
+                .filter_map(|unicode| {
+                    let character = std::char::from_u32(unicode)?;
+                    Some((character, hb_map_get(mapping, unicode)))
+                })
+                .collect();
+            hb_map_destroy(mapping);
+            result
+        }
+    }
+
+    /// Returns the set of all variation selectors covered by the face's
+    /// character map.
+    pub fn variation_selectors(&self) -> Owned<Set> {
+        let mut set = Set::new();
+        unsafe { hb_face_collect_variation_selectors(self.as_raw(), set.as_raw_mut()) };
+        set
+    }
+
+    /// Returns the set of all unicode codepoints that have a mapping for the
+    /// given variation selector in the face's character map.
+    pub fn variation_unicodes(&self, variation_selector: char) -> Owned<Set> {
+        let mut set = Set::new();
+        unsafe {
+            hb_face_collect_variation_unicodes(
+                self.as_raw(),
+                variation_selector as u32,
+                set.as_raw_mut(),
+            )
+        };
+        set
+    }
+
     #[cfg(variation_support)]
     pub fn get_variation_axis_infos(&self) -> Vec<VariationAxisInfo> {
         let mut count = unsafe { hb_ot_var_get_axis_count(self.as_raw()) };
@@ -204,4 +277,27 @@ mod tests {
         let maxp_table = face.table_with_tag(b"hhea").unwrap();
         assert_eq!(&maxp_table.as_ref(), b"hhea-table");
     }
+
+    #[test]
+    fn test_face_unicode_coverage() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face: Shared<Face<'_>> = Face::from_file(path, 0).unwrap().into();
+        let font = crate::Font::new(face.clone());
+
+        let unicodes = face.collect_unicodes();
+        assert!(unicodes.contains('a' as u32));
+        assert!(!unicodes.contains('\u{0627}' as u32));
+
+        let mapping = face.nominal_glyph_mapping();
+        assert_eq!(mapping.len(), unicodes.len());
+        assert_eq!(mapping.get(&'a').copied(), font.get_nominal_glyph('a'));
+
+        for selector in face.variation_selectors().iter() {
+            let selector = std::char::from_u32(selector).unwrap();
+            for unicode in face.variation_unicodes(selector).iter() {
+                let unicode = std::char::from_u32(unicode).unwrap();
+                assert!(font.get_variation_glyph(unicode, selector).is_some());
+            }
+        }
+    }
 }
@@ -0,0 +1,371 @@
+use std::ops::Range;
+use std::os::raw::c_uint;
+
+use harfbuzz_bindings::HB_SCRIPT_INVALID;
+
+use crate::buffer::{BufferFlags, GlyphBuffer, SegmentProperties, UnicodeBuffer};
+use crate::common::{Script, Shared};
+use crate::font::Font;
+use crate::{shape, Feature};
+
+/// An ordered list of fonts used to shape text that is not supported by a
+/// single font.
+///
+/// The first font is the primary font, all others are fallback fonts that
+/// are tried in order for text the fonts before them have no glyphs for.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let fonts = FontStack::new(Font::new(face)).add_fallback(Font::empty());
+///
+/// let runs = shape_with_fallback(&fonts, "Hello \u{1F600}", &[]);
+/// assert_eq!(runs[0].font_index, 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FontStack<'a> {
+    fonts: Vec<Shared<Font<'a>>>,
+}
+
+impl<'a> FontStack<'a> {
+    /// Creates a font stack containing only the primary font.
+    pub fn new(primary: impl Into<Shared<Font<'a>>>) -> FontStack<'a> {
+        FontStack {
+            fonts: vec![primary.into()],
+        }
+    }
+
+    /// Appends a fallback font to the stack.
+    pub fn add_fallback(mut self, font: impl Into<Shared<Font<'a>>>) -> FontStack<'a> {
+        self.fonts.push(font.into());
+        self
+    }
+
+    /// Returns the number of fonts in the stack, including the primary font.
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Returns `true` if the stack contains no fonts.
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// The font used before all others.
+    pub fn primary(&self) -> &Shared<Font<'a>> {
+        &self.fonts[0]
+    }
+
+    /// Returns the font at `index`, with index 0 being the primary font.
+    pub fn get(&self, index: usize) -> Option<&Shared<Font<'a>>> {
+        self.fonts.get(index)
+    }
+
+    /// Returns an iterator over the fonts, starting with the primary font.
+    pub fn iter(&self) -> std::slice::Iter<'_, Shared<Font<'a>>> {
+        self.fonts.iter()
+    }
+}
+
+/// A part of a text shaped with a single font of a `FontStack`.
+#[derive(Debug)]
+pub struct FontRun {
+    /// The index of the font in the `FontStack`.
+    pub font_index: usize,
+    /// The byte range of the text shaped in this run.
+    pub text_range: Range<usize>,
+    /// The shaped glyphs. Their cluster values are byte offsets into the
+    /// whole text.
+    pub glyphs: GlyphBuffer,
+}
+
+/// Shapes `text` using the fonts of `fonts`, falling back to later fonts for
+/// the parts of the text the earlier ones have no glyphs for.
+///
+/// The text is shaped with the primary font first. Every cluster that
+/// contains a `.notdef` glyph is shaped again with the next font, so
+/// combining marks always use the same font as their base character. Text
+/// that no font supports keeps the `.notdef` glyphs of the primary font.
+///
+/// The segment properties are guessed from the whole text. Text shaped with a
+/// fallback font uses the script and direction of its own characters instead,
+/// so e.g. Arabic text in a Latin paragraph is shaped right-to-left. The
+/// returned runs are in the same order as the glyphs of a single
+/// `GlyphBuffer`, so they are in reverse text order for right-to-left text.
+/// Adjacent parts shaped with the same font and direction are merged into one
+/// run.
+pub fn shape_with_fallback(
+    fonts: &FontStack<'_>,
+    text: &str,
+    features: &[Feature],
+) -> Vec<FontRun> {
+    let mut runs = Vec::new();
+    if text.is_empty() {
+        return runs;
+    }
+    let props = UnicodeBuffer::new()
+        .add_str(text)
+        .guess_segment_properties()
+        .get_segment_properties();
+    let backward = props.direction.is_backward();
+    let fallback = Fallback {
+        fonts,
+        text,
+        props,
+        features,
+    };
+    fallback.shape(0, 0..text.len(), &mut runs);
+    if backward {
+        runs.reverse();
+    }
+    runs
+}
+
+struct Fallback<'b> {
+    fonts: &'b FontStack<'b>,
+    text: &'b str,
+    props: SegmentProperties,
+    features: &'b [Feature],
+}
+
+impl<'b> Fallback<'b> {
+    /// Shapes the text in `range` with the font at `font_index` and the fonts
+    /// after it and appends the resulting runs to `runs` in text order.
+    fn shape(&self, font_index: usize, range: Range<usize>, runs: &mut Vec<FontRun>) {
+        if font_index == self.fonts.len() {
+            // No font supports the text, keep the glyphs of the primary font.
+            let glyphs = self.shape_range(0, range.clone());
+            self.push(runs, 0, range, glyphs);
+            return;
+        }
+        let glyphs = self.shape_range(font_index, range.clone());
+        let segments = coverage(&glyphs, range.end);
+        if segments.iter().all(|&(_, missing)| !missing) {
+            self.push(runs, font_index, range, glyphs);
+            return;
+        }
+        let font = &self.fonts.fonts[font_index];
+        for (segment, missing) in segments {
+            if missing {
+                self.shape(font_index + 1, segment, runs);
+            } else {
+                let segment_glyphs =
+                    glyphs.reshape_range(font, self.text, segment.clone(), self.features);
+                self.push(runs, font_index, segment, segment_glyphs);
+            }
+        }
+    }
+
+    /// The segment properties of the text in `range`. The script and
+    /// direction are guessed from the characters in `range` unless these are
+    /// all common or inherited characters.
+    fn segment_properties(&self, range: Range<usize>) -> SegmentProperties {
+        let guessed = UnicodeBuffer::new()
+            .add_str(&self.text[range])
+            .guess_segment_properties()
+            .get_segment_properties();
+        if guessed.script == Script(HB_SCRIPT_INVALID) {
+            return self.props;
+        }
+        SegmentProperties {
+            language: self.props.language,
+            ..guessed
+        }
+    }
+
+    fn shape_range(&self, font_index: usize, range: Range<usize>) -> GlyphBuffer {
+        let mut flags = BufferFlags::PRODUCE_UNSAFE_TO_CONCAT;
+        if range.start == 0 {
+            flags |= BufferFlags::BOT;
+        }
+        if range.end == self.text.len() {
+            flags |= BufferFlags::EOT;
+        }
+        let props = self.segment_properties(range.clone());
+        let mut buffer = UnicodeBuffer::new()
+            .add_str_item(self.text, &self.text[range])
+            .set_flags(flags);
+        buffer.0.set_segment_properties(props);
+        shape(&self.fonts.fonts[font_index], buffer, self.features)
+    }
+
+    fn push(
+        &self,
+        runs: &mut Vec<FontRun>,
+        font_index: usize,
+        text_range: Range<usize>,
+        mut glyphs: GlyphBuffer,
+    ) {
+        if let Some(last) = runs.last_mut() {
+            let direction = glyphs.0.get_direction();
+            if last.font_index == font_index
+                && last.text_range.end == text_range.start
+                && last.glyphs.0.get_direction() == direction
+            {
+                last.text_range.end = text_range.end;
+                if direction.is_backward() {
+                    let len = last.glyphs.len() as c_uint;
+                    glyphs.0.append(&last.glyphs.0, 0, len);
+                    last.glyphs = glyphs;
+                } else {
+                    let len = glyphs.len() as c_uint;
+                    last.glyphs.0.append(&glyphs.0, 0, len);
+                }
+                return;
+            }
+        }
+        runs.push(FontRun {
+            font_index,
+            text_range,
+            glyphs,
+        });
+    }
+}
+
+/// Splits the text shaped in `glyphs` into the ranges that are covered by the
+/// font and the ranges that contain `.notdef` glyphs, in text order.
+fn coverage(glyphs: &GlyphBuffer, text_end: usize) -> Vec<(Range<usize>, bool)> {
+    let infos = glyphs.get_glyph_infos();
+    let mut spans: Vec<_> = glyphs
+        .clusters(text_end)
+        .map(|span| {
+            let missing = infos[span.glyph_range]
+                .iter()
+                .any(|info| info.codepoint == 0);
+            (span.text_range, missing)
+        })
+        .collect();
+    spans.sort_by_key(|(range, _)| range.start);
+    let mut segments: Vec<(Range<usize>, bool)> = Vec::new();
+    for (range, missing) in spans {
+        match segments.last_mut() {
+            Some((segment, segment_missing)) if *segment_missing == missing => {
+                segment.end = range.end;
+            }
+            _ => segments.push((range, missing)),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::Glyph;
+    use crate::font_funcs::FontFuncs;
+    use crate::test_util::{font, font_with_funcs, identity_font};
+
+    // Supports Hebrew letters and combining diacritical marks.
+    struct HebrewFont;
+
+    impl FontFuncs for HebrewFont {
+        fn get_nominal_glyph(&self, _: &Font<'_>, unicode: char) -> Option<Glyph> {
+            match unicode {
+                '\u{5D0}'..='\u{5EA}' | '\u{300}'..='\u{36F}' => Some(unicode as u32),
+                _ => None,
+            }
+        }
+    }
+
+    fn font_stack() -> FontStack<'static> {
+        let primary = font();
+        let hebrew = font_with_funcs(HebrewFont);
+        FontStack::new(primary).add_fallback(hebrew)
+    }
+
+    fn summary(runs: &[FontRun]) -> Vec<(usize, Range<usize>, Vec<u32>)> {
+        runs.iter()
+            .map(|run| {
+                let infos = run.glyphs.get_glyph_infos();
+                let glyphs = infos.iter().map(|info| info.codepoint).collect();
+                (run.font_index, run.text_range.clone(), glyphs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shape_with_fallback() {
+        let identity = identity_font();
+        let fonts = font_stack().add_fallback(identity);
+        assert_eq!(fonts.len(), 3);
+
+        let text = "a\u{5D0}\u{5D1}b\u{5D2}\u{301}c\u{1F600}";
+        let runs = shape_with_fallback(&fonts, text, &[]);
+        let ranges: Vec<_> = runs
+            .iter()
+            .map(|run| (run.font_index, run.text_range.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 0..1),
+                (1, 1..5),
+                (0, 5..6),
+                (1, 6..10),
+                (0, 10..11),
+                (2, 11..15)
+            ]
+        );
+        let summary = summary(&runs);
+        // the Hebrew runs are shaped right-to-left
+        assert_eq!(summary[1].2, vec![0x5D1, 0x5D0]);
+        // the combining mark stays with its base
+        assert_eq!(summary[3].2, vec![0x301, 0x5D2]);
+        assert_eq!(summary[5].2, vec![0x1F600]);
+        let primary = fonts.primary();
+        assert_eq!(summary[0].2, vec![primary.get_nominal_glyph('a').unwrap()]);
+
+        assert!(shape_with_fallback(&fonts, "", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_text_keeps_primary_font() {
+        let fonts = font_stack();
+        let runs = shape_with_fallback(&fonts, "a\u{1F600}b", &[]);
+        // all parts use the primary font, so they are merged into one run
+        assert_eq!(summary(&runs).len(), 1);
+        assert_eq!(runs[0].font_index, 0);
+        assert_eq!(runs[0].text_range, 0..6);
+        assert_eq!(runs[0].glyphs.get_glyph_infos()[1].codepoint, 0);
+    }
+
+    #[test]
+    fn test_fallback_runs_in_visual_order() {
+        let fonts = font_stack();
+        let text = "\u{5D0}a\u{5D1}";
+        let runs = shape_with_fallback(&fonts, text, &[]);
+        let summary = summary(&runs);
+        let ranges: Vec<_> = summary.iter().map(|run| (run.0, run.1.clone())).collect();
+        assert_eq!(ranges, vec![(1, 3..5), (0, 2..3), (1, 0..2)]);
+        assert_eq!(summary[0].2, vec![0x5D1]);
+    }
+
+    #[test]
+    fn test_fallback_segment_properties() {
+        const INITIAL_BEH: u32 = 0xFE91;
+        const FINAL_BEH: u32 = 0xFE90;
+        let primary = font();
+        let identity = identity_font();
+        let fonts = FontStack::new(primary).add_fallback(identity);
+
+        // The Arabic text is shaped right-to-left and joined even though the
+        // text starts with Latin letters.
+        let text = "ab \u{628}\u{628}";
+        let runs = shape_with_fallback(&fonts, text, &[]);
+        let summary = summary(&runs);
+        let ranges: Vec<_> = summary.iter().map(|run| (run.0, run.1.clone())).collect();
+        assert_eq!(ranges, vec![(0, 0..3), (1, 3..7)]);
+        assert_eq!(summary[1].2, vec![FINAL_BEH, INITIAL_BEH]);
+        let clusters: Vec<_> = runs[1]
+            .glyphs
+            .get_glyph_infos()
+            .iter()
+            .map(|info| info.cluster)
+            .collect();
+        assert_eq!(clusters, vec![5, 3]);
+    }
+}
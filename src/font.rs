@@ -3,22 +3,29 @@ use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use harfbuzz_bindings::{
     hb_font_create, hb_font_create_sub_font, hb_font_destroy, hb_font_extents_t, hb_font_get_empty,
     hb_font_get_face, hb_font_get_glyph_contour_point, hb_font_get_glyph_extents,
-    hb_font_get_glyph_from_name, hb_font_get_glyph_h_advance, hb_font_get_glyph_h_origin,
-    hb_font_get_glyph_name, hb_font_get_glyph_v_advance, hb_font_get_glyph_v_origin,
-    hb_font_get_h_extents, hb_font_get_nominal_glyph, hb_font_get_parent, hb_font_get_ppem,
-    hb_font_get_scale, hb_font_get_v_extents, hb_font_get_variation_glyph, hb_font_reference,
-    hb_font_set_funcs, hb_font_set_ppem, hb_font_set_scale, hb_font_set_variations, hb_font_t,
-    hb_glyph_extents_t, hb_position_t,
+    hb_font_get_glyph_from_name, hb_font_get_glyph_h_advance, hb_font_get_glyph_h_advances,
+    hb_font_get_glyph_h_kerning, hb_font_get_glyph_h_origin, hb_font_get_glyph_name,
+    hb_font_get_glyph_v_advance, hb_font_get_glyph_v_advances, hb_font_get_glyph_v_origin,
+    hb_font_get_h_extents, hb_font_get_nominal_glyph, hb_font_get_nominal_glyphs,
+    hb_font_get_parent, hb_font_get_ppem, hb_font_get_ptem, hb_font_get_scale, hb_font_get_serial,
+    hb_font_get_synthetic_bold, hb_font_get_synthetic_slant, hb_font_get_user_data,
+    hb_font_get_v_extents, hb_font_get_variation_glyph, hb_font_paint_glyph, hb_font_reference,
+    hb_font_set_funcs, hb_font_set_ppem, hb_font_set_ptem, hb_font_set_scale,
+    hb_font_set_synthetic_bold, hb_font_set_synthetic_slant, hb_font_set_user_data,
+    hb_font_set_variations, hb_font_t, hb_glyph_extents_t, hb_position_t, hb_user_data_key_t,
 };
 
 use crate::common::{HarfbuzzObject, Owned, Shared};
+use crate::draw::DrawFuncs;
 use crate::face::Face;
 pub use crate::font_funcs::FontFuncs;
 use crate::font_funcs::FontFuncsImpl;
+use crate::paint::{Color, Paint};
 use crate::Variation;
 
 pub type Glyph = u32;
@@ -54,6 +61,20 @@ impl FontExtents {
 
 pub type GlyphExtents = hb_glyph_extents_t;
 
+/// Identifies a `Font` together with the current state of its settings.
+///
+/// See [`Font::identity()`] for details.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FontIdentity {
+    id: usize,
+    serial: u32,
+}
+
+// The address of this key identifies the user data that stores the id of a
+// font.
+static FONT_ID_KEY: hb_user_data_key_t = hb_user_data_key_t { unused: 0 };
+static NEXT_FONT_ID: AtomicUsize = AtomicUsize::new(1);
+
 pub(crate) extern "C" fn destroy_box<U>(ptr: *mut c_void) {
     unsafe { Box::from_raw(ptr as *mut U) };
 }
@@ -243,6 +264,132 @@ impl<'a> Font<'a> {
         unsafe { hb_font_set_ppem(self.as_raw_mut(), x, y) };
     }
 
+    /// Returns the point size of the font. A value of zero means unset.
+    pub fn ptem(&self) -> f32 {
+        unsafe { hb_font_get_ptem(self.as_raw()) }
+    }
+
+    /// Sets the point size of the font.
+    ///
+    /// The point size is used for optical size selection (e.g. for fonts with
+    /// an AAT `trak` table). It does not change the font's scale.
+    pub fn set_ptem(&mut self, ptem: f32) {
+        unsafe { hb_font_set_ptem(self.as_raw_mut(), ptem) };
+    }
+
+    /// Returns the synthetic emboldening settings of the font as a tuple of
+    /// `(x_embolden, y_embolden, in_place)`.
+    pub fn synthetic_bold(&self) -> (f32, f32, bool) {
+        let mut result = (0f32, 0f32, 0);
+        unsafe {
+            hb_font_get_synthetic_bold(self.as_raw(), &mut result.0, &mut result.1, &mut result.2)
+        };
+        (result.0, result.1, result.2 != 0)
+    }
+
+    /// Makes the font synthetically bold ("faux bold").
+    ///
+    /// `x_embolden` and `y_embolden` are the amounts by which glyph outlines
+    /// are grown in each direction, relative to the em size (a typical value
+    /// is `0.02`). Setting both to zero disables emboldening.
+    ///
+    /// Emboldening affects glyph outlines, glyph extents and, unless
+    /// `in_place` is `true`, the glyph advances used during shaping. Use
+    /// `in_place` to make text bolder without changing its layout.
+    ///
+    /// The values HarfBuzz reads from custom [`FontFuncs`] are emboldened in
+    /// the same way. Note that sub-fonts inherit this setting, so when both a
+    /// font and its parent are emboldened values forwarded from the parent
+    /// are emboldened twice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    /// # use std::path::PathBuf;
+    /// # let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    /// # path.push("testfiles/SourceSansVariable-Roman.ttf");
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let mut font = Font::new(face);
+    /// let glyph = font.get_nominal_glyph('l').unwrap();
+    /// let regular_advance = font.get_glyph_h_advance(glyph);
+    ///
+    /// font.set_synthetic_bold(0.02, 0.02, false);
+    /// assert!(font.get_glyph_h_advance(glyph) > regular_advance);
+    /// ```
+    pub fn set_synthetic_bold(&mut self, x_embolden: f32, y_embolden: f32, in_place: bool) {
+        unsafe {
+            hb_font_set_synthetic_bold(self.as_raw_mut(), x_embolden, y_embolden, in_place as _)
+        };
+    }
+
+    /// Returns the synthetic slant of the font.
+    pub fn synthetic_slant(&self) -> f32 {
+        unsafe { hb_font_get_synthetic_slant(self.as_raw()) }
+    }
+
+    /// Makes the font synthetically oblique ("faux italic").
+    ///
+    /// `slant` is the ratio of the horizontal shift to the height, e.g. `0.2`
+    /// slants glyphs by roughly 11 degrees to the right. Slanting affects
+    /// glyph outlines and the glyph offsets produced by shaping (e.g. mark
+    /// positioning), but not glyph advances.
+    pub fn set_synthetic_slant(&mut self, slant: f32) {
+        unsafe { hb_font_set_synthetic_slant(self.as_raw_mut(), slant) };
+    }
+
+    /// Returns the serial number of the font.
+    ///
+    /// The serial number changes every time a setting of the font is changed,
+    /// e.g. its scale, variations or font funcs.
+    pub fn serial(&self) -> u32 {
+        unsafe { hb_font_get_serial(self.as_raw()) }
+    }
+
+    /// Returns a value that identifies this font with its current settings.
+    ///
+    /// All references to a font have the same identity. Changing a setting
+    /// of the font that can influence shaping, like its scale or variations,
+    /// changes its identity. Identities of different fonts are never equal,
+    /// even if one of them has been dropped already. This makes identities
+    /// suitable as keys for caching shaping results.
+    ///
+    /// Note that changes of the parent font are not reflected in the
+    /// identity of a sub font.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let mut font = Font::new(face);
+    ///
+    /// let identity = font.identity();
+    /// assert_eq!(font.identity(), identity);
+    /// font.set_variations(&[Variation::new(b"wght", 800.0)]);
+    /// assert_ne!(font.identity(), identity);
+    /// ```
+    pub fn identity(&self) -> FontIdentity {
+        let key = &FONT_ID_KEY as *const hb_user_data_key_t as *mut hb_user_data_key_t;
+        let id = unsafe {
+            let mut id = hb_font_get_user_data(self.as_raw(), key) as usize;
+            if id == 0 {
+                // Storing the id fails if another thread stored one first or
+                // if this is the empty font, which cannot be modified.
+                let new_id = NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed);
+                hb_font_set_user_data(self.as_raw(), key, new_id as *mut c_void, None, 0);
+                id = hb_font_get_user_data(self.as_raw(), key) as usize;
+            }
+            id
+        };
+        FontIdentity {
+            id,
+            serial: self.serial(),
+        }
+    }
+
     /// Sets the font functions that this font will have from a value that
     /// implements [`FontFuncs`].
     pub fn set_font_funcs<FuncsType>(&mut self, funcs: FuncsType)
@@ -293,6 +440,28 @@ impl<'a> Font<'a> {
         }
     }
 
+    // scale factors from parent font
+    pub(crate) fn parent_scale_factors(&self) -> (f32, f32) {
+        let scale = self.scale();
+        match self.parent() {
+            Some(parent) => {
+                let parent_scale = parent.scale();
+                let factor = |scale: i32, parent_scale: i32| {
+                    if parent_scale == 0 {
+                        0.0
+                    } else {
+                        scale as f32 / parent_scale as f32
+                    }
+                };
+                (
+                    factor(scale.0, parent_scale.0),
+                    factor(scale.1, parent_scale.1),
+                )
+            }
+            None => (0.0, 0.0),
+        }
+    }
+
     // scale from parent font
     pub(crate) fn parent_scale_position(&self, v: (Position, Position)) -> (Position, Position) {
         (
@@ -339,6 +508,41 @@ impl<'a> Font<'a> {
         }
     }
 
+    /// Get the nominal glyphs for a slice of unicode characters at once.
+    ///
+    /// The glyphs are written to `glyphs`. Returns the number of glyphs that
+    /// were found before the first character that has no glyph in this font.
+    /// At most `min(unicodes.len(), glyphs.len())` glyphs are looked up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    /// # use std::path::PathBuf;
+    /// # let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    /// # path.push("testfiles/SourceSansVariable-Roman.ttf");
+    /// let face = Face::from_file(path, 0).expect("Error reading font file.");
+    /// let font = Font::new(face);
+    ///
+    /// let mut glyphs = [0; 3];
+    /// let found = font.get_nominal_glyphs(&['a', 'b', 'c'], &mut glyphs);
+    /// assert_eq!(found, 3);
+    /// assert_eq!(Some(glyphs[1]), font.get_nominal_glyph('b'));
+    /// ```
+    pub fn get_nominal_glyphs(&self, unicodes: &[char], glyphs: &mut [Glyph]) -> usize {
+        let count = unicodes.len().min(glyphs.len());
+        unsafe {
+            hb_font_get_nominal_glyphs(
+                self.as_raw(),
+                count as u32,
+                unicodes.as_ptr() as *const _,
+                std::mem::size_of::<char>() as u32,
+                glyphs.as_mut_ptr(),
+                std::mem::size_of::<Glyph>() as u32,
+            ) as usize
+        }
+    }
+
     pub fn get_variation_glyph(&self, c: char, v: char) -> Option<Glyph> {
         unsafe {
             let mut glyph = 0;
@@ -361,6 +565,53 @@ impl<'a> Font<'a> {
         unsafe { hb_font_get_glyph_v_advance(self.as_raw(), glyph) }
     }
 
+    /// Get the horizontal advance widths of a slice of glyphs at once.
+    ///
+    /// The advances are written to `advances`. Only the first
+    /// `min(glyphs.len(), advances.len())` glyphs are looked up.
+    pub fn get_glyph_h_advances(&self, glyphs: &[Glyph], advances: &mut [Position]) {
+        let count = glyphs.len().min(advances.len());
+        unsafe {
+            hb_font_get_glyph_h_advances(
+                self.as_raw(),
+                count as u32,
+                glyphs.as_ptr(),
+                std::mem::size_of::<Glyph>() as u32,
+                advances.as_mut_ptr(),
+                std::mem::size_of::<Position>() as u32,
+            )
+        }
+    }
+
+    /// Get the vertical advance widths of a slice of glyphs at once.
+    ///
+    /// The advances are written to `advances`. Only the first
+    /// `min(glyphs.len(), advances.len())` glyphs are looked up.
+    pub fn get_glyph_v_advances(&self, glyphs: &[Glyph], advances: &mut [Position]) {
+        let count = glyphs.len().min(advances.len());
+        unsafe {
+            hb_font_get_glyph_v_advances(
+                self.as_raw(),
+                count as u32,
+                glyphs.as_ptr(),
+                std::mem::size_of::<Glyph>() as u32,
+                advances.as_mut_ptr(),
+                std::mem::size_of::<Position>() as u32,
+            )
+        }
+    }
+
+    /// Get the horizontal kerning between two glyphs as provided by the font
+    /// funcs.
+    ///
+    /// HarfBuzz' own font funcs always return zero here, as kerning of
+    /// OpenType fonts is applied from the `GPOS` and `kern` tables during
+    /// shaping. Custom font funcs may provide kerning values that HarfBuzz uses
+    /// as a fallback for fonts without any kerning tables.
+    pub fn get_glyph_h_kerning(&self, left_glyph: Glyph, right_glyph: Glyph) -> Position {
+        unsafe { hb_font_get_glyph_h_kerning(self.as_raw(), left_glyph, right_glyph) }
+    }
+
     pub fn get_glyph_h_origin(&self, glyph: Glyph) -> Option<(Position, Position)> {
         unsafe {
             let mut pos = (0, 0);
@@ -437,6 +688,39 @@ impl<'a> Font<'a> {
         }
     }
 
+    /// Draw the outline of `glyph` into `funcs`.
+    ///
+    /// The outline is scaled according to the font's scale and synthetic
+    /// settings. Glyphs without an outline (e.g. the space glyph) produce no
+    /// drawing commands.
+    pub fn draw_glyph(&self, glyph: Glyph, funcs: &mut dyn DrawFuncs) {
+        crate::draw::draw_glyph(self, glyph, funcs)
+    }
+
+    /// Paint the color glyph `glyph` using the paint operations of `paint`.
+    ///
+    /// `palette_index` selects the color palette (`CPAL`) to use and
+    /// `foreground` is the color that is used for foreground color layers.
+    pub fn paint_glyph(
+        &self,
+        glyph: Glyph,
+        paint: &mut Paint<'_>,
+        palette_index: u32,
+        foreground: Color,
+    ) {
+        let (funcs, data) = paint.as_raw();
+        unsafe {
+            hb_font_paint_glyph(
+                self.as_raw(),
+                glyph,
+                funcs,
+                data,
+                palette_index,
+                foreground.0,
+            )
+        }
+    }
+
     pub fn get_glyph_from_name(&self, name: &str) -> Option<Glyph> {
         unsafe {
             let mut glyph = 0;
@@ -531,4 +815,182 @@ mod test {
     fn test_font_extents_layout() {
         assert_memory_layout_equal::<FontExtents, hb_font_extents_t>()
     }
+
+    // Records the bounding box of all points of an outline.
+    struct Bounds {
+        x_min: f32,
+        x_max: f32,
+        y_max: f32,
+    }
+
+    impl Bounds {
+        fn of(font: &Font<'_>, glyph: Glyph) -> Bounds {
+            let mut bounds = Bounds {
+                x_min: f32::INFINITY,
+                x_max: f32::NEG_INFINITY,
+                y_max: f32::NEG_INFINITY,
+            };
+            font.draw_glyph(glyph, &mut bounds);
+            bounds
+        }
+
+        fn add(&mut self, x: f32, y: f32) {
+            self.x_min = self.x_min.min(x);
+            self.x_max = self.x_max.max(x);
+            self.y_max = self.y_max.max(y);
+        }
+    }
+
+    impl DrawFuncs for Bounds {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.add(x, y)
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.add(x, y)
+        }
+        fn quadratic_to(&mut self, _: f32, _: f32, x: f32, y: f32) {
+            self.add(x, y)
+        }
+        fn cubic_to(&mut self, _: f32, _: f32, _: f32, _: f32, x: f32, y: f32) {
+            self.add(x, y)
+        }
+        fn close_path(&mut self) {}
+    }
+
+    #[test]
+    fn test_synthetic_bold() {
+        let mut font = test_font();
+        let glyph = font.get_nominal_glyph('l').unwrap();
+        let advance = font.get_glyph_h_advance(glyph);
+        let extents = font.get_glyph_extents(glyph).unwrap();
+        let bounds = Bounds::of(&font, glyph);
+
+        font.set_synthetic_bold(0.02, 0.02, false);
+        assert_eq!(font.synthetic_bold(), (0.02, 0.02, false));
+        // the font's scale is 1000 units per em, so outlines grow by 20 units
+        assert_eq!(font.get_glyph_h_advance(glyph), advance + 20);
+        assert_eq!(
+            font.get_glyph_extents(glyph).unwrap().width,
+            extents.width + 20
+        );
+        let bold_bounds = Bounds::of(&font, glyph);
+        assert!(bold_bounds.x_max - bold_bounds.x_min > bounds.x_max - bounds.x_min + 19.0);
+
+        let output = crate::shape(&font, crate::UnicodeBuffer::new().add_str("l"), &[]);
+        assert_eq!(output.get_glyph_positions()[0].x_advance, advance + 20);
+
+        font.set_synthetic_bold(0.02, 0.02, true);
+        assert_eq!(font.get_glyph_h_advance(glyph), advance);
+    }
+
+    #[test]
+    fn test_synthetic_slant_and_ptem() {
+        let mut font = test_font();
+        let glyph = font.get_nominal_glyph('l').unwrap();
+        let bounds = Bounds::of(&font, glyph);
+
+        font.set_synthetic_slant(0.2);
+        assert_eq!(font.synthetic_slant(), 0.2);
+        let slanted_bounds = Bounds::of(&font, glyph);
+        // the top of the glyph is shifted to the right
+        assert!(slanted_bounds.x_max > bounds.x_max + 0.2 * bounds.y_max * 0.9);
+
+        assert_eq!(font.ptem(), 0.0);
+        font.set_ptem(12.0);
+        assert_eq!(font.ptem(), 12.0);
+    }
+
+    #[test]
+    fn test_font_identity() {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face: Shared<Face<'_>> = Face::from_file(path, 0).unwrap().into();
+        let mut font = Font::new(face.clone());
+        let other = Font::new(face);
+        let identity = font.identity();
+        assert_eq!(font.identity(), identity);
+        assert_ne!(other.identity(), identity);
+
+        font.set_scale(100, 100);
+        let scaled = font.identity();
+        assert_ne!(scaled, identity);
+
+        let shared: Shared<Font<'_>> = font.into();
+        assert_eq!(shared.clone().identity(), scaled);
+        assert_eq!(Font::empty().identity(), Font::empty().identity());
+    }
+
+    fn test_font() -> Owned<Font<'static>> {
+        let path = "testfiles/SourceSansVariable-Roman.ttf";
+        let face = Face::from_file(path, 0).unwrap();
+        Font::new(face)
+    }
+
+    #[test]
+    fn test_batched_queries_match_singular_ones() {
+        let font = test_font();
+
+        let text: Vec<char> = "Hello\u{0627}World".chars().collect();
+        let mut glyphs = vec![0; text.len()];
+        // the arabic letter is not covered by the font
+        assert_eq!(font.get_nominal_glyphs(&text, &mut glyphs), 5);
+        for (&c, &glyph) in text.iter().zip(&glyphs).take(5) {
+            assert_eq!(font.get_nominal_glyph(c), Some(glyph));
+        }
+
+        let mut h_advances = vec![0; 5];
+        let mut v_advances = vec![0; 5];
+        font.get_glyph_h_advances(&glyphs[..5], &mut h_advances);
+        font.get_glyph_v_advances(&glyphs[..5], &mut v_advances);
+        for (i, &glyph) in glyphs[..5].iter().enumerate() {
+            assert_eq!(h_advances[i], font.get_glyph_h_advance(glyph));
+            assert_eq!(v_advances[i], font.get_glyph_v_advance(glyph));
+        }
+    }
+
+    struct BatchedFuncs;
+
+    impl FontFuncs for BatchedFuncs {
+        fn get_nominal_glyphs(
+            &self,
+            _font: &Font<'_>,
+            unicodes: &[char],
+            glyphs: &mut [Glyph],
+        ) -> usize {
+            for (&c, glyph) in unicodes.iter().zip(glyphs.iter_mut()) {
+                *glyph = c as Glyph;
+            }
+            unicodes.len()
+        }
+
+        fn get_glyph_h_advances(
+            &self,
+            _font: &Font<'_>,
+            glyphs: &[Glyph],
+            advances: &mut [Position],
+        ) {
+            for (&glyph, advance) in glyphs.iter().zip(advances) {
+                *advance = glyph as Position * 10;
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_font_funcs() {
+        let mut font = Font::create_sub_font(test_font());
+        font.set_font_funcs(BatchedFuncs);
+
+        let mut glyphs = [0; 2];
+        assert_eq!(font.get_nominal_glyphs(&['a', 'b'], &mut glyphs), 2);
+        assert_eq!(glyphs, ['a' as Glyph, 'b' as Glyph]);
+
+        let mut advances = [0; 2];
+        font.get_glyph_h_advances(&glyphs, &mut advances);
+        assert_eq!(advances, [970, 980]);
+
+        let output = crate::shape(&font, crate::UnicodeBuffer::new().add_str("ab"), &[]);
+        let infos = output.get_glyph_infos();
+        let positions = output.get_glyph_positions();
+        assert_eq!(infos[1].codepoint, 'b' as Glyph);
+        assert_eq!(positions[1].x_advance, 980);
+    }
 }
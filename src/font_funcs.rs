@@ -12,23 +12,31 @@ use std::ffi::{CStr, CString};
 use std::fmt;
 use std::io::Write;
 use std::marker::PhantomData;
-use std::os::raw::c_void;
+use std::os::raw::{c_uint, c_void};
 use std::panic;
 use std::ptr::NonNull;
 
 use harfbuzz_bindings::{
-    hb_bool_t, hb_codepoint_t, hb_font_extents_t, hb_font_funcs_create, hb_font_funcs_destroy,
-    hb_font_funcs_get_empty, hb_font_funcs_reference, hb_font_funcs_set_font_h_extents_func,
+    hb_bool_t, hb_codepoint_t, hb_color_t, hb_draw_funcs_t, hb_font_extents_t,
+    hb_font_funcs_create, hb_font_funcs_destroy, hb_font_funcs_get_empty, hb_font_funcs_reference,
+    hb_font_funcs_set_draw_glyph_func, hb_font_funcs_set_font_h_extents_func,
     hb_font_funcs_set_font_v_extents_func, hb_font_funcs_set_glyph_contour_point_func,
     hb_font_funcs_set_glyph_extents_func, hb_font_funcs_set_glyph_from_name_func,
-    hb_font_funcs_set_glyph_h_advance_func, hb_font_funcs_set_glyph_h_origin_func,
+    hb_font_funcs_set_glyph_h_advance_func, hb_font_funcs_set_glyph_h_advances_func,
+    hb_font_funcs_set_glyph_h_kerning_func, hb_font_funcs_set_glyph_h_origin_func,
     hb_font_funcs_set_glyph_name_func, hb_font_funcs_set_glyph_v_advance_func,
-    hb_font_funcs_set_glyph_v_origin_func, hb_font_funcs_set_nominal_glyph_func,
-    hb_font_funcs_set_variation_glyph_func, hb_font_funcs_t, hb_font_t, hb_glyph_extents_t,
+    hb_font_funcs_set_glyph_v_advances_func, hb_font_funcs_set_glyph_v_origin_func,
+    hb_font_funcs_set_nominal_glyph_func, hb_font_funcs_set_nominal_glyphs_func,
+    hb_font_funcs_set_paint_glyph_func, hb_font_funcs_set_variation_glyph_func, hb_font_funcs_t,
+    hb_font_t, hb_glyph_extents_t, hb_paint_funcs_t,
 };
 
-use crate::{Font, FontExtents, Glyph, GlyphExtents, HarfbuzzObject, Owned, Position, Shared};
+use crate::draw::{RawDraw, ScaledDraw};
 use crate::font::destroy_box;
+use crate::{
+    Color, DrawFuncs, Font, FontExtents, Glyph, GlyphExtents, HarfbuzzObject, Owned, Paint,
+    Position, Shared,
+};
 
 /// This Trait specifies the font callbacks that harfbuzz uses for its shaping.
 ///
@@ -75,6 +83,24 @@ pub trait FontFuncs {
     fn get_nominal_glyph(&self, font: &Font<'_>, unicode: char) -> Option<Glyph> {
         font.parent()?.get_nominal_glyph(unicode)
     }
+    /// Batched version of `get_nominal_glyph`. Returns the number of glyphs
+    /// found before the first unicode without a glyph.
+    ///
+    /// The default implementation calls `get_nominal_glyph` for every unicode.
+    fn get_nominal_glyphs(
+        &self,
+        font: &Font<'_>,
+        unicodes: &[char],
+        glyphs: &mut [Glyph],
+    ) -> usize {
+        for (index, (&unicode, glyph)) in unicodes.iter().zip(glyphs.iter_mut()).enumerate() {
+            match self.get_nominal_glyph(font, unicode) {
+                Some(result) => *glyph = result,
+                None => return index,
+            }
+        }
+        unicodes.len().min(glyphs.len())
+    }
     fn get_variation_glyph(
         &self,
         font: &Font<'_>,
@@ -89,6 +115,34 @@ pub trait FontFuncs {
     fn get_glyph_v_advance(&self, font: &Font<'_>, glyph: Glyph) -> Position {
         font.parent_scale_y_distance(|parent| parent.get_glyph_v_advance(glyph))
     }
+    /// Batched version of `get_glyph_h_advance`.
+    ///
+    /// The default implementation calls `get_glyph_h_advance` for every glyph.
+    fn get_glyph_h_advances(&self, font: &Font<'_>, glyphs: &[Glyph], advances: &mut [Position]) {
+        for (&glyph, advance) in glyphs.iter().zip(advances) {
+            *advance = self.get_glyph_h_advance(font, glyph);
+        }
+    }
+    /// Batched version of `get_glyph_v_advance`.
+    ///
+    /// The default implementation calls `get_glyph_v_advance` for every glyph.
+    fn get_glyph_v_advances(&self, font: &Font<'_>, glyphs: &[Glyph], advances: &mut [Position]) {
+        for (&glyph, advance) in glyphs.iter().zip(advances) {
+            *advance = self.get_glyph_v_advance(font, glyph);
+        }
+    }
+    /// Horizontal kerning between two glyphs.
+    ///
+    /// HarfBuzz only consults this function when shaping with a font that has
+    /// no `GPOS` kerning and no `kern` table.
+    fn get_glyph_h_kerning(
+        &self,
+        font: &Font<'_>,
+        left_glyph: Glyph,
+        right_glyph: Glyph,
+    ) -> Position {
+        font.parent_scale_x_distance(|parent| parent.get_glyph_h_kerning(left_glyph, right_glyph))
+    }
     fn get_glyph_h_origin(&self, font: &Font<'_>, glyph: Glyph) -> Option<(Position, Position)> {
         font.parent()?
             .get_glyph_h_origin(glyph)
@@ -125,6 +179,29 @@ pub trait FontFuncs {
     fn get_glyph_from_name(&self, font: &Font<'_>, name: &str) -> Option<Glyph> {
         font.parent()?.get_glyph_from_name(name)
     }
+    /// Draw the outline of `glyph` into `draw`.
+    fn draw_glyph(&self, font: &Font<'_>, glyph: Glyph, draw: &mut dyn DrawFuncs) {
+        if let Some(parent) = font.parent() {
+            let (x_scale, y_scale) = font.parent_scale_factors();
+            parent.draw_glyph(glyph, &mut ScaledDraw::new(draw, x_scale, y_scale));
+        }
+    }
+    /// Paint the color glyph `glyph` using `paint`.
+    fn paint_glyph(
+        &self,
+        font: &Font<'_>,
+        glyph: Glyph,
+        paint: &mut Paint<'_>,
+        palette_index: u32,
+        foreground: Color,
+    ) {
+        if let Some(parent) = font.parent() {
+            let (x_scale, y_scale) = font.parent_scale_factors();
+            paint.push_transform(x_scale, 0.0, 0.0, y_scale, 0.0, 0.0);
+            parent.paint_glyph(glyph, paint, palette_index, foreground);
+            paint.pop_transform();
+        }
+    }
 }
 
 macro_rules! hb_callback {
@@ -229,6 +306,142 @@ hb_callback!(
     }
 );
 
+// Returns a pointer to the `index`th element of a strided array. HarfBuzz
+// passes strides in bytes.
+unsafe fn strided<U>(first: *const U, stride: c_uint, index: usize) -> *const U {
+    (first as *const u8).add(stride as usize * index) as *const U
+}
+
+unsafe fn strided_mut<U>(first: *mut U, stride: c_uint, index: usize) -> *mut U {
+    (first as *mut u8).add(stride as usize * index) as *mut U
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn rust_get_nominal_glyphs_closure<T, F>(
+    font: *mut hb_font_t,
+    font_data: *mut c_void,
+    count: c_uint,
+    first_unicode: *const hb_codepoint_t,
+    unicode_stride: c_uint,
+    first_glyph: *mut hb_codepoint_t,
+    glyph_stride: c_uint,
+    closure_data: *mut c_void,
+) -> c_uint
+where
+    F: Fn(&Font<'_>, &T, &[char], &mut [Glyph]) -> usize,
+{
+    let catch_result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let font_data = unsafe { &*(font_data as *const T) };
+        let font = unsafe { Font::from_raw(font) };
+        let closure = unsafe { &mut *(closure_data as *mut F) };
+        // stop at the first invalid codepoint, it cannot have a glyph anyway
+        let unicodes: Vec<char> = (0..count as usize)
+            .map(|i| unsafe { *strided(first_unicode, unicode_stride, i) })
+            .map_while(std::char::from_u32)
+            .collect();
+        let mut glyphs = vec![0; unicodes.len()];
+        let found = closure(&font, font_data, &unicodes, &mut glyphs).min(glyphs.len());
+        for (i, &glyph) in glyphs[..found].iter().enumerate() {
+            unsafe { *strided_mut(first_glyph, glyph_stride, i) = glyph };
+        }
+        found as c_uint
+    }));
+    match catch_result {
+        Ok(val) => val,
+        Err(_) => {
+            // TODO: Log error
+            0
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn rust_get_glyph_advances_closure<T, F>(
+    font: *mut hb_font_t,
+    font_data: *mut c_void,
+    count: c_uint,
+    first_glyph: *const hb_codepoint_t,
+    glyph_stride: c_uint,
+    first_advance: *mut Position,
+    advance_stride: c_uint,
+    closure_data: *mut c_void,
+) where
+    F: Fn(&Font<'_>, &T, &[Glyph], &mut [Position]),
+{
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let font_data = unsafe { &*(font_data as *const T) };
+        let font = unsafe { Font::from_raw(font) };
+        let closure = unsafe { &mut *(closure_data as *mut F) };
+        let glyphs: Vec<Glyph> = (0..count as usize)
+            .map(|i| unsafe { *strided(first_glyph, glyph_stride, i) })
+            .collect();
+        let mut advances = vec![0; glyphs.len()];
+        closure(&font, font_data, &glyphs, &mut advances);
+        for (i, &advance) in advances.iter().enumerate() {
+            unsafe { *strided_mut(first_advance, advance_stride, i) = advance };
+        }
+    }));
+}
+
+hb_callback!(
+    rust_get_glyph_kerning_closure<
+        first_glyph: hb_codepoint_t,
+        second_glyph: hb_codepoint_t>
+    -> Position {
+        argument Glyph => first_glyph,
+        argument Glyph => second_glyph,
+        return pos: Position => pos
+    }
+);
+
+extern "C" fn rust_draw_glyph_closure<T, F>(
+    font: *mut hb_font_t,
+    font_data: *mut c_void,
+    glyph: hb_codepoint_t,
+    draw_funcs: *mut hb_draw_funcs_t,
+    draw_data: *mut c_void,
+    closure_data: *mut c_void,
+) where
+    F: Fn(&Font<'_>, &T, Glyph, &mut dyn DrawFuncs),
+{
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let font_data = unsafe { &*(font_data as *const T) };
+        let font = unsafe { Font::from_raw(font) };
+        let closure = unsafe { &mut *(closure_data as *mut F) };
+        let mut draw = RawDraw::new(draw_funcs, draw_data);
+        closure(&font, font_data, glyph, &mut draw);
+    }));
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn rust_paint_glyph_closure<T, F>(
+    font: *mut hb_font_t,
+    font_data: *mut c_void,
+    glyph: hb_codepoint_t,
+    paint_funcs: *mut hb_paint_funcs_t,
+    paint_data: *mut c_void,
+    palette_index: c_uint,
+    foreground: hb_color_t,
+    closure_data: *mut c_void,
+) where
+    F: Fn(&Font<'_>, &T, Glyph, &mut Paint<'_>, u32, Color),
+{
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let font_data = unsafe { &*(font_data as *const T) };
+        let font = unsafe { Font::from_raw(font) };
+        let closure = unsafe { &mut *(closure_data as *mut F) };
+        let mut paint = unsafe { Paint::from_raw(paint_funcs, paint_data) };
+        closure(
+            &font,
+            font_data,
+            glyph,
+            &mut paint,
+            palette_index,
+            Color(foreground),
+        );
+    }));
+}
+
 hb_callback!(
     rust_get_glyph_origin_closure<
         glyph: hb_codepoint_t,
@@ -436,11 +649,20 @@ impl<T: FontFuncs> FontFuncsImpl<T> {
         self.set_font_h_extents_func(|font, data| data.get_font_h_extents(font));
         self.set_font_v_extents_func(|font, data| data.get_font_v_extents(font));
         self.set_nominal_glyph_func(|font, data, chr| data.get_nominal_glyph(font, chr));
+        self.set_nominal_glyphs_func(|font, data, chrs, glyphs| {
+            data.get_nominal_glyphs(font, chrs, glyphs)
+        });
         self.set_variation_glyph_func(|font, data, chr, var| {
             data.get_variation_glyph(font, chr, var)
         });
         self.set_glyph_h_advance_func(|font, data, glyph| data.get_glyph_h_advance(font, glyph));
         self.set_glyph_v_advance_func(|font, data, glyph| data.get_glyph_v_advance(font, glyph));
+        self.set_glyph_h_advances_func(|font, data, glyphs, advances| {
+            data.get_glyph_h_advances(font, glyphs, advances)
+        });
+        self.set_glyph_v_advances_func(|font, data, glyphs, advances| {
+            data.get_glyph_v_advances(font, glyphs, advances)
+        });
         self.set_glyph_h_origin_func(|font, data, glyph| data.get_glyph_h_origin(font, glyph));
         self.set_glyph_v_origin_func(|font, data, glyph| data.get_glyph_v_origin(font, glyph));
         self.set_glyph_extents_func(|font, data, glyph| data.get_glyph_extents(font, glyph));
@@ -449,6 +671,13 @@ impl<T: FontFuncs> FontFuncsImpl<T> {
         });
         self.set_glyph_name_func(|font, data, glyph| data.get_glyph_name(font, glyph));
         self.set_glyph_from_name_func(|font, data, name| data.get_glyph_from_name(font, name));
+        self.set_glyph_h_kerning_func(|font, data, left, right| {
+            data.get_glyph_h_kerning(font, left, right)
+        });
+        self.set_draw_glyph_func(|font, data, glyph, draw| data.draw_glyph(font, glyph, draw));
+        self.set_paint_glyph_func(|font, data, glyph, paint, palette, foreground| {
+            data.paint_glyph(font, glyph, paint, palette, foreground)
+        });
     }
 }
 
@@ -502,6 +731,21 @@ impl<T> FontFuncsImpl<T> {
         }
     }
 
+    pub fn set_nominal_glyphs_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, &[char], &mut [Glyph]) -> usize,
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_nominal_glyphs_func(
+                self.as_raw(),
+                Some(rust_get_nominal_glyphs_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
+
     pub fn set_variation_glyph_func<F>(&mut self, func: F)
         where
             F: Fn(&Font<'_>, &T, char, char) -> Option<Glyph>,
@@ -547,6 +791,51 @@ impl<T> FontFuncsImpl<T> {
         }
     }
 
+    pub fn set_glyph_h_advances_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, &[Glyph], &mut [Position]),
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_glyph_h_advances_func(
+                self.as_raw(),
+                Some(rust_get_glyph_advances_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
+
+    pub fn set_glyph_v_advances_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, &[Glyph], &mut [Position]),
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_glyph_v_advances_func(
+                self.as_raw(),
+                Some(rust_get_glyph_advances_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
+
+    pub fn set_glyph_h_kerning_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, Glyph, Glyph) -> Position,
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_glyph_h_kerning_func(
+                self.as_raw(),
+                Some(rust_get_glyph_kerning_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
+
     pub fn set_glyph_h_origin_func<F>(&mut self, func: F)
         where
             F: Fn(&Font<'_>, &T, Glyph) -> Option<(Position, Position)>,
@@ -636,6 +925,36 @@ impl<T> FontFuncsImpl<T> {
             );
         }
     }
+
+    pub fn set_draw_glyph_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, Glyph, &mut dyn DrawFuncs),
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_draw_glyph_func(
+                self.as_raw(),
+                Some(rust_draw_glyph_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
+
+    pub fn set_paint_glyph_func<F>(&mut self, func: F)
+    where
+        F: Fn(&Font<'_>, &T, Glyph, &mut Paint<'_>, u32, Color),
+    {
+        let user_data = Box::new(func);
+        unsafe {
+            hb_font_funcs_set_paint_glyph_func(
+                self.as_raw(),
+                Some(rust_paint_glyph_closure::<T, F>),
+                Box::into_raw(user_data) as *mut _,
+                Some(destroy_box::<F>),
+            );
+        }
+    }
 }
 
 impl<T> fmt::Debug for FontFuncsImpl<T> {
@@ -672,3 +991,164 @@ unsafe impl<T> HarfbuzzObject for FontFuncsImpl<T> {
 unsafe impl<T> Send for FontFuncsImpl<T> {}
 
 unsafe impl<T> Sync for FontFuncsImpl<T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_void;
+
+    use harfbuzz_bindings::{
+        hb_bool_t, hb_color_t, hb_paint_funcs_create, hb_paint_funcs_destroy,
+        hb_paint_funcs_set_color_func, hb_paint_funcs_t,
+    };
+
+    use crate::{shape, Face, UnicodeBuffer};
+
+    use super::*;
+
+    // A font without any tables whose data is entirely provided by font funcs.
+    struct SyntheticFont;
+
+    impl FontFuncs for SyntheticFont {
+        fn get_nominal_glyph(&self, _: &Font<'_>, unicode: char) -> Option<Glyph> {
+            match unicode {
+                'A' => Some(1),
+                'V' => Some(2),
+                _ => None,
+            }
+        }
+
+        fn get_glyph_h_advance(&self, _: &Font<'_>, _: Glyph) -> Position {
+            500
+        }
+
+        fn get_glyph_h_kerning(&self, _: &Font<'_>, left: Glyph, right: Glyph) -> Position {
+            if (left, right) == (1, 2) {
+                -100
+            } else {
+                0
+            }
+        }
+
+        fn draw_glyph(&self, _: &Font<'_>, glyph: Glyph, draw: &mut dyn DrawFuncs) {
+            // a square whose size depends on the glyph, left open on purpose
+            let size = glyph as f32 * 100.0;
+            draw.move_to(0.0, 0.0);
+            draw.line_to(size, 0.0);
+            draw.line_to(size, size);
+            draw.line_to(0.0, size);
+        }
+
+        fn paint_glyph(
+            &self,
+            _: &Font<'_>,
+            _: Glyph,
+            paint: &mut Paint<'_>,
+            _: u32,
+            foreground: Color,
+        ) {
+            paint.color(true, foreground);
+        }
+    }
+
+    // Uses the default implementations for everything.
+    struct Forwarding;
+
+    impl FontFuncs for Forwarding {}
+
+    fn synthetic_font() -> Owned<Font<'static>> {
+        let face = Face::from_table_func(|_| None);
+        let mut font = Font::new(face);
+        font.set_font_funcs(SyntheticFont);
+        font
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl DrawFuncs for Recorder {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("M{},{}", x, y));
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("L{},{}", x, y));
+        }
+        fn quadratic_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) {
+            self.0.push(format!("Q{},{} {},{}", cx, cy, x, y));
+        }
+        fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) {
+            self.0
+                .push(format!("C{},{} {},{} {},{}", c1x, c1y, c2x, c2y, x, y));
+        }
+        fn close_path(&mut self) {
+            self.0.push("Z".to_string());
+        }
+    }
+
+    #[test]
+    fn test_kerning_func() {
+        let font = synthetic_font();
+        assert_eq!(font.get_glyph_h_kerning(1, 2), -100);
+
+        let output = shape(&font, UnicodeBuffer::new().add_str("AV"), &[]);
+        let total_advance: Position = output
+            .get_glyph_positions()
+            .iter()
+            .map(|pos| pos.x_advance)
+            .sum();
+        assert_eq!(total_advance, 900);
+    }
+
+    #[test]
+    fn test_draw_func() {
+        let font = synthetic_font();
+        let mut recorder = Recorder::default();
+        font.draw_glyph(1, &mut recorder);
+        assert_eq!(
+            recorder.0,
+            vec!["M0,0", "L100,0", "L100,100", "L0,100", "L0,0", "Z"]
+        );
+    }
+
+    #[test]
+    fn test_draw_func_forwards_to_parent() {
+        let mut parent = synthetic_font();
+        parent.set_scale(1000, 1000);
+        let mut font = Font::create_sub_font(parent);
+        font.set_scale(2000, 2000);
+        font.set_font_funcs(Forwarding);
+
+        let mut recorder = Recorder::default();
+        font.draw_glyph(1, &mut recorder);
+        assert_eq!(
+            recorder.0,
+            vec!["M0,0", "L200,0", "L200,200", "L0,200", "L0,0", "Z"]
+        );
+    }
+
+    extern "C" fn record_color(
+        _: *mut hb_paint_funcs_t,
+        paint_data: *mut c_void,
+        is_foreground: hb_bool_t,
+        color: hb_color_t,
+        _: *mut c_void,
+    ) {
+        let colors = unsafe { &mut *(paint_data as *mut Vec<(bool, Color)>) };
+        colors.push((is_foreground != 0, Color(color)));
+    }
+
+    #[test]
+    fn test_paint_func() {
+        let font = synthetic_font();
+        let foreground = Color::new(0x10, 0x20, 0x30, 0xff);
+
+        let mut colors: Vec<(bool, Color)> = Vec::new();
+        unsafe {
+            let funcs = hb_paint_funcs_create();
+            hb_paint_funcs_set_color_func(funcs, Some(record_color), std::ptr::null_mut(), None);
+            let mut paint = Paint::from_raw(funcs, &mut colors as *mut _ as *mut c_void);
+            font.paint_glyph(1, &mut paint, 0, foreground);
+            hb_paint_funcs_destroy(funcs);
+        }
+        assert_eq!(colors, vec![(true, foreground)]);
+    }
+}
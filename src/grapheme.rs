@@ -0,0 +1,460 @@
+use std::ops::Range;
+
+use crate::buffer::GlyphBuffer;
+use crate::unicode_funcs::{GeneralCategory, UnicodeFuncs};
+
+/// Grapheme cluster break properties of UAX #29.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum GraphemeClass {
+    CR,
+    LF,
+    Control,
+    Extend,
+    ZWJ,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    LV,
+    LVT,
+    ExtendedPictographic,
+    Other,
+}
+
+/// Indic conjunct break properties of UAX #29.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum IndicConjunct {
+    Consonant,
+    Linker,
+    Extend,
+}
+
+fn grapheme_class(funcs: &UnicodeFuncs, c: char) -> GraphemeClass {
+    use self::GraphemeClass::*;
+    match c {
+        '\r' => return CR,
+        '\n' => return LF,
+        '\u{200D}' => return ZWJ,
+        '\u{200C}'
+        | '\u{FF9E}'
+        | '\u{FF9F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}' => return Extend,
+        '\u{1F1E6}'..='\u{1F1FF}' => return RegionalIndicator,
+        '\u{0600}'..='\u{0605}'
+        | '\u{06DD}'
+        | '\u{070F}'
+        | '\u{0890}'
+        | '\u{0891}'
+        | '\u{08E2}'
+        | '\u{0D4E}'
+        | '\u{110BD}'
+        | '\u{110CD}'
+        | '\u{111C2}'
+        | '\u{111C3}' => return Prepend,
+        '\u{0E33}' | '\u{0EB3}' => return SpacingMark,
+        '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => return L,
+        '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => return V,
+        '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => return T,
+        '\u{AC00}'..='\u{D7A3}' if (c as u32 - 0xAC00) % 28 == 0 => return LV,
+        '\u{AC00}'..='\u{D7A3}' => return LVT,
+        _ if is_extended_pictographic(c) => return ExtendedPictographic,
+        _ => {}
+    }
+    match funcs.general_category(c) {
+        GeneralCategory::Control
+        | GeneralCategory::Format
+        | GeneralCategory::LineSeparator
+        | GeneralCategory::ParagraphSeparator => Control,
+        GeneralCategory::NonSpacingMark | GeneralCategory::EnclosingMark => Extend,
+        GeneralCategory::SpacingMark => SpacingMark,
+        _ => Other,
+    }
+}
+
+fn is_extended_pictographic(c: char) -> bool {
+    match c {
+        '\u{A9}'
+        | '\u{AE}'
+        | '\u{203C}'
+        | '\u{2049}'
+        | '\u{2122}'
+        | '\u{2139}'
+        | '\u{2194}'..='\u{2199}'
+        | '\u{21A9}'
+        | '\u{21AA}'
+        | '\u{231A}'
+        | '\u{231B}'
+        | '\u{2328}'
+        | '\u{2388}'
+        | '\u{23CF}'
+        | '\u{23E9}'..='\u{23F3}'
+        | '\u{23F8}'..='\u{23FA}'
+        | '\u{24C2}'
+        | '\u{25AA}'
+        | '\u{25AB}'
+        | '\u{25B6}'
+        | '\u{25C0}'
+        | '\u{25FB}'..='\u{25FE}'
+        | '\u{2600}'..='\u{27BF}'
+        | '\u{2934}'
+        | '\u{2935}'
+        | '\u{2B05}'..='\u{2B07}'
+        | '\u{2B1B}'
+        | '\u{2B1C}'
+        | '\u{2B50}'
+        | '\u{2B55}'
+        | '\u{3030}'
+        | '\u{303D}'
+        | '\u{3297}'
+        | '\u{3299}'
+        | '\u{1F000}'..='\u{1F0FF}'
+        | '\u{1F10D}'..='\u{1F10F}'
+        | '\u{1F12F}'
+        | '\u{1F16C}'..='\u{1F171}'
+        | '\u{1F17E}'
+        | '\u{1F17F}'
+        | '\u{1F18E}'
+        | '\u{1F191}'..='\u{1F19A}'
+        | '\u{1F1AD}'..='\u{1F1E5}'
+        | '\u{1F201}'..='\u{1F20F}'
+        | '\u{1F21A}'
+        | '\u{1F22F}'
+        | '\u{1F232}'..='\u{1F23A}'
+        | '\u{1F23C}'..='\u{1F23F}'
+        | '\u{1F249}'..='\u{1F3FA}'
+        | '\u{1F400}'..='\u{1F53D}'
+        | '\u{1F546}'..='\u{1F64F}'
+        | '\u{1F680}'..='\u{1F6FF}'
+        | '\u{1F774}'..='\u{1F77F}'
+        | '\u{1F7D5}'..='\u{1F7FF}'
+        | '\u{1F80C}'..='\u{1F80F}'
+        | '\u{1F848}'..='\u{1F84F}'
+        | '\u{1F85A}'..='\u{1F85F}'
+        | '\u{1F888}'..='\u{1F88F}'
+        | '\u{1F8AE}'..='\u{1F8FF}'
+        | '\u{1F90C}'..='\u{1F93A}'
+        | '\u{1F93C}'..='\u{1F945}'
+        | '\u{1F947}'..='\u{1FAFF}'
+        | '\u{1FC00}'..='\u{1FFFD}' => true,
+        _ => false,
+    }
+}
+
+/// The Indic conjunct break property of `c`. Consonants and linkers are
+/// those of the scripts listed in UAX #29, the extending characters are
+/// approximated by the combining marks with a non-zero combining class.
+fn indic_conjunct(funcs: &UnicodeFuncs, c: char) -> Option<IndicConjunct> {
+    match c {
+        '\u{094D}' | '\u{09CD}' | '\u{0ACD}' | '\u{0B4D}' | '\u{0C4D}' | '\u{0D4D}' => {
+            Some(IndicConjunct::Linker)
+        }
+        '\u{0915}'..='\u{0939}'
+        | '\u{0958}'..='\u{095F}'
+        | '\u{0978}'..='\u{097F}'
+        | '\u{0995}'..='\u{09A8}'
+        | '\u{09AA}'..='\u{09B0}'
+        | '\u{09B2}'
+        | '\u{09B6}'..='\u{09B9}'
+        | '\u{09DC}'
+        | '\u{09DD}'
+        | '\u{09DF}'
+        | '\u{09F0}'
+        | '\u{09F1}'
+        | '\u{0A95}'..='\u{0AA8}'
+        | '\u{0AAA}'..='\u{0AB0}'
+        | '\u{0AB2}'
+        | '\u{0AB3}'
+        | '\u{0AB5}'..='\u{0AB9}'
+        | '\u{0AF9}'
+        | '\u{0B15}'..='\u{0B28}'
+        | '\u{0B2A}'..='\u{0B30}'
+        | '\u{0B32}'
+        | '\u{0B33}'
+        | '\u{0B35}'..='\u{0B39}'
+        | '\u{0B5C}'
+        | '\u{0B5D}'
+        | '\u{0B5F}'
+        | '\u{0B71}'
+        | '\u{0C15}'..='\u{0C28}'
+        | '\u{0C2A}'..='\u{0C39}'
+        | '\u{0C58}'..='\u{0C5A}'
+        | '\u{0D15}'..='\u{0D3A}' => Some(IndicConjunct::Consonant),
+        '\u{200D}' => Some(IndicConjunct::Extend),
+        _ if funcs.combining_class(c) != 0 => Some(IndicConjunct::Extend),
+        _ => None,
+    }
+}
+
+/// The context of the current grapheme that the rules GB9c, GB11 and GB12/13
+/// depend on.
+#[derive(Debug, Default, Copy, Clone)]
+struct Context {
+    /// The number of regional indicators directly before the position.
+    regional_indicators: usize,
+    /// Whether an extended pictographic character followed by extending
+    /// characters is directly before the position.
+    pictographic: bool,
+    /// Whether `pictographic` was true before the last character, which is a
+    /// zero width joiner.
+    pictographic_zwj: bool,
+    /// Whether an Indic consonant followed by extending characters is
+    /// directly before the position.
+    consonant: bool,
+    /// Whether `consonant` was true before a linker that is followed by
+    /// extending characters and linkers up to the position.
+    consonant_linker: bool,
+}
+
+impl Context {
+    fn advance(&mut self, class: GraphemeClass, conjunct: Option<IndicConjunct>) {
+        self.regional_indicators = match class {
+            GraphemeClass::RegionalIndicator => self.regional_indicators + 1,
+            _ => 0,
+        };
+        self.pictographic_zwj = self.pictographic && class == GraphemeClass::ZWJ;
+        self.pictographic = match class {
+            GraphemeClass::ExtendedPictographic => true,
+            GraphemeClass::Extend => self.pictographic,
+            _ => false,
+        };
+        let (consonant, linker) = (self.consonant, self.consonant_linker);
+        match conjunct {
+            Some(IndicConjunct::Consonant) => {
+                self.consonant = true;
+                self.consonant_linker = false;
+            }
+            Some(IndicConjunct::Linker) => self.consonant_linker = consonant || linker,
+            Some(IndicConjunct::Extend) => {}
+            None => {
+                self.consonant = false;
+                self.consonant_linker = false;
+            }
+        }
+    }
+}
+
+/// Whether there is a grapheme boundary between characters of the classes
+/// `before` and `after`.
+fn is_break(
+    before: GraphemeClass,
+    after: GraphemeClass,
+    after_conjunct: Option<IndicConjunct>,
+    context: &Context,
+) -> bool {
+    use self::GraphemeClass::*;
+    match (before, after) {
+        (CR, LF) => false,
+        (CR, _) | (LF, _) | (Control, _) | (_, CR) | (_, LF) | (_, Control) => true,
+        (L, L) | (L, V) | (L, LV) | (L, LVT) => false,
+        (LV, V) | (LV, T) | (V, V) | (V, T) => false,
+        (LVT, T) | (T, T) => false,
+        (_, Extend) | (_, ZWJ) | (_, SpacingMark) | (Prepend, _) => false,
+        (_, _) if context.consonant_linker && after_conjunct == Some(IndicConjunct::Consonant) => {
+            false
+        }
+        (ZWJ, ExtendedPictographic) if context.pictographic_zwj => false,
+        (RegionalIndicator, RegionalIndicator) => context.regional_indicators % 2 == 0,
+        _ => true,
+    }
+}
+
+/// Returns the end of the grapheme that starts at `start`.
+fn grapheme_end(funcs: &UnicodeFuncs, text: &str, start: usize) -> usize {
+    let mut chars = text[start..].char_indices();
+    let mut before = match chars.next() {
+        Some((_, c)) => {
+            let class = grapheme_class(funcs, c);
+            let mut context = Context::default();
+            context.advance(class, indic_conjunct(funcs, c));
+            (class, context)
+        }
+        None => return start,
+    };
+    for (offset, c) in chars {
+        let (before_class, mut context) = before;
+        let class = grapheme_class(funcs, c);
+        let conjunct = indic_conjunct(funcs, c);
+        if is_break(before_class, class, conjunct, &context) {
+            return start + offset;
+        }
+        context.advance(class, conjunct);
+        before = (class, context);
+    }
+    text.len()
+}
+
+/// Returns the extended grapheme clusters of `text` as byte ranges.
+///
+/// The boundaries follow the rules of [UAX #29][uax29] including emoji zero
+/// width joiner sequences, pairs of regional indicators and Indic conjuncts.
+/// The character properties are taken from HarfBuzz's default unicode funcs
+/// and tables of this crate, which cover the characters relevant to these
+/// rules, but are not complete.
+///
+/// At the default cluster level, HarfBuzz merges the clusters of a grapheme
+/// when shaping, so the clusters of a `GlyphBuffer` usually contain whole
+/// graphemes. Use `GlyphBuffer::is_grapheme_boundary` to check this.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::graphemes;
+///
+/// let text = "e\u{301}\u{1F1E9}\u{1F1EA}\r\n";
+/// let ranges: Vec<_> = graphemes(text).collect();
+/// assert_eq!(ranges, vec![0..3, 3..11, 11..13]);
+/// ```
+///
+/// [uax29]: https://www.unicode.org/reports/tr29/
+pub fn graphemes(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let funcs = UnicodeFuncs::default_funcs();
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start >= text.len() {
+            return None;
+        }
+        let end = grapheme_end(&funcs, text, start);
+        let grapheme = start..end;
+        start = end;
+        Some(grapheme)
+    })
+}
+
+/// Returns whether the byte offset `offset` of `text` is a grapheme
+/// boundary. The start and the end of the text are grapheme boundaries.
+pub fn is_grapheme_boundary(text: &str, offset: usize) -> bool {
+    if offset == 0 || offset == text.len() {
+        return true;
+    }
+    if !text.is_char_boundary(offset) {
+        return false;
+    }
+    graphemes(text)
+        .take_while(|grapheme| grapheme.start <= offset)
+        .any(|grapheme| grapheme.start == offset)
+}
+
+impl GlyphBuffer {
+    /// Returns whether there is a cluster boundary before the glyph with the
+    /// index `glyph` that is also a grapheme boundary of `text`.
+    ///
+    /// `text` is the text the buffer was shaped from, so that the cluster
+    /// values are byte offsets into `text`. The start and the end of the
+    /// buffer, i.e. `glyph == 0` and `glyph == self.len()`, are boundaries.
+    /// Returns `false` for glyphs inside of a cluster.
+    ///
+    /// Breaking the buffer only at these boundaries, e.g. when splitting it
+    /// into lines or replacing glyphs, keeps every grapheme in one piece.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("could not load face");
+    /// let font = Font::new(face);
+    ///
+    /// let text = "x\u{301}y";
+    /// let buffer = shape(&font, UnicodeBuffer::new().add_str(text), &[]);
+    /// assert_eq!(buffer.len(), 3);
+    /// // the glyph of the accent belongs to the grapheme of "x"
+    /// assert!(!buffer.is_grapheme_boundary(text, 1));
+    /// assert!(buffer.is_grapheme_boundary(text, 2));
+    /// ```
+    pub fn is_grapheme_boundary(&self, text: &str, glyph: usize) -> bool {
+        if glyph == 0 || glyph == self.len() {
+            return true;
+        }
+        let backward = self.0.get_direction().is_backward();
+        self.clusters(text.len())
+            .find(|span| span.glyph_range.start == glyph)
+            .map_or(false, |span| {
+                let offset = if backward {
+                    span.text_range.end
+                } else {
+                    span.text_range.start
+                };
+                is_grapheme_boundary(text, offset)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{font, shaped};
+    use crate::{shape, ClusterLevel, UnicodeBuffer};
+
+    fn ranges(text: &str) -> Vec<Range<usize>> {
+        graphemes(text).collect()
+    }
+
+    #[test]
+    fn test_graphemes() {
+        assert_eq!(ranges(""), vec![]);
+        assert_eq!(ranges("ab"), vec![0..1, 1..2]);
+        assert_eq!(ranges("e\u{301}\u{302}x"), vec![0..5, 5..6]);
+        assert_eq!(ranges("a\r\nb"), vec![0..1, 1..3, 3..4]);
+        assert_eq!(ranges("\n\u{301}"), vec![0..1, 1..3]);
+        // prepended concatenation mark
+        assert_eq!(ranges("\u{600}1"), vec![0..3]);
+        // Hangul jamo and syllables
+        assert_eq!(ranges("\u{1100}\u{1161}\u{11A8}"), vec![0..9]);
+        assert_eq!(ranges("\u{AC00}\u{11A8}\u{1100}"), vec![0..6, 6..9]);
+    }
+
+    #[test]
+    fn test_emoji() {
+        // family: man, woman, girl joined by zero width joiners
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(ranges(family), vec![0..family.len()]);
+        // thumbs up with skin tone modifier
+        assert_eq!(ranges("\u{1F44D}\u{1F3FD}"), vec![0..8]);
+        // a zero width joiner only joins pictographs
+        assert_eq!(ranges("a\u{200D}b"), vec![0..4, 4..5]);
+        // flags are pairs of regional indicators
+        let flags = "\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}\u{1F1E9}";
+        assert_eq!(ranges(flags), vec![0..8, 8..16, 16..20]);
+    }
+
+    #[test]
+    fn test_indic_conjuncts() {
+        // Devanagari ksha with vowel sign i
+        assert_eq!(ranges("\u{915}\u{94D}\u{937}\u{93F}"), vec![0..12]);
+        // a linker without a preceding consonant does not join
+        assert_eq!(ranges("a\u{94D}\u{937}"), vec![0..4, 4..7]);
+        assert!(is_grapheme_boundary("\u{915}\u{915}", 3));
+        assert!(!is_grapheme_boundary("\u{915}\u{94D}\u{915}", 6));
+        assert!(!is_grapheme_boundary("\u{915}", 1));
+    }
+
+    #[test]
+    fn test_glyph_buffer_boundaries() {
+        let font = font();
+        let text = "x\u{301}y";
+        let buffer = shaped(&font, text);
+        let boundaries: Vec<_> = (0..=buffer.len())
+            .map(|glyph| buffer.is_grapheme_boundary(text, glyph))
+            .collect();
+        assert_eq!(boundaries, vec![true, false, true, true]);
+
+        // at the character cluster level the accent has a cluster of its own
+        let buffer = UnicodeBuffer::new()
+            .set_cluster_level(ClusterLevel::Characters)
+            .add_str(text);
+        let buffer = shape(&font, buffer, &[]);
+        assert!(!buffer.is_grapheme_boundary(text, 1));
+
+        let text = "\u{5D0}\u{5B8}\u{5D1}";
+        let buffer = shaped(&font, text);
+        // the glyphs are in visual order: bet, alef, qamats
+        assert_eq!(buffer.len(), 3);
+        assert!(buffer.is_grapheme_boundary(text, 1));
+        assert!(!buffer.is_grapheme_boundary(text, 2));
+    }
+}
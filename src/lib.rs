@@ -83,19 +83,53 @@ use harfbuzz_bindings::{hb_feature_t, hb_shape, hb_variation_t};
 
 pub use crate::blob::*;
 pub use crate::buffer::*;
+pub use crate::cache::*;
 pub use crate::common::*;
+pub use crate::decoration::*;
+pub use crate::draw::*;
 pub use crate::face::*;
+pub use crate::fallback::*;
 pub use crate::font::*;
+pub use crate::grapheme::*;
+pub use crate::paint::*;
+pub use crate::paragraph::*;
+pub use crate::set::*;
 pub use crate::subset::*;
+pub use crate::svg::*;
+pub use crate::tracking::*;
+pub use crate::vertical::*;
 
+mod bidi;
 mod blob;
 mod buffer;
+mod cache;
 mod common;
+mod decoration;
+mod draw;
 mod face;
+mod fallback;
 mod font;
+mod grapheme;
+mod paint;
+mod paragraph;
+mod selection;
+mod set;
 mod subset;
+mod svg;
+mod tracking;
+mod vertical;
+pub mod caret;
 pub mod font_funcs;
+pub mod linebreak;
+pub mod unicode_funcs;
 
+#[cfg(test)]
+mod test_util;
+
+#[cfg(feature = "raster")]
+pub mod atlas;
+#[cfg(feature = "raster")]
+pub mod raster;
 #[cfg(feature = "rusttype")]
 pub mod rusttype;
 
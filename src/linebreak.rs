@@ -0,0 +1,624 @@
+//! Line breaking of shaped paragraphs.
+//!
+//! This module finds the positions where a paragraph of text may be broken
+//! into lines (following a simplified version of the [Unicode Line Breaking
+//! Algorithm][uax14]) and chooses the lines using the advances of the shaped
+//! text.
+//!
+//! # Examples
+//!
+//! ```
+//! use harfbuzz_rs::*;
+//! use harfbuzz_rs::linebreak::LineBreaker;
+//!
+//! let path = "testfiles/SourceSansVariable-Roman.ttf";
+//! let face = Face::from_file(path, 0).expect("could not load face");
+//! let font = Font::new(face);
+//!
+//! let text = "The quick brown fox jumps over the lazy dog.";
+//! let breaker = LineBreaker::new(&font, text, &[]);
+//! for line in breaker.greedy(5000) {
+//!     assert!(line.width <= 5000);
+//!     println!("{:?}: {}", line.text_range, &text[line.text_range.clone()]);
+//! }
+//! ```
+//!
+//! [uax14]: https://www.unicode.org/reports/tr14/
+
+use std::ops::Range;
+
+use harfbuzz_bindings::{
+    hb_unicode_funcs_get_default, hb_unicode_general_category,
+    HB_UNICODE_GENERAL_CATEGORY_CLOSE_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_CONTROL,
+    HB_UNICODE_GENERAL_CATEGORY_DECIMAL_NUMBER, HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK,
+    HB_UNICODE_GENERAL_CATEGORY_FINAL_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_FORMAT,
+    HB_UNICODE_GENERAL_CATEGORY_INITIAL_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK,
+    HB_UNICODE_GENERAL_CATEGORY_OPEN_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR,
+    HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK,
+};
+
+use crate::buffer::{BufferFlags, ClusterSpan, GlyphBuffer, UnicodeBuffer};
+use crate::font::{Font, Position};
+use crate::{shape, Feature};
+
+/// A position in a text where a line may start.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BreakOpportunity {
+    /// The byte offset of the break in the text.
+    pub offset: usize,
+    /// Whether the line must be broken here, e.g. after a newline character.
+    pub mandatory: bool,
+}
+
+/// Line breaking classes of UAX #14 that are distinguished by this module.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Class {
+    BK,
+    CR,
+    LF,
+    NL,
+    SP,
+    ZW,
+    ZWJ,
+    CM,
+    WJ,
+    GL,
+    OP,
+    CL,
+    CP,
+    QU,
+    EX,
+    IS,
+    SY,
+    HY,
+    BA,
+    BB,
+    B2,
+    NS,
+    IN,
+    NU,
+    PR,
+    PO,
+    ID,
+    AL,
+}
+
+fn line_break_class(c: char) -> Class {
+    use self::Class::*;
+    match c {
+        '\n' => return LF,
+        '\r' => return CR,
+        '\u{0B}' | '\u{0C}' | '\u{2028}' | '\u{2029}' => return BK,
+        '\u{85}' => return NL,
+        ' ' => return SP,
+        '\u{200B}' => return ZW,
+        '\u{200D}' => return ZWJ,
+        '\u{2060}' | '\u{FEFF}' => return WJ,
+        '\u{A0}' | '\u{2007}' | '\u{2011}' | '\u{202F}' | '\u{0F0C}' => return GL,
+        '\t' | '\u{AD}' | '\u{2010}' | '\u{2012}' | '\u{2013}' => return BA,
+        '-' => return HY,
+        '\u{2014}' => return B2,
+        '\u{B4}' => return BB,
+        ')' | ']' => return CP,
+        '"' | '\'' => return QU,
+        '!' | '?' => return EX,
+        ',' | '.' | ':' | ';' => return IS,
+        '/' => return SY,
+        '\u{2024}' | '\u{2025}' | '\u{2026}' => return IN,
+        '$' | '+' | '\\' | '\u{A3}' | '\u{A5}' | '\u{20AC}' => return PR,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}' => return PO,
+        '\u{3001}' | '\u{3002}' | '\u{FF0C}' | '\u{FF0E}' => return CL,
+        '\u{FF01}' | '\u{FF1F}' => return EX,
+        '\u{FF09}' | '\u{FF3D}' | '\u{FF5D}' | '\u{FF60}' => return CL,
+        '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' | '\u{FF5F}' => return OP,
+        '\u{FF1A}' | '\u{FF1B}' => return NS,
+        '\u{3005}' | '\u{303B}' | '\u{309D}' | '\u{309E}' | '\u{30FC}' | '\u{30FD}'
+        | '\u{30FE}' => return NS,
+        '\u{1160}'..='\u{11FF}' => return CM,
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{2FFF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF01}'..='\u{FF60}'
+        | '\u{1F300}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}' => return ID,
+        _ => {}
+    }
+    let category = unsafe { hb_unicode_general_category(hb_unicode_funcs_get_default(), c as u32) };
+    match category {
+        HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK
+        | HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK
+        | HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK
+        | HB_UNICODE_GENERAL_CATEGORY_CONTROL
+        | HB_UNICODE_GENERAL_CATEGORY_FORMAT => CM,
+        HB_UNICODE_GENERAL_CATEGORY_DECIMAL_NUMBER => NU,
+        HB_UNICODE_GENERAL_CATEGORY_OPEN_PUNCTUATION => OP,
+        HB_UNICODE_GENERAL_CATEGORY_CLOSE_PUNCTUATION => CL,
+        HB_UNICODE_GENERAL_CATEGORY_INITIAL_PUNCTUATION
+        | HB_UNICODE_GENERAL_CATEGORY_FINAL_PUNCTUATION => QU,
+        HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR => BA,
+        _ => AL,
+    }
+}
+
+/// Whether a line may be broken between two characters of the classes
+/// `before` and `after`. `before_spaces` is the class of the last character
+/// before `after` that is not a space.
+///
+/// Returns `None` if no break is allowed and `Some(mandatory)` otherwise.
+fn break_between(before: Class, before_spaces: Class, after: Class) -> Option<bool> {
+    use self::Class::*;
+    let alphabetic = |class| class == AL;
+    // LB4 - LB6
+    match (before, after) {
+        (BK, _) | (LF, _) | (NL, _) => return Some(true),
+        (CR, LF) => return None,
+        (CR, _) => return Some(true),
+        (_, BK) | (_, CR) | (_, LF) | (_, NL) => return None,
+        _ => {}
+    }
+    // LB7 - LB8
+    if after == SP || after == ZW {
+        return None;
+    }
+    if before_spaces == ZW {
+        return Some(false);
+    }
+    // LB11 - LB17
+    let no_break = before == WJ
+        || after == WJ
+        || before == GL
+        || (after == GL && before != SP && before != BA && before != HY)
+        || [CL, CP, EX, IS, SY].contains(&after)
+        || before_spaces == OP
+        || (before_spaces == QU && after == OP)
+        || ((before_spaces == CL || before_spaces == CP) && after == NS)
+        || (before_spaces == B2 && after == B2);
+    if no_break {
+        return None;
+    }
+    // LB18
+    if before == SP {
+        return Some(false);
+    }
+    // LB19 - LB30
+    let no_break = before == QU
+        || after == QU
+        || [BA, HY, NS, IN].contains(&after)
+        || before == BB
+        || (alphabetic(before) && after == NU)
+        || (before == NU && alphabetic(after))
+        || ((before == PR || before == PO) && alphabetic(after))
+        || (alphabetic(before) && (after == PR || after == PO))
+        || ([CL, CP, NU].contains(&before) && (after == PO || after == PR))
+        || ((before == PO || before == PR) && (after == OP || after == NU))
+        || ([HY, IS, NU, SY].contains(&before) && after == NU)
+        || (alphabetic(before) && alphabetic(after))
+        || (before == IS && alphabetic(after))
+        || ((alphabetic(before) || before == NU) && after == OP)
+        || (before == CP && (alphabetic(after) || after == NU));
+    if no_break {
+        None
+    } else {
+        Some(false)
+    }
+}
+
+/// Returns the positions in `text` where a line may be broken.
+///
+/// The break opportunities are found using a simplified version of the
+/// Unicode Line Breaking Algorithm ([UAX #14]). Characters that need a
+/// dictionary to be broken (e.g. Thai) are treated like letters, so no breaks
+/// are found within words of these scripts.
+///
+/// The end of a non-empty text is always a mandatory break.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::linebreak::{break_opportunities, BreakOpportunity};
+///
+/// let breaks = break_opportunities("Hello world!\nBye");
+/// let offsets: Vec<_> = breaks.iter().map(|b| (b.offset, b.mandatory)).collect();
+/// assert_eq!(offsets, vec![(6, false), (13, true), (16, true)]);
+/// ```
+///
+/// [UAX #14]: https://www.unicode.org/reports/tr14/
+pub fn break_opportunities(text: &str) -> Vec<BreakOpportunity> {
+    use self::Class::*;
+    let mut breaks = Vec::new();
+    let mut chars = text.char_indices();
+    let mut before = match chars.next() {
+        // LB10: a combining mark without a base is treated like a letter
+        Some((_, c)) => match line_break_class(c) {
+            CM | ZWJ => AL,
+            class => class,
+        },
+        None => return breaks,
+    };
+    let mut before_spaces = before;
+    for (offset, c) in chars {
+        let class = line_break_class(c);
+        // LB9: combining marks take the class of their base
+        if (class == CM || class == ZWJ) && ![BK, CR, LF, NL, SP, ZW].contains(&before) {
+            continue;
+        }
+        let class = if class == CM || class == ZWJ {
+            AL
+        } else {
+            class
+        };
+        if let Some(mandatory) = break_between(before, before_spaces, class) {
+            breaks.push(BreakOpportunity { offset, mandatory });
+        }
+        before = class;
+        if class != SP {
+            before_spaces = class;
+        }
+    }
+    // LB3
+    breaks.push(BreakOpportunity {
+        offset: text.len(),
+        mandatory: true,
+    });
+    breaks
+}
+
+/// Whether `c` is not visible at the end of a line.
+fn is_trailing_whitespace(c: char) -> bool {
+    use self::Class::*;
+    [SP, BK, CR, LF, NL].contains(&line_break_class(c))
+}
+
+/// The end of `range` in `text` without trailing whitespace.
+pub(crate) fn visible_end(text: &str, range: Range<usize>) -> usize {
+    let visible = text[range.clone()].trim_end_matches(is_trailing_whitespace);
+    range.start + visible.len()
+}
+
+/// The advances of the clusters of some shaped text, used to measure the
+/// width of parts of the text.
+#[derive(Debug, Clone)]
+pub(crate) struct Advances {
+    // The text offsets of the clusters in ascending order and the sum of the
+    // advances of all clusters before each of them.
+    cluster_starts: Vec<usize>,
+    advance_before: Vec<Position>,
+}
+
+impl Advances {
+    pub(crate) fn new(spans: impl IntoIterator<Item = ClusterSpan>) -> Advances {
+        let mut spans: Vec<_> = spans
+            .into_iter()
+            .map(|span| (span.text_range.start, span.advance))
+            .collect();
+        spans.sort_by_key(|&(start, _)| start);
+        let cluster_starts = spans.iter().map(|&(start, _)| start).collect();
+        let mut advance_before = Vec::with_capacity(spans.len() + 1);
+        let mut sum = 0;
+        advance_before.push(sum);
+        for &(_, advance) in &spans {
+            sum += advance;
+            advance_before.push(sum);
+        }
+        Advances {
+            cluster_starts,
+            advance_before,
+        }
+    }
+
+    /// The sum of the advances of the clusters starting in `range`.
+    pub(crate) fn width(&self, range: Range<usize>) -> Position {
+        let cluster_index = |offset: usize| match self.cluster_starts.binary_search(&offset) {
+            Ok(index) | Err(index) => index,
+        };
+        let (start, end) = (cluster_index(range.start), cluster_index(range.end));
+        self.advance_before[end] - self.advance_before[start]
+    }
+}
+
+/// Breaks a paragraph greedily at `opportunities` into lines no wider than
+/// `max_width`.
+///
+/// `width` measures a candidate line using the shaped paragraph and `line`
+/// creates a line together with its actual width. Reshaping can change the
+/// advances at the line edges, so a line that does not fit after all is
+/// shortened by one break opportunity at a time.
+pub(crate) fn greedy_lines<L>(
+    opportunities: &[BreakOpportunity],
+    max_width: Position,
+    width: impl Fn(Range<usize>) -> Position,
+    mut line: impl FnMut(Range<usize>) -> (L, Position),
+) -> Vec<L> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut next = 0;
+    while next < opportunities.len() {
+        let mut end = next;
+        for (index, opportunity) in opportunities.iter().enumerate().skip(next) {
+            if index > next && width(start..opportunity.offset) > max_width {
+                break;
+            }
+            end = index;
+            if opportunity.mandatory {
+                break;
+            }
+        }
+        let mut measured = line(start..opportunities[end].offset);
+        while measured.1 > max_width && end > next {
+            end -= 1;
+            measured = line(start..opportunities[end].offset);
+        }
+        start = opportunities[end].offset;
+        next = end + 1;
+        lines.push(measured.0);
+    }
+    lines
+}
+
+/// A line of a paragraph.
+#[derive(Debug)]
+pub struct Line {
+    /// The range of the paragraph's text that is part of this line. This
+    /// includes trailing whitespace.
+    pub text_range: Range<usize>,
+    /// The advance of the line's glyphs excluding trailing whitespace.
+    pub width: Position,
+    /// The shaped glyphs of the line.
+    pub glyphs: GlyphBuffer,
+}
+
+/// Breaks a paragraph of text into lines that fit into a given width.
+///
+/// The paragraph is shaped once. The widths of the candidate lines are
+/// measured using the advances of this shaping result. The glyphs of the
+/// chosen lines are created using `GlyphBuffer::reshape_range`, so only the
+/// glyphs at the line edges that are affected by the break are reshaped.
+#[derive(Debug)]
+pub struct LineBreaker<'a> {
+    font: &'a Font<'a>,
+    text: &'a str,
+    features: &'a [Feature],
+    paragraph: GlyphBuffer,
+    opportunities: Vec<BreakOpportunity>,
+    advances: Advances,
+}
+
+impl<'a> LineBreaker<'a> {
+    /// Shapes `text` and prepares it for line breaking.
+    ///
+    /// The segment properties of the text are guessed. Use
+    /// `from_glyph_buffer` for more control over the shaping.
+    pub fn new(font: &'a Font<'a>, text: &'a str, features: &'a [Feature]) -> LineBreaker<'a> {
+        let buffer = UnicodeBuffer::new()
+            .add_str(text)
+            .guess_segment_properties()
+            .set_flags(BufferFlags::BOT | BufferFlags::EOT | BufferFlags::PRODUCE_UNSAFE_TO_CONCAT);
+        let paragraph = shape(font, buffer, features);
+        LineBreaker::from_glyph_buffer(font, text, features, paragraph)
+    }
+
+    /// Creates a `LineBreaker` from the result of shaping all of `text` using
+    /// `font` and `features`.
+    ///
+    /// The cluster values of `paragraph` must be byte offsets into `text`.
+    /// For the least amount of reshaping it should be shaped with
+    /// `BufferFlags::PRODUCE_UNSAFE_TO_CONCAT`.
+    pub fn from_glyph_buffer(
+        font: &'a Font<'a>,
+        text: &'a str,
+        features: &'a [Feature],
+        paragraph: GlyphBuffer,
+    ) -> LineBreaker<'a> {
+        let advances = Advances::new(paragraph.clusters(text.len()));
+        LineBreaker {
+            font,
+            text,
+            features,
+            paragraph,
+            opportunities: break_opportunities(text),
+            advances,
+        }
+    }
+
+    /// The shaped paragraph.
+    pub fn paragraph(&self) -> &GlyphBuffer {
+        &self.paragraph
+    }
+
+    /// The break opportunities of the paragraph's text.
+    pub fn opportunities(&self) -> &[BreakOpportunity] {
+        &self.opportunities
+    }
+
+    /// Breaks the paragraph by putting as much text as possible on each line.
+    ///
+    /// A line is only wider than `max_width` if it consists of a single
+    /// piece of text that cannot be broken.
+    pub fn greedy(&self, max_width: Position) -> Vec<Line> {
+        greedy_lines(
+            &self.opportunities,
+            max_width,
+            |range| self.width(range),
+            |range| {
+                let line = self.line(range);
+                let width = line.width;
+                (line, width)
+            },
+        )
+    }
+
+    /// Breaks the paragraph into lines of similar width using the
+    /// Knuth-Plass algorithm.
+    ///
+    /// Minimizes the sum of the squared amounts of space left on each line,
+    /// except for lines ending in a mandatory break. Lines wider than
+    /// `max_width` are only chosen if the text cannot be broken otherwise.
+    /// The line widths are measured using the shaped paragraph, the reshaped
+    /// lines might differ slightly.
+    pub fn optimal(&self, max_width: Position) -> Vec<Line> {
+        // Node 0 is the start of the paragraph, node `i` is the break at
+        // `self.opportunities[i - 1]`.
+        const OVERFLOW_COST: f64 = 1e15;
+        let node_offset = |node: usize| match node {
+            0 => 0,
+            node => self.opportunities[node - 1].offset,
+        };
+        let nodes = self.opportunities.len() + 1;
+        let mut cost = vec![std::f64::INFINITY; nodes];
+        let mut previous = vec![0; nodes];
+        cost[0] = 0.0;
+        for from in 0..nodes {
+            if cost[from].is_infinite() {
+                continue;
+            }
+            let start = node_offset(from);
+            for to in from + 1..nodes {
+                let opportunity = self.opportunities[to - 1];
+                let width = self.width(start..opportunity.offset);
+                if width > max_width && to > from + 1 {
+                    break;
+                }
+                let line_cost = if width > max_width {
+                    OVERFLOW_COST + f64::from(width - max_width).powi(2)
+                } else if opportunity.mandatory {
+                    0.0
+                } else {
+                    f64::from(max_width - width).powi(2)
+                };
+                if cost[from] + line_cost < cost[to] {
+                    cost[to] = cost[from] + line_cost;
+                    previous[to] = from;
+                }
+                if opportunity.mandatory {
+                    break;
+                }
+            }
+        }
+
+        let mut breaks = Vec::new();
+        let mut node = nodes - 1;
+        while node > 0 {
+            breaks.push(node);
+            node = previous[node];
+        }
+        breaks
+            .iter()
+            .rev()
+            .scan(0, |start, &node| {
+                let range = *start..node_offset(node);
+                *start = range.end;
+                Some(self.line(range))
+            })
+            .collect()
+    }
+
+    /// The width of the text in `range` as measured in the shaped paragraph.
+    fn width(&self, range: Range<usize>) -> Position {
+        let end = visible_end(self.text, range.clone());
+        self.advances.width(range.start..end)
+    }
+
+    fn line(&self, text_range: Range<usize>) -> Line {
+        let glyphs =
+            self.paragraph
+                .reshape_range(self.font, self.text, text_range.clone(), self.features);
+        let visible_end = visible_end(self.text, text_range.clone());
+        let width = glyphs
+            .clusters(text_range.end)
+            .filter(|span| span.text_range.start < visible_end)
+            .map(|span| span.advance)
+            .sum();
+        Line {
+            text_range,
+            width,
+            glyphs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::font;
+
+    fn offsets(text: &str) -> Vec<(usize, bool)> {
+        break_opportunities(text)
+            .iter()
+            .map(|b| (b.offset, b.mandatory))
+            .collect()
+    }
+
+    #[test]
+    fn test_break_opportunities() {
+        assert_eq!(
+            offsets("Hello world, this is-a test.\nNext"),
+            vec![
+                (6, false),
+                (13, false),
+                (18, false),
+                (21, false),
+                (23, false),
+                (29, true),
+                (33, true)
+            ]
+        );
+        assert_eq!(
+            offsets("x (y) 10 %"),
+            vec![(2, false), (6, false), (9, false), (10, true)]
+        );
+        assert_eq!(offsets("a\u{A0}b"), vec![(4, true)]);
+        assert_eq!(offsets("a\r\nb"), vec![(3, true), (4, true)]);
+        assert_eq!(offsets("e\u{301} x"), vec![(4, false), (5, true)]);
+        assert_eq!(offsets("a\u{200B}b"), vec![(4, false), (5, true)]);
+        assert_eq!(offsets("漢字。"), vec![(3, false), (9, true)]);
+        assert_eq!(
+            offsets("漢字（かな）！"),
+            vec![(3, false), (6, false), (12, false), (21, true)]
+        );
+        assert_eq!(offsets("漢：字"), vec![(6, false), (9, true)]);
+        assert_eq!(offsets(""), vec![]);
+    }
+
+    fn ranges(lines: &[Line]) -> Vec<Range<usize>> {
+        lines.iter().map(|line| line.text_range.clone()).collect()
+    }
+
+    #[test]
+    fn test_greedy_and_optimal() {
+        let font = font();
+        let a = font.get_glyph_h_advance(font.get_nominal_glyph('a').unwrap());
+        let space = font.get_glyph_h_advance(font.get_nominal_glyph(' ').unwrap());
+        let max_width = 6 * a + space;
+
+        let text = "aaa aa aa aaaaa";
+        let breaker = LineBreaker::new(&font, text, &[]);
+        let greedy = breaker.greedy(max_width);
+        assert_eq!(ranges(&greedy), vec![0..7, 7..10, 10..15]);
+        assert_eq!(greedy[0].glyphs.len(), 7);
+        assert_eq!(greedy[0].width, 5 * a + space);
+        assert_eq!(greedy[1].width, 2 * a);
+
+        let optimal = breaker.optimal(max_width);
+        assert_eq!(ranges(&optimal), vec![0..4, 4..10, 10..15]);
+        assert!(optimal.iter().all(|line| line.width <= max_width));
+    }
+
+    #[test]
+    fn test_mandatory_breaks_and_overflow() {
+        let font = font();
+        let text = "aa\naa aaaaaaaaaa aa";
+        let breaker = LineBreaker::new(&font, text, &[]);
+        let a = font.get_glyph_h_advance(font.get_nominal_glyph('a').unwrap());
+
+        let expected = vec![0..3, 3..6, 6..17, 17..19];
+        assert_eq!(ranges(&breaker.greedy(4 * a)), expected);
+        assert_eq!(ranges(&breaker.optimal(4 * a)), expected);
+        assert_eq!(ranges(&breaker.greedy(100 * a)), vec![0..3, 3..19]);
+        assert_eq!(ranges(&breaker.optimal(100 * a)), vec![0..3, 3..19]);
+    }
+}
@@ -0,0 +1,280 @@
+use std;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+use harfbuzz_bindings::{
+    hb_color_t, hb_paint_color, hb_paint_composite_mode_t, hb_paint_funcs_t, hb_paint_pop_clip,
+    hb_paint_pop_group, hb_paint_pop_transform, hb_paint_push_clip_glyph,
+    hb_paint_push_clip_rectangle, hb_paint_push_group, hb_paint_push_transform,
+    HB_PAINT_COMPOSITE_MODE_CLEAR, HB_PAINT_COMPOSITE_MODE_COLOR_BURN,
+    HB_PAINT_COMPOSITE_MODE_COLOR_DODGE, HB_PAINT_COMPOSITE_MODE_DARKEN,
+    HB_PAINT_COMPOSITE_MODE_DEST, HB_PAINT_COMPOSITE_MODE_DEST_ATOP,
+    HB_PAINT_COMPOSITE_MODE_DEST_IN, HB_PAINT_COMPOSITE_MODE_DEST_OUT,
+    HB_PAINT_COMPOSITE_MODE_DEST_OVER, HB_PAINT_COMPOSITE_MODE_DIFFERENCE,
+    HB_PAINT_COMPOSITE_MODE_EXCLUSION, HB_PAINT_COMPOSITE_MODE_HARD_LIGHT,
+    HB_PAINT_COMPOSITE_MODE_HSL_COLOR, HB_PAINT_COMPOSITE_MODE_HSL_HUE,
+    HB_PAINT_COMPOSITE_MODE_HSL_LUMINOSITY, HB_PAINT_COMPOSITE_MODE_HSL_SATURATION,
+    HB_PAINT_COMPOSITE_MODE_LIGHTEN, HB_PAINT_COMPOSITE_MODE_MULTIPLY,
+    HB_PAINT_COMPOSITE_MODE_OVERLAY, HB_PAINT_COMPOSITE_MODE_PLUS, HB_PAINT_COMPOSITE_MODE_SCREEN,
+    HB_PAINT_COMPOSITE_MODE_SOFT_LIGHT, HB_PAINT_COMPOSITE_MODE_SRC,
+    HB_PAINT_COMPOSITE_MODE_SRC_ATOP, HB_PAINT_COMPOSITE_MODE_SRC_IN,
+    HB_PAINT_COMPOSITE_MODE_SRC_OUT, HB_PAINT_COMPOSITE_MODE_SRC_OVER, HB_PAINT_COMPOSITE_MODE_XOR,
+};
+
+use crate::common::HarfbuzzObject;
+use crate::font::{Font, Glyph};
+
+/// A color in the format used by HarfBuzz (`hb_color_t`), i.e. BGRA with
+/// 8 bits per channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Color(pub hb_color_t);
+
+impl Color {
+    /// Create a color from its red, green, blue and alpha channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::Color;
+    ///
+    /// let color = Color::new(0x11, 0x22, 0x33, 0xff);
+    /// assert_eq!(color.red(), 0x11);
+    /// assert_eq!(color.alpha(), 0xff);
+    /// ```
+    pub const fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+        Color(((blue as u32) << 24) | ((green as u32) << 16) | ((red as u32) << 8) | alpha as u32)
+    }
+
+    pub const fn red(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub const fn green(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    pub const fn blue(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub const fn alpha(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+/// The modes for compositing a group onto its parent surface, see
+/// `Paint::pop_group`. They are the Porter-Duff operators and the blend modes
+/// of COLRv1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CompositeMode {
+    /// Clears the destination.
+    Clear,
+    /// Replaces the destination with the group.
+    Src,
+    /// Keeps the destination and discards the group.
+    Dest,
+    /// Draws the group over the destination. This is the default mode.
+    SrcOver,
+    /// Draws the destination over the group.
+    DestOver,
+    /// Draws the group where the destination is opaque.
+    SrcIn,
+    /// Keeps the destination where the group is opaque.
+    DestIn,
+    /// Draws the group where the destination is transparent.
+    SrcOut,
+    /// Keeps the destination where the group is transparent.
+    DestOut,
+    /// Draws the group over the destination, inside the destination.
+    SrcAtop,
+    /// Draws the destination over the group, inside the group.
+    DestAtop,
+    /// Keeps the parts of the group and the destination that do not overlap.
+    Xor,
+    /// Adds the group to the destination.
+    Plus,
+    /// Blends with the `screen` mode.
+    Screen,
+    /// Blends with the `overlay` mode.
+    Overlay,
+    /// Blends with the `darken` mode.
+    Darken,
+    /// Blends with the `lighten` mode.
+    Lighten,
+    /// Blends with the `color-dodge` mode.
+    ColorDodge,
+    /// Blends with the `color-burn` mode.
+    ColorBurn,
+    /// Blends with the `hard-light` mode.
+    HardLight,
+    /// Blends with the `soft-light` mode.
+    SoftLight,
+    /// Blends with the `difference` mode.
+    Difference,
+    /// Blends with the `exclusion` mode.
+    Exclusion,
+    /// Blends with the `multiply` mode.
+    Multiply,
+    /// Blends with the `hue` mode.
+    HslHue,
+    /// Blends with the `saturation` mode.
+    HslSaturation,
+    /// Blends with the `color` mode.
+    HslColor,
+    /// Blends with the `luminosity` mode.
+    HslLuminosity,
+}
+
+impl CompositeMode {
+    /// Convert into raw value of type `hb_paint_composite_mode_t`.
+    pub fn to_raw(self) -> hb_paint_composite_mode_t {
+        match self {
+            CompositeMode::Clear => HB_PAINT_COMPOSITE_MODE_CLEAR,
+            CompositeMode::Src => HB_PAINT_COMPOSITE_MODE_SRC,
+            CompositeMode::Dest => HB_PAINT_COMPOSITE_MODE_DEST,
+            CompositeMode::SrcOver => HB_PAINT_COMPOSITE_MODE_SRC_OVER,
+            CompositeMode::DestOver => HB_PAINT_COMPOSITE_MODE_DEST_OVER,
+            CompositeMode::SrcIn => HB_PAINT_COMPOSITE_MODE_SRC_IN,
+            CompositeMode::DestIn => HB_PAINT_COMPOSITE_MODE_DEST_IN,
+            CompositeMode::SrcOut => HB_PAINT_COMPOSITE_MODE_SRC_OUT,
+            CompositeMode::DestOut => HB_PAINT_COMPOSITE_MODE_DEST_OUT,
+            CompositeMode::SrcAtop => HB_PAINT_COMPOSITE_MODE_SRC_ATOP,
+            CompositeMode::DestAtop => HB_PAINT_COMPOSITE_MODE_DEST_ATOP,
+            CompositeMode::Xor => HB_PAINT_COMPOSITE_MODE_XOR,
+            CompositeMode::Plus => HB_PAINT_COMPOSITE_MODE_PLUS,
+            CompositeMode::Screen => HB_PAINT_COMPOSITE_MODE_SCREEN,
+            CompositeMode::Overlay => HB_PAINT_COMPOSITE_MODE_OVERLAY,
+            CompositeMode::Darken => HB_PAINT_COMPOSITE_MODE_DARKEN,
+            CompositeMode::Lighten => HB_PAINT_COMPOSITE_MODE_LIGHTEN,
+            CompositeMode::ColorDodge => HB_PAINT_COMPOSITE_MODE_COLOR_DODGE,
+            CompositeMode::ColorBurn => HB_PAINT_COMPOSITE_MODE_COLOR_BURN,
+            CompositeMode::HardLight => HB_PAINT_COMPOSITE_MODE_HARD_LIGHT,
+            CompositeMode::SoftLight => HB_PAINT_COMPOSITE_MODE_SOFT_LIGHT,
+            CompositeMode::Difference => HB_PAINT_COMPOSITE_MODE_DIFFERENCE,
+            CompositeMode::Exclusion => HB_PAINT_COMPOSITE_MODE_EXCLUSION,
+            CompositeMode::Multiply => HB_PAINT_COMPOSITE_MODE_MULTIPLY,
+            CompositeMode::HslHue => HB_PAINT_COMPOSITE_MODE_HSL_HUE,
+            CompositeMode::HslSaturation => HB_PAINT_COMPOSITE_MODE_HSL_SATURATION,
+            CompositeMode::HslColor => HB_PAINT_COMPOSITE_MODE_HSL_COLOR,
+            CompositeMode::HslLuminosity => HB_PAINT_COMPOSITE_MODE_HSL_LUMINOSITY,
+        }
+    }
+
+    /// Create from raw value of type `hb_paint_composite_mode_t`. Unknown
+    /// values are treated as `SrcOver`.
+    pub fn from_raw(mode: hb_paint_composite_mode_t) -> Self {
+        match mode {
+            HB_PAINT_COMPOSITE_MODE_CLEAR => CompositeMode::Clear,
+            HB_PAINT_COMPOSITE_MODE_SRC => CompositeMode::Src,
+            HB_PAINT_COMPOSITE_MODE_DEST => CompositeMode::Dest,
+            HB_PAINT_COMPOSITE_MODE_SRC_OVER => CompositeMode::SrcOver,
+            HB_PAINT_COMPOSITE_MODE_DEST_OVER => CompositeMode::DestOver,
+            HB_PAINT_COMPOSITE_MODE_SRC_IN => CompositeMode::SrcIn,
+            HB_PAINT_COMPOSITE_MODE_DEST_IN => CompositeMode::DestIn,
+            HB_PAINT_COMPOSITE_MODE_SRC_OUT => CompositeMode::SrcOut,
+            HB_PAINT_COMPOSITE_MODE_DEST_OUT => CompositeMode::DestOut,
+            HB_PAINT_COMPOSITE_MODE_SRC_ATOP => CompositeMode::SrcAtop,
+            HB_PAINT_COMPOSITE_MODE_DEST_ATOP => CompositeMode::DestAtop,
+            HB_PAINT_COMPOSITE_MODE_XOR => CompositeMode::Xor,
+            HB_PAINT_COMPOSITE_MODE_PLUS => CompositeMode::Plus,
+            HB_PAINT_COMPOSITE_MODE_SCREEN => CompositeMode::Screen,
+            HB_PAINT_COMPOSITE_MODE_OVERLAY => CompositeMode::Overlay,
+            HB_PAINT_COMPOSITE_MODE_DARKEN => CompositeMode::Darken,
+            HB_PAINT_COMPOSITE_MODE_LIGHTEN => CompositeMode::Lighten,
+            HB_PAINT_COMPOSITE_MODE_COLOR_DODGE => CompositeMode::ColorDodge,
+            HB_PAINT_COMPOSITE_MODE_COLOR_BURN => CompositeMode::ColorBurn,
+            HB_PAINT_COMPOSITE_MODE_HARD_LIGHT => CompositeMode::HardLight,
+            HB_PAINT_COMPOSITE_MODE_SOFT_LIGHT => CompositeMode::SoftLight,
+            HB_PAINT_COMPOSITE_MODE_DIFFERENCE => CompositeMode::Difference,
+            HB_PAINT_COMPOSITE_MODE_EXCLUSION => CompositeMode::Exclusion,
+            HB_PAINT_COMPOSITE_MODE_MULTIPLY => CompositeMode::Multiply,
+            HB_PAINT_COMPOSITE_MODE_HSL_HUE => CompositeMode::HslHue,
+            HB_PAINT_COMPOSITE_MODE_HSL_SATURATION => CompositeMode::HslSaturation,
+            HB_PAINT_COMPOSITE_MODE_HSL_COLOR => CompositeMode::HslColor,
+            HB_PAINT_COMPOSITE_MODE_HSL_LUMINOSITY => CompositeMode::HslLuminosity,
+            _ => CompositeMode::SrcOver,
+        }
+    }
+}
+
+/// A handle to the paint funcs HarfBuzz uses to render color glyphs.
+///
+/// A `Paint` is handed to [`FontFuncs::paint_glyph()`] implementations. It
+/// allows emitting paint operations for color glyphs or forwarding the paint
+/// request to another font using [`Font::paint_glyph()`].
+///
+/// Transforms, clips and groups must be balanced: every `push_*` call needs a
+/// matching `pop_*` call.
+///
+/// [`FontFuncs::paint_glyph()`]: crate::font_funcs::FontFuncs::paint_glyph
+#[derive(Debug)]
+pub struct Paint<'a> {
+    funcs: *mut hb_paint_funcs_t,
+    data: *mut c_void,
+    marker: PhantomData<&'a mut c_void>,
+}
+
+impl<'a> Paint<'a> {
+    /// Creates a `Paint` from raw HarfBuzz paint funcs and their paint data.
+    ///
+    /// # Safety
+    ///
+    /// `funcs` must be a valid pointer to paint funcs and `data` must be the
+    /// paint data these funcs expect. Both must stay valid for `'a`.
+    pub(crate) unsafe fn from_raw(funcs: *mut hb_paint_funcs_t, data: *mut c_void) -> Paint<'a> {
+        Paint {
+            funcs,
+            data,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the raw paint funcs and paint data.
+    pub(crate) fn as_raw(&self) -> (*mut hb_paint_funcs_t, *mut c_void) {
+        (self.funcs, self.data)
+    }
+
+    /// Applies the 2x3 affine transform `[xx yx xy yy dx dy]` to all
+    /// subsequent paint operations until the matching `pop_transform`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_transform(&mut self, xx: f32, yx: f32, xy: f32, yy: f32, dx: f32, dy: f32) {
+        unsafe { hb_paint_push_transform(self.funcs, self.data, xx, yx, xy, yy, dx, dy) }
+    }
+
+    pub fn pop_transform(&mut self) {
+        unsafe { hb_paint_pop_transform(self.funcs, self.data) }
+    }
+
+    /// Clips subsequent paint operations to the outline of `glyph` in `font`.
+    pub fn push_clip_glyph(&mut self, glyph: Glyph, font: &Font<'_>) {
+        unsafe { hb_paint_push_clip_glyph(self.funcs, self.data, glyph, font.as_raw()) }
+    }
+
+    /// Clips subsequent paint operations to the given rectangle.
+    pub fn push_clip_rectangle(&mut self, x_min: f32, y_min: f32, x_max: f32, y_max: f32) {
+        unsafe { hb_paint_push_clip_rectangle(self.funcs, self.data, x_min, y_min, x_max, y_max) }
+    }
+
+    pub fn pop_clip(&mut self) {
+        unsafe { hb_paint_pop_clip(self.funcs, self.data) }
+    }
+
+    /// Fills the current clip with a solid color.
+    ///
+    /// If `is_foreground` is `true` the renderer should use its foreground
+    /// color instead, with `color`'s alpha applied.
+    pub fn color(&mut self, is_foreground: bool, color: Color) {
+        unsafe { hb_paint_color(self.funcs, self.data, is_foreground as _, color.0) }
+    }
+
+    /// Starts an intermediate surface that subsequent operations paint to.
+    pub fn push_group(&mut self) {
+        unsafe { hb_paint_push_group(self.funcs, self.data) }
+    }
+
+    /// Composites the current group onto its parent surface using `mode`.
+    pub fn pop_group(&mut self, mode: CompositeMode) {
+        unsafe { hb_paint_pop_group(self.funcs, self.data, mode.to_raw()) }
+    }
+}
@@ -0,0 +1,520 @@
+use std::ops::Range;
+
+use harfbuzz_bindings::{
+    hb_script_t, hb_unicode_funcs_get_default, hb_unicode_script, HB_SCRIPT_COMMON,
+    HB_SCRIPT_INHERITED, HB_SCRIPT_UNKNOWN,
+};
+
+use crate::bidi::{level_direction, visual_order, BidiParagraph};
+use crate::buffer::{BufferFlags, GlyphBuffer, UnicodeBuffer};
+use crate::caret::CaretPiece;
+use crate::common::{Direction, Rect, Script};
+use crate::font::{Font, Glyph, Position};
+use crate::linebreak::{break_opportunities, greedy_lines, visible_end, Advances};
+use crate::selection::selection_rects;
+use crate::shape;
+
+/// A glyph of a `Paragraph` together with its absolute position.
+#[derive(Debug, Copy, Clone)]
+pub struct PositionedGlyph<'a> {
+    /// The glyph index in `font`.
+    pub glyph: Glyph,
+    /// The byte offset of the glyph's cluster in the paragraph's text.
+    pub cluster: u32,
+    /// The x coordinate of the glyph's origin, including its offset.
+    pub x: Position,
+    /// The y coordinate of the glyph's origin, including its offset.
+    pub y: Position,
+    /// How far the next glyph is moved to the right.
+    pub x_advance: Position,
+    /// The font the glyph belongs to.
+    pub font: &'a Font<'a>,
+}
+
+/// A sequence of glyphs on a line that share the same direction.
+#[derive(Debug, Clone)]
+pub struct GlyphRun<'a> {
+    /// The part of the paragraph's text shown by the run.
+    pub text_range: Range<usize>,
+    /// The bidirectional embedding level of the run. Runs with an odd level
+    /// are right-to-left.
+    pub level: u8,
+    /// The glyphs of the run from left to right.
+    pub glyphs: Vec<PositionedGlyph<'a>>,
+    // The parts of the run showing each grapheme, from left to right.
+    pieces: Vec<CaretPiece>,
+}
+
+impl<'a> GlyphRun<'a> {
+    /// The direction of the run's text.
+    pub fn direction(&self) -> Direction {
+        level_direction(self.level)
+    }
+}
+
+/// A line of a `Paragraph` in visual order.
+#[derive(Debug, Clone)]
+pub struct VisualLine<'a> {
+    /// The part of the paragraph's text on this line, including trailing
+    /// whitespace.
+    pub text_range: Range<usize>,
+    /// The y coordinate of the line's baseline.
+    pub baseline: Position,
+    /// The advance of the line's glyphs excluding trailing whitespace.
+    pub width: Position,
+    /// The runs of the line from left to right.
+    pub runs: Vec<GlyphRun<'a>>,
+}
+
+impl<'a> VisualLine<'a> {
+    /// Returns an iterator over the glyphs of the line from left to right.
+    pub fn glyphs(&self) -> impl Iterator<Item = &PositionedGlyph<'a>> {
+        self.runs.iter().flat_map(|run| run.glyphs.iter())
+    }
+}
+
+/// A paragraph of text shaped and broken into lines of a given width.
+///
+/// The text is split into runs of a single direction and script using a
+/// simplified version of the [Unicode Bidirectional Algorithm][uax9], each of
+/// which is shaped separately. The lines are chosen greedily and the runs of
+/// every line are reordered visually, with whitespace at the end of a line
+/// taking the direction of the paragraph. Characters like parentheses are
+/// mirrored by HarfBuzz when they are part of a right-to-left run.
+///
+/// Glyph positions use the coordinate system of HarfBuzz, so y grows upwards.
+/// The top of the first line is at `y = 0` and each line is placed
+/// `ascender - descender + line_gap` below the previous one. Lines of a
+/// left-to-right paragraph start at `x = 0`, lines of a right-to-left
+/// paragraph end at `x = width`.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+///
+/// let text = "Hello \u{5E2}\u{5D5}\u{5DC}\u{5DD}!";
+/// let paragraph = Paragraph::new(text, &font, Direction::Ltr, 10000);
+/// for line in paragraph.lines() {
+///     for glyph in line.glyphs() {
+///         println!("{} at ({}, {})", glyph.glyph, glyph.x, glyph.y);
+///     }
+/// }
+/// ```
+///
+/// [uax9]: https://www.unicode.org/reports/tr9/
+#[derive(Debug)]
+pub struct Paragraph<'a> {
+    text: &'a str,
+    font: &'a Font<'a>,
+    direction: Direction,
+    width: Position,
+    lines: Vec<VisualLine<'a>>,
+}
+
+/// A part of the text with a single embedding level and script.
+#[derive(Debug)]
+struct ShapedRun {
+    text_range: Range<usize>,
+    glyphs: GlyphBuffer,
+}
+
+impl<'a> Paragraph<'a> {
+    /// Lays out `text` using `font` in lines no wider than `width`.
+    ///
+    /// If `base_direction` is neither `Ltr` nor `Rtl` the direction of the
+    /// paragraph is taken from its first character with a strong direction.
+    /// A line is only wider than `width` if it consists of a single piece of
+    /// text that cannot be broken.
+    pub fn new(
+        text: &'a str,
+        font: &'a Font<'a>,
+        base_direction: Direction,
+        width: Position,
+    ) -> Paragraph<'a> {
+        let bidi = BidiParagraph::new(text, base_direction);
+        let runs: Vec<ShapedRun> = itemize(text, bidi.levels())
+            .into_iter()
+            .map(|(text_range, script)| {
+                let mut flags = BufferFlags::PRODUCE_UNSAFE_TO_CONCAT;
+                if text_range.start == 0 {
+                    flags |= BufferFlags::BOT;
+                }
+                if text_range.end == text.len() {
+                    flags |= BufferFlags::EOT;
+                }
+                let buffer = UnicodeBuffer::new()
+                    .add_str_item(text, &text[text_range.clone()])
+                    .set_direction(level_direction(bidi.levels()[text_range.start]))
+                    .set_script(Script(script).to_iso15924_tag())
+                    .guess_segment_properties()
+                    .set_flags(flags);
+                ShapedRun {
+                    glyphs: shape(font, buffer, &[]),
+                    text_range,
+                }
+            })
+            .collect();
+        let advances = Advances::new(
+            runs.iter()
+                .flat_map(|run| run.glyphs.clusters(run.text_range.end)),
+        );
+
+        let extents = font.get_font_h_extents().unwrap_or_default();
+        let line_height = extents.ascender - extents.descender + extents.line_gap;
+        let lines = greedy_lines(
+            &break_opportunities(text),
+            width,
+            |range| advances.width(range.start..visible_end(text, range)),
+            |range| {
+                let line = layout_line(text, font, &bidi, &runs, range);
+                let width = line.width;
+                (line, width)
+            },
+        );
+        let lines = lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut line)| {
+                let baseline = -extents.ascender - index as Position * line_height;
+                let advance: Position = line.glyphs().map(|glyph| glyph.x_advance).sum();
+                let offset = if bidi.level % 2 == 0 {
+                    0
+                } else {
+                    width - advance
+                };
+                for run in &mut line.runs {
+                    for glyph in &mut run.glyphs {
+                        glyph.x += offset;
+                        glyph.y += baseline;
+                    }
+                    for piece in &mut run.pieces {
+                        piece.left += offset;
+                        piece.right += offset;
+                    }
+                }
+                line.baseline = baseline;
+                line
+            })
+            .collect();
+
+        Paragraph {
+            text,
+            font,
+            direction: bidi.direction(),
+            width,
+            lines,
+        }
+    }
+
+    /// The text of the paragraph.
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// The resolved base direction of the paragraph, either `Ltr` or `Rtl`.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The width the paragraph was laid out for.
+    pub fn width(&self) -> Position {
+        self.width
+    }
+
+    /// The lines of the paragraph from top to bottom.
+    pub fn lines(&self) -> &[VisualLine<'a>] {
+        &self.lines
+    }
+
+    /// Returns the rectangles that highlight the part `text_range` of the
+    /// paragraph's text.
+    ///
+    /// Every line gets its own rectangles, ordered from left to right and
+    /// reaching from the font's descender to its ascender. A selection that
+    /// covers text of both directions usually results in several disjoint
+    /// rectangles on a line. See `GlyphBuffer::selection_rects` for how
+    /// ligatures and partly selected graphemes are handled.
+    pub fn selection_rects(&self, text_range: Range<usize>) -> Vec<Rect> {
+        let extents = self.font.get_font_h_extents().unwrap_or_default();
+        let height = extents.ascender - extents.descender;
+        self.lines
+            .iter()
+            .flat_map(|line| {
+                let pieces = line.runs.iter().flat_map(|run| &run.pieces);
+                let y = line.baseline + extents.descender;
+                selection_rects(pieces, text_range.clone(), y, height)
+            })
+            .collect()
+    }
+}
+
+/// Splits `text` into items of a single embedding level and script.
+///
+/// Characters of the common and inherited scripts belong to the script of
+/// the surrounding text.
+fn itemize(text: &str, levels: &[u8]) -> Vec<(Range<usize>, hb_script_t)> {
+    let funcs = unsafe { hb_unicode_funcs_get_default() };
+    let mut items: Vec<(Range<usize>, Option<hb_script_t>)> = Vec::new();
+    for (offset, c) in text.char_indices() {
+        let end = offset + c.len_utf8();
+        let script = match unsafe { hb_unicode_script(funcs, c as u32) } {
+            HB_SCRIPT_COMMON | HB_SCRIPT_INHERITED | HB_SCRIPT_UNKNOWN => None,
+            script => Some(script),
+        };
+        match items.last_mut() {
+            Some((range, item_script))
+                if levels[range.start] == levels[offset]
+                    && (script.is_none() || item_script.is_none() || *item_script == script) =>
+            {
+                range.end = end;
+                *item_script = item_script.or(script);
+            }
+            _ => items.push((offset..end, script)),
+        }
+    }
+    items
+        .into_iter()
+        .map(|(range, script)| (range, script.unwrap_or(HB_SCRIPT_COMMON)))
+        .collect()
+}
+
+/// Shapes and reorders the text of a line. The glyphs are positioned
+/// relative to the left end of the line and its baseline.
+fn layout_line<'a>(
+    text: &str,
+    font: &'a Font<'a>,
+    bidi: &BidiParagraph,
+    runs: &[ShapedRun],
+    text_range: Range<usize>,
+) -> VisualLine<'a> {
+    // Split the line into pieces of a single shaped run and level.
+    let levels = bidi.line_levels(text_range.clone());
+    let mut pieces: Vec<(Range<usize>, u8, usize)> = Vec::new();
+    let mut run_index = 0;
+    for (offset, c) in text[text_range.clone()].char_indices() {
+        let offset = text_range.start + offset;
+        let end = offset + c.len_utf8();
+        let level = levels[offset - text_range.start];
+        while runs[run_index].text_range.end <= offset {
+            run_index += 1;
+        }
+        match pieces.last_mut() {
+            Some((range, piece_level, piece_run))
+                if *piece_level == level && *piece_run == run_index =>
+            {
+                range.end = end;
+            }
+            _ => pieces.push((offset..end, level, run_index)),
+        }
+    }
+
+    let visible_end = visible_end(text, text_range.clone());
+    let piece_levels: Vec<u8> = pieces.iter().map(|&(_, level, _)| level).collect();
+    let mut x = 0;
+    let mut width = 0;
+    let mut glyph_runs = Vec::with_capacity(pieces.len());
+    for index in visual_order(&piece_levels) {
+        let (range, level, run_index) = pieces[index].clone();
+        let buffer = runs[run_index]
+            .glyphs
+            .reshape_range(font, text, range.clone(), &[]);
+        let mut pieces = buffer.caret_pieces(font, &text[..range.end]);
+        for piece in &mut pieces {
+            piece.left += x;
+            piece.right += x;
+        }
+        width += buffer
+            .clusters(range.end)
+            .filter(|span| span.text_range.start < visible_end)
+            .map(|span| span.advance)
+            .sum::<Position>();
+        let glyphs = buffer
+            .get_glyph_infos()
+            .iter()
+            .zip(buffer.get_glyph_positions())
+            .map(|(info, position)| {
+                let glyph = PositionedGlyph {
+                    glyph: info.codepoint,
+                    cluster: info.cluster,
+                    x: x + position.x_offset,
+                    y: position.y_offset,
+                    x_advance: position.x_advance,
+                    font,
+                };
+                x += position.x_advance;
+                glyph
+            })
+            .collect();
+        glyph_runs.push(GlyphRun {
+            text_range: range,
+            level,
+            glyphs,
+            pieces,
+        });
+    }
+    VisualLine {
+        text_range,
+        baseline: 0,
+        width,
+        runs: glyph_runs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font_funcs::FontFuncs;
+    use crate::test_util::{font, font_with_funcs};
+
+    fn clusters(line: &VisualLine<'_>) -> Vec<u32> {
+        line.glyphs().map(|glyph| glyph.cluster).collect()
+    }
+
+    fn right_edge(line: &VisualLine<'_>) -> Position {
+        let last = line.glyphs().last().unwrap();
+        last.x + last.x_advance
+    }
+
+    #[test]
+    fn test_mixed_directions() {
+        let font = font();
+        let text = "abc \u{5D0}\u{5D1}\u{5D2} def";
+        let paragraph = Paragraph::new(text, &font, Direction::Invalid, 1_000_000);
+        assert_eq!(paragraph.direction(), Direction::Ltr);
+        let line = &paragraph.lines()[0];
+        assert_eq!(paragraph.lines().len(), 1);
+        assert_eq!(clusters(line), vec![0, 1, 2, 3, 8, 6, 4, 10, 11, 12, 13]);
+        let levels: Vec<_> = line.runs.iter().map(|run| run.level).collect();
+        assert_eq!(levels, vec![0, 1, 0]);
+        assert_eq!(line.runs[1].direction(), Direction::Rtl);
+
+        let extents = font.get_font_h_extents().unwrap();
+        let mut x = 0;
+        for glyph in line.glyphs() {
+            assert_eq!((glyph.x, glyph.y), (x, -extents.ascender));
+            assert_eq!(glyph.font, &*font);
+            x += glyph.x_advance;
+        }
+        assert_eq!(line.width, x);
+    }
+
+    #[test]
+    fn test_rtl_mirroring_and_numbers() {
+        let font = font();
+        let open = font.get_nominal_glyph('(').unwrap();
+        let close = font.get_nominal_glyph(')').unwrap();
+        let text = "\u{5D0}(\u{5D1})";
+        let paragraph = Paragraph::new(text, &font, Direction::Rtl, 10000);
+        let line = &paragraph.lines()[0];
+        assert_eq!(clusters(line), vec![5, 3, 2, 0]);
+        let glyphs: Vec<_> = line.glyphs().map(|glyph| glyph.glyph).collect();
+        assert_eq!(glyphs[0], open);
+        assert_eq!(glyphs[2], close);
+        assert_eq!(right_edge(line), 10000);
+
+        let text = "\u{5D0}\u{5D1} 12";
+        let paragraph = Paragraph::new(text, &font, Direction::Invalid, 10000);
+        assert_eq!(paragraph.direction(), Direction::Rtl);
+        assert_eq!(clusters(&paragraph.lines()[0]), vec![5, 6, 4, 2, 0]);
+    }
+
+    #[test]
+    fn test_line_reordering() {
+        let font = font();
+        let text = "abc def";
+        let unbroken = Paragraph::new(text, &font, Direction::Rtl, 1_000_000);
+        let width = unbroken.lines()[0].width - 1;
+
+        let paragraph = Paragraph::new(text, &font, Direction::Rtl, width);
+        let lines = paragraph.lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text_range, 0..4);
+        // The trailing space is moved to the left end of the line.
+        assert_eq!(clusters(&lines[0]), vec![3, 0, 1, 2]);
+        assert_eq!(lines[0].runs[0].level, 1);
+        assert_eq!(lines[0].runs[1].level, 2);
+        assert_eq!(right_edge(&lines[0]), width);
+        assert_eq!(lines[0].runs[1].glyphs[0].x, width - lines[0].width);
+        assert_eq!(clusters(&lines[1]), vec![4, 5, 6]);
+        assert_eq!(right_edge(&lines[1]), width);
+
+        let extents = font.get_font_h_extents().unwrap();
+        let line_height = extents.ascender - extents.descender + extents.line_gap;
+        assert_eq!(lines[0].baseline - lines[1].baseline, line_height);
+
+        assert!(Paragraph::new("", &font, Direction::Ltr, width)
+            .lines()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_selection_rects() {
+        let font = font();
+        let text = "ab \u{5D0}\u{5D1}";
+        let paragraph = Paragraph::new(text, &font, Direction::Ltr, 1_000_000);
+        let line = &paragraph.lines()[0];
+        let glyphs: Vec<_> = line.glyphs().collect();
+        let extents = font.get_font_h_extents().unwrap();
+
+        // "b" and the first Hebrew letter are at opposite ends of the line
+        let rects = paragraph.selection_rects(1..5);
+        let edges: Vec<_> = rects.iter().map(|rect| (rect.x, rect.right())).collect();
+        let alef = glyphs.iter().find(|glyph| glyph.cluster == 3).unwrap();
+        assert_eq!(
+            edges,
+            vec![
+                (glyphs[1].x, glyphs[3].x),
+                (alef.x, alef.x + alef.x_advance)
+            ]
+        );
+        assert_eq!(rects[0].y, line.baseline + extents.descender);
+        assert_eq!(rects[0].height, extents.ascender - extents.descender);
+
+        assert_eq!(paragraph.selection_rects(0..text.len()).len(), 1);
+        assert!(paragraph.selection_rects(2..2).is_empty());
+    }
+
+    /// Maps characters to glyphs of the same value with an advance of 100,
+    /// and kerns a space followed by "b".
+    struct KerningFont;
+
+    impl FontFuncs for KerningFont {
+        fn get_nominal_glyph(&self, _: &Font<'_>, unicode: char) -> Option<Glyph> {
+            Some(unicode as u32)
+        }
+
+        fn get_glyph_h_advance(&self, _: &Font<'_>, _: Glyph) -> Position {
+            100
+        }
+
+        fn get_glyph_h_kerning(&self, _: &Font<'_>, left: Glyph, right: Glyph) -> Position {
+            if (left, right) == (' ' as u32, 'b' as u32) {
+                -100
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn test_reshaped_line_overflow() {
+        let font = font_with_funcs(KerningFont);
+        // In the shaped paragraph both "b" are kerned against the preceding
+        // space, so "b b" seems to fit. At the start of a line the first "b"
+        // loses its kerning and the line has to be broken once more.
+        let text = "a b b";
+        let paragraph = Paragraph::new(text, &font, Direction::Ltr, 175);
+        let ranges: Vec<_> = paragraph
+            .lines()
+            .iter()
+            .map(|line| line.text_range.clone())
+            .collect();
+        assert_eq!(ranges, vec![0..2, 2..4, 4..5]);
+        assert!(paragraph.lines().iter().all(|line| line.width <= 175));
+    }
+}
@@ -0,0 +1,502 @@
+//! A small CPU rasterizer for glyph outlines.
+//!
+//! This module turns glyph outlines into 8-bit anti-aliased coverage masks
+//! without depending on a graphics library. The coverage of every pixel is the
+//! exact area of the pixel covered by the outline, with curves approximated by
+//! line segments. Overlapping contours are filled using the nonzero winding
+//! rule.
+//!
+//! Positions are in the font's scale, where 64 units are one pixel. So a font
+//! with `font.set_scale(16 * 64, 16 * 64)` is rendered at 16 pixels per em and
+//! glyph positions with fractions of a pixel are rendered at the right
+//! subpixel offsets.
+//!
+//! This module is only available with the `raster` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use harfbuzz_rs::*;
+//! use harfbuzz_rs::raster::render_buffer;
+//!
+//! let path = "testfiles/SourceSansVariable-Roman.ttf";
+//! let face = Face::from_file(path, 0).expect("could not load face");
+//! let mut font = Font::new(face);
+//! font.set_scale(20 * 64, 20 * 64);
+//!
+//! let buffer = shape(&font, UnicodeBuffer::new().add_str("Hello"), &[]);
+//! let image = render_buffer(&font, &buffer);
+//! assert!(image.pixels().iter().any(|&coverage| coverage == 255));
+//! ```
+
+use crate::buffer::GlyphBuffer;
+use crate::draw::DrawFuncs;
+use crate::font::{Font, Glyph, Position};
+
+/// The number of font units per pixel.
+const UNITS_PER_PIXEL: f32 = 64.0;
+
+/// An 8-bit grayscale image.
+///
+/// Pixels are stored row by row from the top left corner with one byte per
+/// pixel, so `pixels()` can be compared or written out directly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GrayImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Creates a black image of `width` by `height` pixels.
+    pub fn new(width: usize, height: usize) -> GrayImage {
+        GrayImage {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Returns the number of pixels in each row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixel at column `x` of row `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the image.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        self.pixels[y * self.width + x]
+    }
+
+    /// Returns the pixels row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the pixels row by row.
+    pub fn into_raw(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Adds the coverage of `mask` to the image, clipping the parts of the
+    /// mask outside of the image.
+    pub(crate) fn composite(&mut self, mask: &GlyphMask) {
+        for row in 0..mask.image.height {
+            let y = mask.top + row as i32;
+            if y < 0 || y >= self.height as i32 {
+                continue;
+            }
+            for column in 0..mask.image.width {
+                let x = mask.left + column as i32;
+                if x < 0 || x >= self.width as i32 {
+                    continue;
+                }
+                let pixel = &mut self.pixels[y as usize * self.width + x as usize];
+                *pixel = pixel.saturating_add(mask.image.pixel(column, row));
+            }
+        }
+    }
+}
+
+/// The coverage mask of a glyph.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlyphMask {
+    /// The column of the mask's left edge in the pixel grid.
+    pub left: i32,
+    /// The row of the mask's top edge in the pixel grid.
+    pub top: i32,
+    /// The coverage of the pixels covered by the mask.
+    pub image: GrayImage,
+}
+
+/// Rasterizes the outline of `glyph` with its origin at the pixel position
+/// `(x, y)`.
+///
+/// `y` points downwards like the rows of the pixel grid. The fractional parts
+/// of `x` and `y` place the outline between pixels. Returns `None` if the
+/// glyph has no outline.
+pub fn rasterize_glyph(font: &Font<'_>, glyph: Glyph, x: f32, y: f32) -> Option<GlyphMask> {
+    let mut outline = Outline::new(x, y);
+    font.draw_glyph(glyph, &mut outline);
+    outline.close();
+    if outline.lines.is_empty() {
+        return None;
+    }
+
+    let (mut x_min, mut y_min) = (f32::INFINITY, f32::INFINITY);
+    let (mut x_max, mut y_max) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for &(start, end) in &outline.lines {
+        x_min = x_min.min(start.0).min(end.0);
+        y_min = y_min.min(start.1).min(end.1);
+        x_max = x_max.max(start.0).max(end.0);
+        y_max = y_max.max(start.1).max(end.1);
+    }
+    let (left, top) = (x_min.floor() as i32, y_min.floor() as i32);
+    let width = (x_max.ceil() as i32 - left).max(1) as usize;
+    let height = (y_max.ceil() as i32 - top).max(1) as usize;
+
+    let mut rasterizer = Rasterizer::new(width, height);
+    for &(start, end) in &outline.lines {
+        let shift = |point: Point| (point.0 - left as f32, point.1 - top as f32);
+        rasterizer.draw_line(shift(start), shift(end));
+    }
+    Some(GlyphMask {
+        left,
+        top,
+        image: rasterizer.into_image(),
+    })
+}
+
+/// Renders a line of shaped glyphs into an image.
+///
+/// The image is as wide as the advances of the glyphs and as high as the
+/// distance between the font's ascender and descender, with the baseline at
+/// the ascender and the first glyph starting at the left edge. Ink outside of
+/// these bounds is clipped. The coverage of overlapping glyphs is added up.
+pub fn render_buffer(font: &Font<'_>, buffer: &GlyphBuffer) -> GrayImage {
+    let extents = font.get_font_h_extents().unwrap_or_default();
+    let advance: Position = buffer
+        .get_glyph_positions()
+        .iter()
+        .map(|position| position.x_advance)
+        .sum();
+    let width = (advance as f32 / UNITS_PER_PIXEL).ceil().max(0.0) as usize;
+    let height = ((extents.ascender - extents.descender) as f32 / UNITS_PER_PIXEL).ceil();
+    let mut image = GrayImage::new(width, height.max(0.0) as usize);
+
+    let baseline = extents.ascender as f32 / UNITS_PER_PIXEL;
+    let (mut pen_x, mut pen_y) = (0, 0);
+    for (info, position) in buffer
+        .get_glyph_infos()
+        .iter()
+        .zip(buffer.get_glyph_positions())
+    {
+        let x = (pen_x + position.x_offset) as f32 / UNITS_PER_PIXEL;
+        let y = baseline - (pen_y + position.y_offset) as f32 / UNITS_PER_PIXEL;
+        if let Some(mask) = rasterize_glyph(font, info.codepoint, x, y) {
+            image.composite(&mask);
+        }
+        pen_x += position.x_advance;
+        pen_y += position.y_advance;
+    }
+    image
+}
+
+/// A point in pixels, with the y-axis pointing downwards.
+type Point = (f32, f32);
+
+/// Collects the outline of a glyph as line segments in pixels.
+struct Outline {
+    origin: Point,
+    start: Point,
+    current: Point,
+    lines: Vec<(Point, Point)>,
+}
+
+impl Outline {
+    // The maximal distance in pixels between a curve and its approximation.
+    const TOLERANCE: f32 = 0.1;
+
+    fn new(x: f32, y: f32) -> Outline {
+        Outline {
+            origin: (x, y),
+            start: (x, y),
+            current: (x, y),
+            lines: Vec::new(),
+        }
+    }
+
+    fn to_pixels(&self, x: f32, y: f32) -> Point {
+        (
+            self.origin.0 + x / UNITS_PER_PIXEL,
+            self.origin.1 - y / UNITS_PER_PIXEL,
+        )
+    }
+
+    fn line(&mut self, end: Point) {
+        if end != self.current {
+            self.lines.push((self.current, end));
+        }
+        self.current = end;
+    }
+
+    /// Closes the current contour.
+    fn close(&mut self) {
+        let start = self.start;
+        self.line(start);
+    }
+
+    /// Approximates a curve with `deviation` as the largest second difference
+    /// of its control points by line segments through `point(t)`.
+    fn curve(&mut self, deviation: f32, point: impl Fn(f32) -> Point) {
+        // The distance between a quadratic curve and a line through its ends
+        // is at most a quarter of the second difference. Splitting the curve
+        // into n parts reduces it by n².
+        let steps = (deviation / (4.0 * Self::TOLERANCE)).sqrt().ceil().max(1.0);
+        let steps = steps.min(256.0) as usize;
+        for step in 1..=steps {
+            let end = point(step as f32 / steps as f32);
+            self.line(end);
+        }
+    }
+}
+
+fn second_difference(a: Point, b: Point, c: Point) -> f32 {
+    let (x, y) = (a.0 - 2.0 * b.0 + c.0, a.1 - 2.0 * b.1 + c.1);
+    (x * x + y * y).sqrt()
+}
+
+impl DrawFuncs for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        let start = self.to_pixels(x, y);
+        self.start = start;
+        self.current = start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let end = self.to_pixels(x, y);
+        self.line(end);
+    }
+
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (
+            self.current,
+            self.to_pixels(control_x, control_y),
+            self.to_pixels(x, y),
+        );
+        self.curve(second_difference(p0, p1, p2), |t| {
+            let u = 1.0 - t;
+            let (a, b, c) = (u * u, 2.0 * u * t, t * t);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0,
+                a * p0.1 + b * p1.1 + c * p2.1,
+            )
+        });
+    }
+
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) {
+        let p0 = self.current;
+        let p1 = self.to_pixels(control1_x, control1_y);
+        let p2 = self.to_pixels(control2_x, control2_y);
+        let p3 = self.to_pixels(x, y);
+        // The second derivative of a cubic curve is up to 1.5 times larger
+        // than that of a quadratic curve with the same second differences.
+        let deviation = 1.5 * second_difference(p0, p1, p2).max(second_difference(p1, p2, p3));
+        self.curve(deviation, |t| {
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+            )
+        });
+    }
+
+    fn close_path(&mut self) {
+        self.close();
+    }
+}
+
+/// Accumulates the signed area that line segments cover in every pixel.
+///
+/// Every segment adds its winding direction times the covered area to the
+/// pixels it crosses and the rest of the row's area to the pixel after it.
+/// Summing up the values row by row yields the winding number of every pixel,
+/// weighted by coverage.
+struct Rasterizer {
+    width: usize,
+    height: usize,
+    // One extra cell for the area right of the last column.
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    fn new(width: usize, height: usize) -> Rasterizer {
+        Rasterizer {
+            width,
+            height,
+            accumulation: vec![0.0; width * height + 2],
+        }
+    }
+
+    /// Adds the line from `start` to `end`. Both points must lie inside of
+    /// the rasterizer's bounds.
+    fn draw_line(&mut self, start: Point, end: Point) {
+        if start.1 == end.1 {
+            return;
+        }
+        let (direction, top, bottom) = if start.1 < end.1 {
+            (1.0, start, end)
+        } else {
+            (-1.0, end, start)
+        };
+        let dxdy = (bottom.0 - top.0) / (bottom.1 - top.1);
+        let mut x = top.0;
+        let first_row = top.1.max(0.0) as usize;
+        let last_row = (bottom.1.ceil() as usize).min(self.height);
+        for row in first_row..last_row {
+            let row_start = row * self.width;
+            let dy = ((row + 1) as f32).min(bottom.1) - (row as f32).max(top.1);
+            let x_next = x + dxdy * dy;
+            let area = dy * direction;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0_cell = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1_cell = x1_ceil as usize;
+            let cell = row_start + x0_cell;
+            if x1_cell <= x0_cell + 1 {
+                // The segment stays within one pixel of the row.
+                let center = 0.5 * (x + x_next) - x0_floor;
+                self.accumulation[cell] += area - area * center;
+                self.accumulation[cell + 1] += area * center;
+            } else {
+                let slope = (x1 - x0).recip();
+                let x0_fraction = x0 - x0_floor;
+                let first = 0.5 * slope * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+                let x1_fraction = x1 - x1_ceil + 1.0;
+                let last = 0.5 * slope * x1_fraction * x1_fraction;
+                self.accumulation[cell] += area * first;
+                if x1_cell == x0_cell + 2 {
+                    self.accumulation[cell + 1] += area * (1.0 - first - last);
+                } else {
+                    let second = slope * (1.5 - x0_fraction);
+                    self.accumulation[cell + 1] += area * (second - first);
+                    for column in x0_cell + 2..x1_cell - 1 {
+                        self.accumulation[row_start + column] += area * slope;
+                    }
+                    let before_last = second + (x1_cell - x0_cell - 3) as f32 * slope;
+                    self.accumulation[row_start + x1_cell - 1] += area * (1.0 - before_last - last);
+                }
+                self.accumulation[row_start + x1_cell] += area * last;
+            }
+            x = x_next;
+        }
+    }
+
+    /// Sums up the accumulated areas into coverage values, where any nonzero
+    /// winding number counts as covered.
+    fn into_image(self) -> GrayImage {
+        let mut image = GrayImage::new(self.width, self.height);
+        let mut sum = 0.0;
+        for (pixel, area) in image.pixels.iter_mut().zip(&self.accumulation) {
+            sum += area;
+            *pixel = (sum.abs().min(1.0) * 255.0).round() as u8;
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{font, shaped};
+    use crate::{shape, UnicodeBuffer};
+
+    fn scaled_font() -> crate::Owned<Font<'static>> {
+        let mut font = font();
+        font.set_scale(32 * 64, 32 * 64);
+        font
+    }
+
+    fn square(rasterizer: &mut Rasterizer, x: f32, y: f32, size: f32, clockwise: bool) {
+        let mut corners = vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)];
+        if !clockwise {
+            corners.reverse();
+        }
+        for i in 0..corners.len() {
+            rasterizer.draw_line(corners[i], corners[(i + 1) % corners.len()]);
+        }
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        square(&mut rasterizer, 0.5, 1.0, 2.0, true);
+        let image = rasterizer.into_image();
+        let row: Vec<_> = (0..4).map(|x| image.pixel(x, 1)).collect();
+        assert_eq!(row, vec![128, 255, 128, 0]);
+        assert!((0..4).all(|x| image.pixel(x, 0) == 0 && image.pixel(x, 3) == 0));
+    }
+
+    #[test]
+    fn test_nonzero_winding() {
+        // overlapping contours with the same direction stay covered
+        let mut rasterizer = Rasterizer::new(4, 4);
+        square(&mut rasterizer, 0.0, 0.0, 3.0, true);
+        square(&mut rasterizer, 1.0, 1.0, 3.0, true);
+        let image = rasterizer.into_image();
+        assert_eq!(image.pixel(1, 1), 255);
+        assert_eq!(image.pixel(0, 3), 0);
+
+        // a contour with the opposite direction cuts a hole
+        let mut rasterizer = Rasterizer::new(4, 4);
+        square(&mut rasterizer, 0.0, 0.0, 4.0, true);
+        square(&mut rasterizer, 1.0, 1.0, 2.0, false);
+        let image = rasterizer.into_image();
+        assert_eq!((image.pixel(0, 0), image.pixel(1, 1)), (255, 0));
+    }
+
+    #[test]
+    fn test_rasterize_glyph() {
+        let font = scaled_font();
+        let glyph = font.get_nominal_glyph('o').unwrap();
+        let mask = rasterize_glyph(&font, glyph, 10.0, 40.0).unwrap();
+        assert!(mask.top < 40 && mask.top + mask.image.height() as i32 <= 41);
+        // the counter of "o" is empty
+        let (center_x, center_y) = (mask.image.width() / 2, mask.image.height() / 2);
+        assert_eq!(mask.image.pixel(center_x, center_y), 0);
+        assert!(mask.image.pixels().contains(&255));
+
+        // subpixel offsets change the coverage, but not the total ink
+        let shifted = rasterize_glyph(&font, glyph, 10.5, 40.0).unwrap();
+        assert_ne!(shifted.image, mask.image);
+        let ink = |mask: &GlyphMask| mask.image.pixels().iter().map(|&p| p as i32).sum::<i32>();
+        assert!((ink(&mask) - ink(&shifted)).abs() < ink(&mask) / 50);
+
+        let space = font.get_nominal_glyph(' ').unwrap();
+        assert_eq!(rasterize_glyph(&font, space, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_render_buffer() {
+        let font = scaled_font();
+        let buffer = shaped(&font, "Hi");
+        let image = render_buffer(&font, &buffer);
+        let extents = font.get_font_h_extents().unwrap();
+        let advance: Position = buffer
+            .get_glyph_positions()
+            .iter()
+            .map(|position| position.x_advance)
+            .sum();
+        assert_eq!(image.width(), (advance as f32 / 64.0).ceil() as usize);
+        let height = (extents.ascender - extents.descender) as f32 / 64.0;
+        assert_eq!(image.height(), height.ceil() as usize);
+        assert_eq!(image.pixels().len(), image.width() * image.height());
+        // the stem of "H" is solid and nothing is below the baseline
+        let baseline = (extents.ascender as f32 / 64.0) as usize;
+        assert!((0..image.width()).any(|x| image.pixel(x, baseline - 2) == 255));
+        assert!((0..image.width()).all(|x| image.pixel(x, baseline + 2) == 0));
+
+        let empty = shape(&font, UnicodeBuffer::new(), &[]);
+        assert_eq!(render_buffer(&font, &empty).width(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use crate::buffer::GlyphBuffer;
+use crate::caret::CaretPiece;
+use crate::common::Rect;
+use crate::font::{Font, Position};
+
+impl GlyphBuffer {
+    /// Returns the rectangles that highlight the part `text_range` of `text`
+    /// on a line of shaped text.
+    ///
+    /// `text` is the text the buffer was shaped from, see
+    /// [`hit_test`](#method.hit_test). Graphemes that are only partly inside
+    /// `text_range` are highlighted completely. Ligatures are split into the
+    /// parts showing each grapheme like for caret positions. The highlighted
+    /// graphemes are merged into one rectangle wherever they are visually
+    /// adjacent, so a selection results in several rectangles if its
+    /// graphemes are not next to each other on the line. Use
+    /// `Paragraph::selection_rects` for lines with runs of mixed directions.
+    ///
+    /// The rectangles are ordered from left to right and reach from the
+    /// font's descender to its ascender. The first glyph starts at `x = 0`
+    /// and the baseline is at `y = 0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("could not load face");
+    /// let font = Font::new(face);
+    ///
+    /// let text = "Hello world";
+    /// let buffer = shape(&font, UnicodeBuffer::new().add_str(text), &[]);
+    /// let rects = buffer.selection_rects(&font, text, 6..11);
+    /// assert_eq!(rects.len(), 1);
+    /// assert_eq!(rects[0].x, buffer.caret_x(&font, text, 6).unwrap());
+    /// ```
+    pub fn selection_rects(
+        &self,
+        font: &Font<'_>,
+        text: &str,
+        text_range: Range<usize>,
+    ) -> Vec<Rect> {
+        let extents = font.get_font_h_extents().unwrap_or_default();
+        let height = extents.ascender - extents.descender;
+        let pieces = self.caret_pieces(font, text);
+        selection_rects(&pieces, text_range, extents.descender, height)
+    }
+}
+
+/// Returns the rectangles covering the `pieces` of a line that overlap
+/// `text_range`, merging visually adjacent pieces.
+pub(crate) fn selection_rects<'b>(
+    pieces: impl IntoIterator<Item = &'b CaretPiece>,
+    text_range: Range<usize>,
+    y: Position,
+    height: Position,
+) -> Vec<Rect> {
+    let mut rects: Vec<Rect> = Vec::new();
+    for piece in pieces {
+        let selected =
+            piece.text_range.start < text_range.end && text_range.start < piece.text_range.end;
+        if !selected || piece.left == piece.right {
+            continue;
+        }
+        match rects.last_mut() {
+            Some(rect) if rect.right() == piece.left => {
+                rect.width = piece.right - rect.x;
+            }
+            _ => rects.push(Rect::new(piece.left, y, piece.right - piece.left, height)),
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{font, shaped};
+    use crate::{Font, GlyphBuffer};
+
+    fn edges(
+        buffer: &GlyphBuffer,
+        font: &Font<'_>,
+        text: &str,
+        range: std::ops::Range<usize>,
+    ) -> Vec<(i32, i32)> {
+        buffer
+            .selection_rects(font, text, range)
+            .iter()
+            .map(|rect| (rect.x, rect.right()))
+            .collect()
+    }
+
+    #[test]
+    fn test_selection_rects() {
+        let font = font();
+        let text = "abc";
+        let buffer = shaped(&font, text);
+        let caret = |offset| buffer.caret_x(&font, text, offset).unwrap();
+        let rects = buffer.selection_rects(&font, text, 1..2);
+        let extents = font.get_font_h_extents().unwrap();
+        assert_eq!(rects.len(), 1);
+        assert_eq!((rects[0].x, rects[0].right()), (caret(1), caret(2)));
+        assert_eq!(
+            (rects[0].y, rects[0].top()),
+            (extents.descender, extents.ascender)
+        );
+        assert_eq!(edges(&buffer, &font, text, 0..3), vec![(0, caret(3))]);
+        assert!(buffer.selection_rects(&font, text, 1..1).is_empty());
+
+        // part of a ligature
+        let text = "after";
+        let buffer = shaped(&font, text);
+        let caret = |offset| buffer.caret_x(&font, text, offset).unwrap();
+        assert_eq!(edges(&buffer, &font, text, 0..2), vec![(0, caret(2))]);
+        assert_eq!(
+            edges(&buffer, &font, text, 2..5),
+            vec![(caret(2), caret(5))]
+        );
+    }
+
+    #[test]
+    fn test_right_to_left_selection() {
+        let font = font();
+        let text = "\u{5D0}\u{5D1}\u{5D2}";
+        let buffer = shaped(&font, text);
+        let caret = |offset| buffer.caret_x(&font, text, offset).unwrap();
+        assert_eq!(
+            edges(&buffer, &font, text, 0..4),
+            vec![(caret(4), caret(0))]
+        );
+        // a partly selected grapheme is highlighted completely
+        assert_eq!(
+            edges(&buffer, &font, text, 3..5),
+            vec![(caret(6), caret(2))]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ptr::NonNull;
+
+use harfbuzz_bindings::{
+    hb_set_add, hb_set_add_range, hb_set_clear, hb_set_copy, hb_set_create, hb_set_del,
+    hb_set_destroy, hb_set_get_max, hb_set_get_min, hb_set_get_population, hb_set_has,
+    hb_set_is_empty, hb_set_is_equal, hb_set_next, hb_set_reference, hb_set_t,
+};
+
+use crate::common::{HarfbuzzObject, Owned};
+
+/// The value HarfBuzz uses to signal "no value" when iterating a set.
+const SET_VALUE_INVALID: u32 = u32::max_value();
+
+/// A wrapper around `hb_set_t`.
+///
+/// A `Set` is an efficient set of `u32` values. HarfBuzz uses it to represent
+/// collections of unicode codepoints (e.g. the characters covered by a
+/// [`Face`](crate::Face)) or glyph indices.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::Set;
+///
+/// let mut set = Set::new();
+/// set.insert('a' as u32);
+/// set.insert_range('0' as u32..='9' as u32);
+///
+/// assert!(set.contains('a' as u32));
+/// assert!(!set.contains('b' as u32));
+/// assert_eq!(set.len(), 11);
+/// ```
+pub struct Set {
+    raw: NonNull<hb_set_t>,
+}
+
+impl Set {
+    /// Creates a new empty `Set`.
+    pub fn new() -> Owned<Set> {
+        unsafe { Owned::from_raw(hb_set_create()) }
+    }
+
+    /// Returns the number of values contained in the set.
+    pub fn len(&self) -> usize {
+        unsafe { hb_set_get_population(self.as_raw()) as usize }
+    }
+
+    /// Returns `true` if the set contains no values.
+    pub fn is_empty(&self) -> bool {
+        unsafe { hb_set_is_empty(self.as_raw()) == 1 }
+    }
+
+    /// Returns `true` if `value` is contained in the set.
+    pub fn contains(&self, value: u32) -> bool {
+        unsafe { hb_set_has(self.as_raw(), value) == 1 }
+    }
+
+    /// Adds `value` to the set.
+    pub fn insert(&mut self, value: u32) {
+        unsafe { hb_set_add(self.as_raw_mut(), value) }
+    }
+
+    /// Adds all values in the inclusive `range` to the set.
+    pub fn insert_range(&mut self, range: std::ops::RangeInclusive<u32>) {
+        if range.is_empty() {
+            return;
+        }
+        unsafe { hb_set_add_range(self.as_raw_mut(), *range.start(), *range.end()) }
+    }
+
+    /// Removes `value` from the set.
+    pub fn remove(&mut self, value: u32) {
+        unsafe { hb_set_del(self.as_raw_mut(), value) }
+    }
+
+    /// Removes all values from the set.
+    pub fn clear(&mut self) {
+        unsafe { hb_set_clear(self.as_raw_mut()) }
+    }
+
+    /// Returns the smallest value in the set or `None` if the set is empty.
+    pub fn min(&self) -> Option<u32> {
+        match unsafe { hb_set_get_min(self.as_raw()) } {
+            SET_VALUE_INVALID => None,
+            value => Some(value),
+        }
+    }
+
+    /// Returns the largest value in the set or `None` if the set is empty.
+    pub fn max(&self) -> Option<u32> {
+        match unsafe { hb_set_get_max(self.as_raw()) } {
+            SET_VALUE_INVALID => None,
+            value => Some(value),
+        }
+    }
+
+    /// Returns an iterator over the values of the set in ascending order.
+    pub fn iter(&self) -> SetIter<'_> {
+        SetIter {
+            set: self,
+            current: SET_VALUE_INVALID,
+        }
+    }
+}
+
+impl fmt::Debug for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Set {
+    fn eq(&self, other: &Set) -> bool {
+        unsafe { hb_set_is_equal(self.as_raw(), other.as_raw()) == 1 }
+    }
+}
+
+impl Eq for Set {}
+
+impl Clone for Owned<Set> {
+    fn clone(&self) -> Self {
+        unsafe { Owned::from_raw(hb_set_copy(self.as_raw())) }
+    }
+}
+
+impl Default for Owned<Set> {
+    fn default() -> Self {
+        Set::new()
+    }
+}
+
+impl FromIterator<u32> for Owned<Set> {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut set = Set::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<u32> for Set {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Set {
+    type Item = u32;
+    type IntoIter = SetIter<'a>;
+
+    fn into_iter(self) -> SetIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a `Set`.
+///
+/// You get an iterator of this type from the `.iter()` method on `Set`.
+#[derive(Debug, Clone)]
+pub struct SetIter<'a> {
+    set: &'a Set,
+    current: u32,
+}
+
+impl<'a> Iterator for SetIter<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let found = unsafe { hb_set_next(self.set.as_raw(), &mut self.current) };
+        if found == 1 {
+            Some(self.current)
+        } else {
+            None
+        }
+    }
+}
+
+unsafe impl HarfbuzzObject for Set {
+    type Raw = hb_set_t;
+
+    unsafe fn from_raw(raw: *const Self::Raw) -> Self {
+        Set {
+            raw: NonNull::new(raw as *mut _).unwrap(),
+        }
+    }
+
+    fn as_raw(&self) -> *mut Self::Raw {
+        self.raw.as_ptr()
+    }
+
+    unsafe fn reference(&self) {
+        hb_set_reference(self.as_raw());
+    }
+
+    unsafe fn dereference(&self) {
+        hb_set_destroy(self.as_raw());
+    }
+}
+
+// `Set` is not `Sync`: HarfBuzz caches the population and the last page
+// lookup of a set and updates these caches in read-only queries.
+unsafe impl Send for Set {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations() {
+        let mut set = Set::new();
+        assert!(set.is_empty());
+        assert_eq!(set.min(), None);
+
+        set.insert(5);
+        set.insert_range(10..=12);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![5, 10, 11, 12]);
+        assert_eq!(set.min(), Some(5));
+        assert_eq!(set.max(), Some(12));
+
+        set.remove(11);
+        assert!(!set.contains(11));
+        assert!(set.contains(12));
+
+        let copy = set.clone();
+        set.clear();
+        assert!(set.is_empty());
+        assert_eq!(copy.len(), 3);
+    }
+
+    #[test]
+    fn test_set_from_iter() {
+        let set: Owned<Set> = vec![3, 1, 2, 3].into_iter().collect();
+        let other: Owned<Set> = (1..=3).collect();
+        assert_eq!(set, other);
+        assert_eq!(format!("{:?}", &*set), "{1, 2, 3}");
+    }
+}
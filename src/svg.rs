@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::buffer::GlyphBuffer;
+use crate::draw::DrawFuncs;
+use crate::font::{Font, Glyph, Position};
+
+/// Options for `GlyphBuffer::to_svg`.
+///
+/// By default only the glyphs are drawn. The overlays are drawn on top of the
+/// glyphs with thin strokes in distinct colors.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SvgOptions {
+    /// Draw a vertical line at the start of every cluster except the first.
+    pub cluster_boundaries: bool,
+    /// Draw the bounding box of every glyph with ink.
+    pub glyph_boxes: bool,
+    /// Draw the baseline along the whole text.
+    pub baseline: bool,
+}
+
+impl GlyphBuffer {
+    /// Renders the shaped glyphs as an SVG document.
+    ///
+    /// Every glyph outline is emitted once as a `<path>` in the `<defs>` of
+    /// the document and placed at the positions of the buffer by `<use>`
+    /// elements. The coordinates are those of the glyph positions with the
+    /// y-axis flipped, so the first glyph starts at `x = 0` on the baseline
+    /// `y = 0`. The view box spans the font's ascender and descender as well
+    /// as the ink of all glyphs.
+    ///
+    /// The output only depends on the font and the buffer, which makes it
+    /// suitable for snapshots of shaping results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use harfbuzz_rs::*;
+    ///
+    /// let path = "testfiles/SourceSansVariable-Roman.ttf";
+    /// let face = Face::from_file(path, 0).expect("could not load face");
+    /// let font = Font::new(face);
+    ///
+    /// let buffer = shape(&font, UnicodeBuffer::new().add_str("lol"), &[]);
+    /// let options = SvgOptions {
+    ///     baseline: true,
+    ///     ..SvgOptions::default()
+    /// };
+    /// let svg = buffer.to_svg(&font, options);
+    /// assert!(svg.starts_with("<svg"));
+    /// // the outline of "l" is only included once
+    /// assert_eq!(svg.matches("<path").count(), 2);
+    /// assert_eq!(svg.matches("<use").count(), 3);
+    /// ```
+    pub fn to_svg(&self, font: &Font<'_>, options: SvgOptions) -> String {
+        let infos = self.get_glyph_infos();
+        let positions = self.get_glyph_positions();
+        let extents = font.get_font_h_extents().unwrap_or_default();
+
+        // The origins of the glyphs and the end of the pen.
+        let mut origins = Vec::with_capacity(positions.len() + 1);
+        let (mut pen_x, mut pen_y) = (0, 0);
+        for position in positions {
+            origins.push((pen_x, pen_y));
+            pen_x += position.x_advance;
+            pen_y += position.y_advance;
+        }
+        origins.push((pen_x, pen_y));
+
+        // The view box in font coordinates with the y-axis pointing upwards.
+        let mut bounds = Bounds::new(0, extents.descender, pen_x, extents.ascender);
+        bounds.add(pen_x, pen_y + extents.descender);
+        bounds.add(pen_x, pen_y + extents.ascender);
+
+        let mut defs = String::new();
+        let mut uses = String::new();
+        let mut boxes = String::new();
+        let mut paths: HashMap<Glyph, bool> = HashMap::new();
+        for ((info, position), &(x, y)) in infos.iter().zip(positions).zip(&origins) {
+            let glyph = info.codepoint;
+            let (x, y) = (x + position.x_offset, y + position.y_offset);
+            if let Some(glyph_extents) = font.get_glyph_extents(glyph) {
+                let left = x + glyph_extents.x_bearing;
+                let top = y + glyph_extents.y_bearing;
+                let (width, height) = (glyph_extents.width, -glyph_extents.height);
+                if width != 0 && height != 0 {
+                    bounds.add(left, top);
+                    bounds.add(left + width, top - height);
+                    if options.glyph_boxes {
+                        writeln!(
+                            boxes,
+                            r#"<rect class="bbox" x="{}" y="{}" width="{}" height="{}"/>"#,
+                            left, -top, width, height
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            let has_outline = *paths.entry(glyph).or_insert_with(|| {
+                let mut path = SvgPath::default();
+                font.draw_glyph(glyph, &mut path);
+                if path.0.is_empty() {
+                    return false;
+                }
+                writeln!(defs, r#"<path id="g{}" d="{}"/>"#, glyph, path.0.trim_end()).unwrap();
+                true
+            });
+            if has_outline {
+                writeln!(uses, r##"<use href="#g{}" x="{}" y="{}"/>"##, glyph, x, -y).unwrap();
+            }
+        }
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+            bounds.x_min,
+            -bounds.y_max,
+            bounds.x_max - bounds.x_min,
+            bounds.y_max - bounds.y_min
+        )
+        .unwrap();
+        if !defs.is_empty() {
+            writeln!(svg, "<defs>\n{}</defs>", defs).unwrap();
+        }
+        svg.push_str(&uses);
+
+        let stroke = |color: &str| {
+            format!(
+                r#"fill="none" stroke="{}" stroke-width="1" vector-effect="non-scaling-stroke""#,
+                color
+            )
+        };
+        if options.baseline {
+            writeln!(
+                svg,
+                r#"<line class="baseline" x1="0" y1="0" x2="{}" y2="{}" {}/>"#,
+                pen_x,
+                -pen_y,
+                stroke("blue")
+            )
+            .unwrap();
+        }
+        if options.glyph_boxes && !boxes.is_empty() {
+            writeln!(svg, "<g {}>\n{}</g>", stroke("green"), boxes).unwrap();
+        }
+        if options.cluster_boundaries {
+            let text_len = infos.iter().map(|info| info.cluster as usize + 1).max();
+            let mut lines = String::new();
+            for span in self.clusters(text_len.unwrap_or(0)).skip(1) {
+                let (x, y) = origins[span.glyph_range.start];
+                writeln!(
+                    lines,
+                    r#"<line class="cluster" x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                    x,
+                    -(y + extents.ascender),
+                    x,
+                    -(y + extents.descender)
+                )
+                .unwrap();
+            }
+            if !lines.is_empty() {
+                writeln!(svg, "<g {}>\n{}</g>", stroke("red"), lines).unwrap();
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// A bounding box with the y-axis pointing upwards.
+struct Bounds {
+    x_min: Position,
+    y_min: Position,
+    x_max: Position,
+    y_max: Position,
+}
+
+impl Bounds {
+    fn new(x_min: Position, y_min: Position, x_max: Position, y_max: Position) -> Bounds {
+        Bounds {
+            x_min: x_min.min(x_max),
+            y_min: y_min.min(y_max),
+            x_max: x_min.max(x_max),
+            y_max: y_min.max(y_max),
+        }
+    }
+
+    fn add(&mut self, x: Position, y: Position) {
+        self.x_min = self.x_min.min(x);
+        self.y_min = self.y_min.min(y);
+        self.x_max = self.x_max.max(x);
+        self.y_max = self.y_max.max(y);
+    }
+}
+
+/// Builds the path data of an SVG `<path>` from a glyph outline, flipping
+/// the y-axis.
+#[derive(Default)]
+struct SvgPath(String);
+
+impl SvgPath {
+    fn push(&mut self, command: char, points: &[(f32, f32)]) {
+        self.0.push(command);
+        for (i, &(x, y)) in points.iter().enumerate() {
+            if i > 0 {
+                self.0.push(' ');
+            }
+            // Adding zero turns a negative zero into a positive one.
+            write!(self.0, "{},{}", x + 0.0, -y + 0.0).unwrap();
+        }
+    }
+}
+
+impl DrawFuncs for SvgPath {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.push('M', &[(x, y)]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push('L', &[(x, y)]);
+    }
+
+    fn quadratic_to(&mut self, control_x: f32, control_y: f32, x: f32, y: f32) {
+        self.push('Q', &[(control_x, control_y), (x, y)]);
+    }
+
+    fn cubic_to(
+        &mut self,
+        control1_x: f32,
+        control1_y: f32,
+        control2_x: f32,
+        control2_y: f32,
+        x: f32,
+        y: f32,
+    ) {
+        self.push(
+            'C',
+            &[(control1_x, control1_y), (control2_x, control2_y), (x, y)],
+        );
+    }
+
+    fn close_path(&mut self) {
+        self.0.push_str("Z ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{font, shaped};
+
+    #[test]
+    fn test_svg_path() {
+        let mut path = SvgPath::default();
+        path.move_to(0.0, 0.0);
+        path.line_to(10.0, -0.0);
+        path.quadratic_to(10.0, 5.5, 0.0, 10.0);
+        path.close_path();
+        assert_eq!(path.0, "M0,0L10,0Q10,-5.5 0,-10Z ");
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let font = font();
+        let buffer = shaped(&font, "a a");
+        let svg = buffer.to_svg(&font, SvgOptions::default());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 "));
+        assert!(svg.ends_with("</svg>\n"));
+        // the space has no outline
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert_eq!(svg.matches("<use").count(), 2);
+        let glyph = buffer.get_glyph_infos()[0].codepoint;
+        let second_x = buffer.get_glyph_positions()[..2]
+            .iter()
+            .map(|position| position.x_advance)
+            .sum::<i32>();
+        assert!(svg.contains(&format!(r##"<use href="#g{}" x="0" y="0"/>"##, glyph)));
+        assert!(svg.contains(&format!(
+            r##"<use href="#g{}" x="{}" y="0"/>"##,
+            glyph, second_x
+        )));
+        assert!(!svg.contains("<line") && !svg.contains("<rect"));
+    }
+
+    #[test]
+    fn test_overlays() {
+        let font = font();
+        let text = "x\u{301}y z";
+        let buffer = shaped(&font, text);
+        let options = SvgOptions {
+            cluster_boundaries: true,
+            glyph_boxes: true,
+            baseline: true,
+        };
+        let svg = buffer.to_svg(&font, options);
+        assert_eq!(svg.matches(r#"class="baseline""#).count(), 1);
+        // every glyph but the space has a box
+        assert_eq!(svg.matches(r#"class="bbox""#).count(), buffer.len() - 1);
+        // "x" and its accent form a single cluster
+        let clusters = buffer.clusters(text.len()).count();
+        assert_eq!(svg.matches(r#"class="cluster""#).count(), clusters - 1);
+        assert_eq!(clusters, buffer.len() - 1);
+    }
+}
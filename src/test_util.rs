@@ -0,0 +1,46 @@
+//! Fixtures shared by the tests of several modules.
+
+use crate::buffer::{GlyphBuffer, UnicodeBuffer};
+use crate::font::{Font, Glyph, Position};
+use crate::font_funcs::FontFuncs;
+use crate::{shape, Face, Owned};
+
+/// The font in `testfiles` used by most tests.
+pub(crate) fn font() -> Owned<Font<'static>> {
+    let path = "testfiles/SourceSansVariable-Roman.ttf";
+    Font::new(Face::from_file(path, 0).unwrap())
+}
+
+/// Shapes `text` with `font` and no features.
+pub(crate) fn shaped(font: &Font<'_>, text: &str) -> GlyphBuffer {
+    shape(font, UnicodeBuffer::new().add_str(text), &[])
+}
+
+/// Maps every character to the glyph with the same value. All glyphs have an
+/// advance of 500.
+pub(crate) struct IdentityFont;
+
+impl FontFuncs for IdentityFont {
+    fn get_nominal_glyph(&self, _: &Font<'_>, unicode: char) -> Option<Glyph> {
+        Some(unicode as u32)
+    }
+
+    fn get_glyph_h_advance(&self, _: &Font<'_>, _: Glyph) -> Position {
+        500
+    }
+}
+
+/// A font without tables that uses `funcs`.
+pub(crate) fn font_with_funcs<T>(funcs: T) -> Owned<Font<'static>>
+where
+    T: 'static + Send + Sync + FontFuncs,
+{
+    let mut font = Font::new(Face::from_table_func(|_| None));
+    font.set_font_funcs(funcs);
+    font
+}
+
+/// A font without tables that uses `IdentityFont`.
+pub(crate) fn identity_font() -> Owned<Font<'static>> {
+    font_with_funcs(IdentityFont)
+}
@@ -0,0 +1,360 @@
+use std::convert::TryInto;
+
+use crate::buffer::{GlyphBuffer, UnicodeBuffer};
+use crate::common::Direction;
+use crate::font::{Font, Position};
+use crate::{shape, Feature};
+
+/// The ligature features that are disabled when tracking is applied.
+///
+/// Required ligatures (`rlig`) are kept, because the text can not be rendered
+/// correctly without them.
+const OPTIONAL_LIGATURES: [&[u8; 4]; 4] = [b"liga", b"clig", b"dlig", b"hlig"];
+
+/// Shapes `buffer` and adds `tracking` to the space between adjacent clusters.
+///
+/// The extra space is added to the advance of the last glyph of every cluster
+/// in visual order except the last one, so the glyphs of a ligature or a base
+/// character with its marks are never moved apart. This works the same way
+/// for left-to-right and right-to-left text. For vertical text the space is
+/// added downwards. No space is added after the last cluster.
+///
+/// When `tracking` is not zero, optional ligatures (`liga`, `clig`, `dlig`
+/// and `hlig`) are disabled, because letter-spaced text should not contain
+/// them. They can be enabled again with `features`.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+///
+/// let buffer = UnicodeBuffer::new().add_str("offtake");
+/// let output = shape_with_tracking(&font, buffer, &[], 50);
+///
+/// // the "ff" and "ft" ligatures are not used
+/// assert_eq!(output.len(), 7);
+/// ```
+pub fn shape_with_tracking(
+    font: &Font<'_>,
+    buffer: UnicodeBuffer,
+    features: &[Feature],
+    tracking: Position,
+) -> GlyphBuffer {
+    if tracking == 0 {
+        return shape(font, buffer, features);
+    }
+    let mut all_features: Vec<Feature> = OPTIONAL_LIGATURES
+        .iter()
+        .map(|&tag| Feature::new(tag, 0, ..))
+        .collect();
+    all_features.extend_from_slice(features);
+    let mut glyphs = shape(font, buffer, &all_features);
+    apply_tracking(&mut glyphs, tracking);
+    glyphs
+}
+
+/// Shapes `buffer` with the tracking the font's AAT `trak` table specifies
+/// for `track` at the point size of the font.
+///
+/// See `trak_tracking` for how the value is determined and
+/// `shape_with_tracking` for how it is applied. HarfBuzz's own handling of
+/// the `trak` table is disabled, so the tracking is only applied once.
+pub fn shape_with_trak(
+    font: &Font<'_>,
+    buffer: UnicodeBuffer,
+    features: &[Feature],
+    track: f32,
+) -> GlyphBuffer {
+    let buffer = buffer.guess_segment_properties();
+    let tracking = trak_tracking(font, buffer.get_direction(), track).unwrap_or(0);
+    let mut all_features = vec![Feature::new(b"trak", 0, ..)];
+    all_features.extend_from_slice(features);
+    shape_with_tracking(font, buffer, &all_features, tracking)
+}
+
+/// Returns the tracking the font's AAT `trak` table specifies for `track` at
+/// the point size of the font, in font units scaled by the font's scale.
+///
+/// The normal track has the value 0, tighter tracks have negative and looser
+/// tracks positive values. The tracking is interpolated linearly between the
+/// point sizes of the table and clamped at the smallest and largest size.
+///
+/// Returns `None` if the font has no `trak` table, the table has no data for
+/// `direction` or no track with the value `track`, or the point size of the
+/// font is not set.
+pub fn trak_tracking(font: &Font<'_>, direction: Direction, track: f32) -> Option<Position> {
+    let ptem = font.ptem();
+    if ptem <= 0.0 {
+        return None;
+    }
+    let face = font.face();
+    let table = face.table_with_tag(b"trak")?;
+    let data = table.get_data();
+    let vertical = direction.is_vertical();
+    let value = TrakTable::new(data)?.tracking(vertical, track, ptem)?;
+    let scale = if vertical {
+        font.scale().1
+    } else {
+        font.scale().0
+    };
+    let upem = face.upem().max(1) as f32;
+    Some((value * scale as f32 / upem).round() as Position)
+}
+
+/// Adds `tracking` to the last glyph of every cluster but the last one.
+///
+/// No space is added before a cluster without an advance in text order, so
+/// combining marks that got their own cluster (e.g. with
+/// `ClusterLevel::Characters`) stay with their base.
+fn apply_tracking(glyphs: &mut GlyphBuffer, tracking: Position) {
+    let direction = glyphs.0.get_direction();
+    // Only the glyph ranges and advances of the clusters are needed.
+    let spans: Vec<(usize, bool)> = glyphs
+        .clusters(usize::max_value())
+        .map(|span| (span.glyph_range.end, span.advance != 0))
+        .collect();
+    let positions = glyphs.get_glyph_positions_mut();
+    for pair in spans.windows(2) {
+        // The cluster after the gap in text order.
+        let (_, has_advance) = if direction.is_backward() {
+            pair[0]
+        } else {
+            pair[1]
+        };
+        if !has_advance {
+            continue;
+        }
+        let (end, _) = pair[0];
+        if direction.is_vertical() {
+            positions[end - 1].y_advance -= tracking;
+        } else {
+            positions[end - 1].x_advance += tracking;
+        }
+    }
+}
+
+/// A parsed AAT `trak` table.
+struct TrakTable<'b> {
+    data: &'b [u8],
+}
+
+impl<'b> TrakTable<'b> {
+    fn new(data: &'b [u8]) -> Option<TrakTable<'b>> {
+        let table = TrakTable { data };
+        if table.u32(0)? != 0x0001_0000 || table.u16(4)? != 0 {
+            return None;
+        }
+        Some(table)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn fixed(&self, offset: usize) -> Option<f32> {
+        Some(self.u32(offset)? as i32 as f32 / 65536.0)
+    }
+
+    /// The tracking in font units for `track` at the point size `ptem`.
+    fn tracking(&self, vertical: bool, track: f32, ptem: f32) -> Option<f32> {
+        let data_offset = self.u16(if vertical { 8 } else { 6 })? as usize;
+        if data_offset == 0 {
+            return None;
+        }
+        let track_count = self.u16(data_offset)? as usize;
+        let size_count = self.u16(data_offset + 2)? as usize;
+        let size_table = self.u32(data_offset + 4)? as usize;
+        if size_count == 0 {
+            return None;
+        }
+        let track = (track * 65536.0).round() as i32 as u32;
+        let values = (0..track_count).find_map(|i| {
+            let entry = data_offset + 8 + i * 8;
+            match self.u32(entry) {
+                Some(value) if value == track => Some(self.u16(entry + 6).map(usize::from)),
+                Some(_) => None,
+                None => Some(None),
+            }
+        })??;
+        let size = |i: usize| self.fixed(size_table + i * 4);
+        let value = |i: usize| self.u16(values + i * 2).map(|value| value as i16 as f32);
+
+        let next = (0..size_count)
+            .map(&size)
+            .collect::<Option<Vec<f32>>>()?
+            .iter()
+            .position(|&size| size >= ptem);
+        match next {
+            Some(0) => value(0),
+            None => value(size_count - 1),
+            Some(i) => {
+                let (size0, size1) = (size(i - 1)?, size(i)?);
+                let (value0, value1) = (value(i - 1)?, value(i)?);
+                let t = (ptem - size0) / (size1 - size0);
+                Some(value0 + t * (value1 - value0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob::Blob;
+    use crate::buffer::ClusterLevel;
+    use crate::test_util::{font, identity_font, shaped, IdentityFont};
+    use crate::Face;
+
+    fn advances(buffer: &GlyphBuffer) -> Vec<Position> {
+        let positions = buffer.get_glyph_positions();
+        positions
+            .iter()
+            .map(|position| position.x_advance)
+            .collect()
+    }
+
+    #[test]
+    fn test_tracking_between_clusters() {
+        let font = font();
+        let text = "offtake";
+        let untracked = shape(
+            &font,
+            UnicodeBuffer::new().add_str(text),
+            &[Feature::new(b"liga", 0, ..)],
+        );
+        let tracked = shape_with_tracking(&font, UnicodeBuffer::new().add_str(text), &[], 100);
+        assert_eq!(tracked.len(), 7);
+        let mut expected = advances(&untracked);
+        for advance in &mut expected[..6] {
+            *advance += 100;
+        }
+        assert_eq!(advances(&tracked), expected);
+
+        // ligatures can be enabled again
+        let features = [Feature::new(b"liga", 1, ..)];
+        let ligatures =
+            shape_with_tracking(&font, UnicodeBuffer::new().add_str(text), &features, 100);
+        assert!(ligatures.len() < 7);
+
+        let untracked = shaped(&font, text);
+        let output = shape_with_tracking(&font, UnicodeBuffer::new().add_str(text), &[], 0);
+        assert_eq!(advances(&output), advances(&untracked));
+    }
+
+    #[test]
+    fn test_tracking_keeps_marks_with_base() {
+        let font = font();
+        let text = "x\u{301}y";
+        let untracked = shaped(&font, text);
+        let tracked = shape_with_tracking(&font, UnicodeBuffer::new().add_str(text), &[], 100);
+        let (before, after) = (
+            untracked.get_glyph_positions(),
+            tracked.get_glyph_positions(),
+        );
+        assert_eq!(tracked.len(), 3);
+        assert_eq!(after[0].x_advance, before[0].x_advance);
+        // the mark is not moved
+        assert_eq!(after[1].x_offset, before[1].x_offset);
+        assert_eq!(after[1].x_advance, before[1].x_advance + 100);
+        assert_eq!(after[2].x_advance, before[2].x_advance);
+    }
+
+    /// The x coordinates of the glyphs, including their offsets.
+    fn glyph_x(buffer: &GlyphBuffer) -> Vec<Position> {
+        let mut pen = 0;
+        let mut xs = Vec::new();
+        for position in buffer.get_glyph_positions() {
+            xs.push(pen + position.x_offset);
+            pen += position.x_advance;
+        }
+        xs
+    }
+
+    #[test]
+    fn test_tracking_keeps_marks_with_base_at_character_level() {
+        let font = font();
+        for &text in &["e\u{301}x", "x\u{301}y"] {
+            let buffer = || {
+                UnicodeBuffer::new()
+                    .add_str(text)
+                    .set_cluster_level(ClusterLevel::Characters)
+            };
+            let untracked = glyph_x(&shape(&font, buffer(), &[]));
+            let tracked = glyph_x(&shape_with_tracking(&font, buffer(), &[], 100));
+            let last = untracked.len() - 1;
+            // the base and its mark are not moved apart
+            assert_eq!(tracked[..last], untracked[..last], "{:?}", text);
+            assert_eq!(tracked[last], untracked[last] + 100, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_tracking_right_to_left() {
+        let font = identity_font();
+        // the required lam-alef ligature is kept
+        let text = "\u{628}\u{644}\u{627}";
+        let tracked = shape_with_tracking(&font, UnicodeBuffer::new().add_str(text), &[], 100);
+        let clusters: Vec<u32> = tracked
+            .get_glyph_infos()
+            .iter()
+            .map(|info| info.cluster)
+            .collect();
+        assert_eq!(clusters, vec![2, 0]);
+        assert_eq!(advances(&tracked), vec![600, 500]);
+    }
+
+    fn trak_table() -> Vec<u8> {
+        let mut table = Vec::new();
+        // header: version, format, horizontal and vertical data, reserved
+        table.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0]);
+        // one track and two sizes, the sizes start at offset 28
+        table.extend_from_slice(&[0, 1, 0, 2, 0, 0, 0, 28]);
+        // track 0, name index 256, the values start at offset 36
+        table.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 36]);
+        // sizes 12 and 24
+        table.extend_from_slice(&[0, 12, 0, 0, 0, 24, 0, 0]);
+        // values 100 and -100
+        table.extend_from_slice(&100i16.to_be_bytes());
+        table.extend_from_slice(&(-100i16).to_be_bytes());
+        table
+    }
+
+    #[test]
+    fn test_trak_tracking() {
+        let face = Face::from_table_func(|tag| {
+            if tag == b"trak".into() {
+                Some(Blob::with_bytes_owned(trak_table(), Vec::as_slice).into())
+            } else {
+                None
+            }
+        });
+        let mut font = Font::new(face);
+        font.set_font_funcs(IdentityFont);
+        assert_eq!(trak_tracking(&font, Direction::Ltr, 0.0), None);
+
+        let cases = [(6.0, 100), (12.0, 100), (15.0, 50), (18.0, 0), (30.0, -100)];
+        for &(ptem, tracking) in &cases {
+            font.set_ptem(ptem);
+            assert_eq!(trak_tracking(&font, Direction::Ltr, 0.0), Some(tracking));
+        }
+        assert_eq!(trak_tracking(&font, Direction::Ltr, 1.0), None);
+        assert_eq!(trak_tracking(&font, Direction::Ttb, 0.0), None);
+
+        font.set_ptem(12.0);
+        let output = shape_with_trak(&font, UnicodeBuffer::new().add_str("ab"), &[], 0.0);
+        assert_eq!(advances(&output), vec![600, 500]);
+
+        font.set_scale(2000, 2000);
+        assert_eq!(trak_tracking(&font, Direction::Ltr, 0.0), Some(200));
+    }
+}
@@ -0,0 +1,581 @@
+//! Contains the `UnicodeFuncsTrait` trait and the `UnicodeFuncs` type.
+//!
+//! HarfBuzz looks up Unicode character properties through a set of unicode
+//! funcs. They decide the script of characters when guessing segment
+//! properties and drive normalization, mirroring and mark handling during
+//! shaping. Custom unicode funcs can be set on a buffer with
+//! `UnicodeBuffer::set_unicode_funcs`.
+
+use std::os::raw::c_void;
+use std::panic;
+use std::ptr::NonNull;
+
+use harfbuzz_bindings::{
+    hb_bool_t, hb_codepoint_t, hb_script_t, hb_unicode_combining_class,
+    hb_unicode_combining_class_t, hb_unicode_compose, hb_unicode_decompose,
+    hb_unicode_funcs_create, hb_unicode_funcs_destroy, hb_unicode_funcs_get_default,
+    hb_unicode_funcs_get_empty, hb_unicode_funcs_get_parent, hb_unicode_funcs_make_immutable,
+    hb_unicode_funcs_reference, hb_unicode_funcs_set_combining_class_func,
+    hb_unicode_funcs_set_compose_func, hb_unicode_funcs_set_decompose_func,
+    hb_unicode_funcs_set_general_category_func, hb_unicode_funcs_set_mirroring_func,
+    hb_unicode_funcs_set_script_func, hb_unicode_funcs_t, hb_unicode_general_category,
+    hb_unicode_general_category_t, hb_unicode_mirroring, hb_unicode_script,
+    HB_UNICODE_GENERAL_CATEGORY_CLOSE_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_CONNECT_PUNCTUATION,
+    HB_UNICODE_GENERAL_CATEGORY_CONTROL, HB_UNICODE_GENERAL_CATEGORY_CURRENCY_SYMBOL,
+    HB_UNICODE_GENERAL_CATEGORY_DASH_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_DECIMAL_NUMBER,
+    HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK, HB_UNICODE_GENERAL_CATEGORY_FINAL_PUNCTUATION,
+    HB_UNICODE_GENERAL_CATEGORY_FORMAT, HB_UNICODE_GENERAL_CATEGORY_INITIAL_PUNCTUATION,
+    HB_UNICODE_GENERAL_CATEGORY_LETTER_NUMBER, HB_UNICODE_GENERAL_CATEGORY_LINE_SEPARATOR,
+    HB_UNICODE_GENERAL_CATEGORY_LOWERCASE_LETTER, HB_UNICODE_GENERAL_CATEGORY_MATH_SYMBOL,
+    HB_UNICODE_GENERAL_CATEGORY_MODIFIER_LETTER, HB_UNICODE_GENERAL_CATEGORY_MODIFIER_SYMBOL,
+    HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK, HB_UNICODE_GENERAL_CATEGORY_OPEN_PUNCTUATION,
+    HB_UNICODE_GENERAL_CATEGORY_OTHER_LETTER, HB_UNICODE_GENERAL_CATEGORY_OTHER_NUMBER,
+    HB_UNICODE_GENERAL_CATEGORY_OTHER_PUNCTUATION, HB_UNICODE_GENERAL_CATEGORY_OTHER_SYMBOL,
+    HB_UNICODE_GENERAL_CATEGORY_PARAGRAPH_SEPARATOR, HB_UNICODE_GENERAL_CATEGORY_PRIVATE_USE,
+    HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR, HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK,
+    HB_UNICODE_GENERAL_CATEGORY_SURROGATE, HB_UNICODE_GENERAL_CATEGORY_TITLECASE_LETTER,
+    HB_UNICODE_GENERAL_CATEGORY_UNASSIGNED, HB_UNICODE_GENERAL_CATEGORY_UPPERCASE_LETTER,
+};
+
+use crate::common::{HarfbuzzObject, Owned, Script, Shared};
+use crate::font::destroy_box;
+
+macro_rules! general_categories {
+    ($($(#[$attr:meta])* $variant:ident => $raw:ident,)*) => {
+        /// The Unicode General Category of a character.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub enum GeneralCategory {
+            $(
+                $(#[$attr])*
+                $variant,
+            )*
+        }
+
+        impl GeneralCategory {
+            /// Convert into raw value of type `hb_unicode_general_category_t`.
+            pub fn to_raw(self) -> hb_unicode_general_category_t {
+                match self {
+                    $(GeneralCategory::$variant => $raw,)*
+                }
+            }
+
+            /// Create from raw value of type `hb_unicode_general_category_t`.
+            pub fn from_raw(raw: hb_unicode_general_category_t) -> Self {
+                match raw {
+                    $($raw => GeneralCategory::$variant,)*
+                    _ => GeneralCategory::Unassigned,
+                }
+            }
+        }
+    };
+}
+
+general_categories! {
+    /// Cc
+    Control => HB_UNICODE_GENERAL_CATEGORY_CONTROL,
+    /// Cf
+    Format => HB_UNICODE_GENERAL_CATEGORY_FORMAT,
+    /// Cn
+    Unassigned => HB_UNICODE_GENERAL_CATEGORY_UNASSIGNED,
+    /// Co
+    PrivateUse => HB_UNICODE_GENERAL_CATEGORY_PRIVATE_USE,
+    /// Cs
+    Surrogate => HB_UNICODE_GENERAL_CATEGORY_SURROGATE,
+    /// Ll
+    LowercaseLetter => HB_UNICODE_GENERAL_CATEGORY_LOWERCASE_LETTER,
+    /// Lm
+    ModifierLetter => HB_UNICODE_GENERAL_CATEGORY_MODIFIER_LETTER,
+    /// Lo
+    OtherLetter => HB_UNICODE_GENERAL_CATEGORY_OTHER_LETTER,
+    /// Lt
+    TitlecaseLetter => HB_UNICODE_GENERAL_CATEGORY_TITLECASE_LETTER,
+    /// Lu
+    UppercaseLetter => HB_UNICODE_GENERAL_CATEGORY_UPPERCASE_LETTER,
+    /// Mc
+    SpacingMark => HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK,
+    /// Me
+    EnclosingMark => HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK,
+    /// Mn
+    NonSpacingMark => HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK,
+    /// Nd
+    DecimalNumber => HB_UNICODE_GENERAL_CATEGORY_DECIMAL_NUMBER,
+    /// Nl
+    LetterNumber => HB_UNICODE_GENERAL_CATEGORY_LETTER_NUMBER,
+    /// No
+    OtherNumber => HB_UNICODE_GENERAL_CATEGORY_OTHER_NUMBER,
+    /// Pc
+    ConnectPunctuation => HB_UNICODE_GENERAL_CATEGORY_CONNECT_PUNCTUATION,
+    /// Pd
+    DashPunctuation => HB_UNICODE_GENERAL_CATEGORY_DASH_PUNCTUATION,
+    /// Pe
+    ClosePunctuation => HB_UNICODE_GENERAL_CATEGORY_CLOSE_PUNCTUATION,
+    /// Pf
+    FinalPunctuation => HB_UNICODE_GENERAL_CATEGORY_FINAL_PUNCTUATION,
+    /// Pi
+    InitialPunctuation => HB_UNICODE_GENERAL_CATEGORY_INITIAL_PUNCTUATION,
+    /// Po
+    OtherPunctuation => HB_UNICODE_GENERAL_CATEGORY_OTHER_PUNCTUATION,
+    /// Ps
+    OpenPunctuation => HB_UNICODE_GENERAL_CATEGORY_OPEN_PUNCTUATION,
+    /// Sc
+    CurrencySymbol => HB_UNICODE_GENERAL_CATEGORY_CURRENCY_SYMBOL,
+    /// Sk
+    ModifierSymbol => HB_UNICODE_GENERAL_CATEGORY_MODIFIER_SYMBOL,
+    /// Sm
+    MathSymbol => HB_UNICODE_GENERAL_CATEGORY_MATH_SYMBOL,
+    /// So
+    OtherSymbol => HB_UNICODE_GENERAL_CATEGORY_OTHER_SYMBOL,
+    /// Zl
+    LineSeparator => HB_UNICODE_GENERAL_CATEGORY_LINE_SEPARATOR,
+    /// Zp
+    ParagraphSeparator => HB_UNICODE_GENERAL_CATEGORY_PARAGRAPH_SEPARATOR,
+    /// Zs
+    SpaceSeparator => HB_UNICODE_GENERAL_CATEGORY_SPACE_SEPARATOR,
+}
+
+/// This trait specifies the Unicode character properties HarfBuzz uses for
+/// shaping.
+///
+/// Every method gets the `parent` unicode funcs, which are HarfBuzz's default
+/// implementation, and the default implementation of every method returns
+/// the result of `parent`. So implementors only need to override the
+/// properties they want to change.
+///
+/// # Examples
+///
+/// Assign the Latin script to the characters of the Private Use Area, so that
+/// icons in an icon font are shaped together with the surrounding text:
+///
+/// ```
+/// use harfbuzz_rs::*;
+/// use harfbuzz_rs::unicode_funcs::{UnicodeFuncs, UnicodeFuncsTrait};
+///
+/// struct IconScript;
+///
+/// impl UnicodeFuncsTrait for IconScript {
+///     fn script(&self, parent: &UnicodeFuncs, c: char) -> Script {
+///         match c {
+///             '\u{E000}'..='\u{F8FF}' => Script::from_iso15924_tag(b"Latn".into()),
+///             _ => parent.script(c),
+///         }
+///     }
+/// }
+///
+/// let funcs = UnicodeFuncs::new(IconScript);
+/// let buffer = UnicodeBuffer::new()
+///     .set_unicode_funcs(&funcs)
+///     .add_str("\u{E001}")
+///     .guess_segment_properties();
+/// assert_eq!(buffer.get_script(), Tag::from(b"Latn"));
+/// ```
+pub trait UnicodeFuncsTrait {
+    /// The Unicode General Category of `c`.
+    fn general_category(&self, parent: &UnicodeFuncs, c: char) -> GeneralCategory {
+        parent.general_category(c)
+    }
+    /// The canonical combining class of `c`.
+    fn combining_class(&self, parent: &UnicodeFuncs, c: char) -> u8 {
+        parent.combining_class(c)
+    }
+    /// The character that is displayed instead of `c` in right-to-left text.
+    /// Characters that are not mirrored return themselves.
+    fn mirroring(&self, parent: &UnicodeFuncs, c: char) -> char {
+        parent.mirroring(c)
+    }
+    /// The script `c` belongs to.
+    fn script(&self, parent: &UnicodeFuncs, c: char) -> Script {
+        parent.script(c)
+    }
+    /// The canonical composition of `a` and `b`, if there is one.
+    fn compose(&self, parent: &UnicodeFuncs, a: char, b: char) -> Option<char> {
+        parent.compose(a, b)
+    }
+    /// The canonical decomposition of `ab` into one or two characters, if
+    /// there is one.
+    fn decompose(&self, parent: &UnicodeFuncs, ab: char) -> Option<(char, Option<char>)> {
+        parent.decompose(ab)
+    }
+}
+
+/// A set of Unicode character property functions.
+///
+/// Use `UnicodeFuncs::default_funcs` to get HarfBuzz's built-in
+/// implementation or `UnicodeFuncs::new` to create unicode funcs from an
+/// implementation of `UnicodeFuncsTrait`.
+pub struct UnicodeFuncs {
+    raw: NonNull<hb_unicode_funcs_t>,
+}
+
+impl UnicodeFuncs {
+    /// Returns HarfBuzz's default unicode funcs.
+    pub fn default_funcs() -> Shared<UnicodeFuncs> {
+        unsafe { Shared::from_raw_ref(hb_unicode_funcs_get_default()) }
+    }
+
+    /// Creates unicode funcs that use the properties of `funcs` and whose
+    /// parent are the default unicode funcs.
+    pub fn new<T>(funcs: T) -> Owned<UnicodeFuncs>
+    where
+        T: 'static + Send + Sync + UnicodeFuncsTrait,
+    {
+        let raw = unsafe { hb_unicode_funcs_create(hb_unicode_funcs_get_default()) };
+        let user_data = Box::into_raw(Box::new(funcs)) as *mut c_void;
+        // All callbacks share the same user data, which is destroyed with
+        // the general category callback.
+        unsafe {
+            hb_unicode_funcs_set_general_category_func(
+                raw,
+                Some(rust_general_category::<T>),
+                user_data,
+                Some(destroy_box::<T>),
+            );
+            hb_unicode_funcs_set_combining_class_func(
+                raw,
+                Some(rust_combining_class::<T>),
+                user_data,
+                None,
+            );
+            hb_unicode_funcs_set_mirroring_func(raw, Some(rust_mirroring::<T>), user_data, None);
+            hb_unicode_funcs_set_script_func(raw, Some(rust_script::<T>), user_data, None);
+            hb_unicode_funcs_set_compose_func(raw, Some(rust_compose::<T>), user_data, None);
+            hb_unicode_funcs_set_decompose_func(raw, Some(rust_decompose::<T>), user_data, None);
+            hb_unicode_funcs_make_immutable(raw);
+            Owned::from_raw(raw)
+        }
+    }
+
+    /// Returns the parent of these unicode funcs, or `None` if they have no
+    /// parent like the default unicode funcs.
+    pub fn parent(&self) -> Option<Shared<UnicodeFuncs>> {
+        let parent = unsafe { hb_unicode_funcs_get_parent(self.as_raw()) };
+        if parent.is_null() || parent == unsafe { hb_unicode_funcs_get_empty() } {
+            None
+        } else {
+            Some(unsafe { Shared::from_raw_ref(parent) })
+        }
+    }
+
+    /// The Unicode General Category of `c`.
+    pub fn general_category(&self, c: char) -> GeneralCategory {
+        GeneralCategory::from_raw(unsafe { hb_unicode_general_category(self.as_raw(), c as u32) })
+    }
+
+    /// The canonical combining class of `c`.
+    pub fn combining_class(&self, c: char) -> u8 {
+        unsafe { hb_unicode_combining_class(self.as_raw(), c as u32) as u8 }
+    }
+
+    /// The character that is displayed instead of `c` in right-to-left text.
+    pub fn mirroring(&self, c: char) -> char {
+        let mirrored = unsafe { hb_unicode_mirroring(self.as_raw(), c as u32) };
+        std::char::from_u32(mirrored).unwrap_or(c)
+    }
+
+    /// The script `c` belongs to.
+    pub fn script(&self, c: char) -> Script {
+        Script(unsafe { hb_unicode_script(self.as_raw(), c as u32) })
+    }
+
+    /// The canonical composition of `a` and `b`, if there is one.
+    pub fn compose(&self, a: char, b: char) -> Option<char> {
+        let mut ab = 0;
+        let result = unsafe { hb_unicode_compose(self.as_raw(), a as u32, b as u32, &mut ab) };
+        if result == 1 {
+            std::char::from_u32(ab)
+        } else {
+            None
+        }
+    }
+
+    /// The canonical decomposition of `ab` into one or two characters, if
+    /// there is one.
+    pub fn decompose(&self, ab: char) -> Option<(char, Option<char>)> {
+        let (mut a, mut b) = (0, 0);
+        let result = unsafe { hb_unicode_decompose(self.as_raw(), ab as u32, &mut a, &mut b) };
+        if result == 1 {
+            let b = if b == 0 { None } else { std::char::from_u32(b) };
+            std::char::from_u32(a).map(|a| (a, b))
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Debug for UnicodeFuncs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnicodeFuncs")
+            .field("raw", &self.as_raw())
+            .finish()
+    }
+}
+
+unsafe impl HarfbuzzObject for UnicodeFuncs {
+    type Raw = hb_unicode_funcs_t;
+
+    unsafe fn from_raw(raw: *const Self::Raw) -> Self {
+        UnicodeFuncs {
+            raw: NonNull::new(raw as *mut _).unwrap(),
+        }
+    }
+
+    fn as_raw(&self) -> *mut Self::Raw {
+        self.raw.as_ptr()
+    }
+
+    unsafe fn reference(&self) {
+        hb_unicode_funcs_reference(self.as_raw());
+    }
+
+    unsafe fn dereference(&self) {
+        hb_unicode_funcs_destroy(self.as_raw())
+    }
+}
+
+unsafe impl Send for UnicodeFuncs {}
+
+unsafe impl Sync for UnicodeFuncs {}
+
+/// Returns the parent of the unicode funcs `ufuncs` without taking a
+/// reference to it.
+fn parent_of(ufuncs: *mut hb_unicode_funcs_t) -> UnicodeFuncs {
+    unsafe { UnicodeFuncs::from_raw(hb_unicode_funcs_get_parent(ufuncs)) }
+}
+
+/// Calls `f` with the user data and the parent of `ufuncs`. If `f` panics,
+/// the result of `fallback` for the parent is returned instead.
+fn call<T, R>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    user_data: *mut c_void,
+    f: impl FnOnce(&T, &UnicodeFuncs) -> R,
+    fallback: impl FnOnce(&UnicodeFuncs) -> R,
+) -> R {
+    let parent = parent_of(ufuncs);
+    let funcs = unsafe { &*(user_data as *const T) };
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| f(funcs, &parent))) {
+        Ok(result) => result,
+        Err(_) => fallback(&parent),
+    }
+}
+
+// Codepoints that are not valid `char`s, like surrogates, are passed on to
+// the parent unicode funcs.
+
+extern "C" fn rust_general_category<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    unicode: hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_unicode_general_category_t {
+    let c = match std::char::from_u32(unicode) {
+        Some(c) => c,
+        None => return unsafe { hb_unicode_general_category(parent_of(ufuncs).as_raw(), unicode) },
+    };
+    call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.general_category(parent, c).to_raw(),
+        |parent| parent.general_category(c).to_raw(),
+    )
+}
+
+extern "C" fn rust_combining_class<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    unicode: hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_unicode_combining_class_t {
+    let c = match std::char::from_u32(unicode) {
+        Some(c) => c,
+        None => return unsafe { hb_unicode_combining_class(parent_of(ufuncs).as_raw(), unicode) },
+    };
+    let class = call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.combining_class(parent, c),
+        |parent| parent.combining_class(c),
+    );
+    class as hb_unicode_combining_class_t
+}
+
+extern "C" fn rust_mirroring<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    unicode: hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_codepoint_t {
+    let c = match std::char::from_u32(unicode) {
+        Some(c) => c,
+        None => return unsafe { hb_unicode_mirroring(parent_of(ufuncs).as_raw(), unicode) },
+    };
+    let mirrored = call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.mirroring(parent, c),
+        |parent| parent.mirroring(c),
+    );
+    mirrored as hb_codepoint_t
+}
+
+extern "C" fn rust_script<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    unicode: hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_script_t {
+    let c = match std::char::from_u32(unicode) {
+        Some(c) => c,
+        None => return unsafe { hb_unicode_script(parent_of(ufuncs).as_raw(), unicode) },
+    };
+    let script = call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.script(parent, c),
+        |parent| parent.script(c),
+    );
+    script.0
+}
+
+extern "C" fn rust_compose<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    a: hb_codepoint_t,
+    b: hb_codepoint_t,
+    ab: *mut hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_bool_t {
+    let (a, b) = match (std::char::from_u32(a), std::char::from_u32(b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return unsafe { hb_unicode_compose(parent_of(ufuncs).as_raw(), a, b, ab) },
+    };
+    let composed = call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.compose(parent, a, b),
+        |parent| parent.compose(a, b),
+    );
+    match composed {
+        Some(composed) => {
+            unsafe { *ab = composed as hb_codepoint_t };
+            1
+        }
+        None => 0,
+    }
+}
+
+extern "C" fn rust_decompose<T: UnicodeFuncsTrait>(
+    ufuncs: *mut hb_unicode_funcs_t,
+    ab: hb_codepoint_t,
+    a: *mut hb_codepoint_t,
+    b: *mut hb_codepoint_t,
+    user_data: *mut c_void,
+) -> hb_bool_t {
+    let c = match std::char::from_u32(ab) {
+        Some(c) => c,
+        None => return unsafe { hb_unicode_decompose(parent_of(ufuncs).as_raw(), ab, a, b) },
+    };
+    let decomposed = call(
+        ufuncs,
+        user_data,
+        |funcs: &T, parent| funcs.decompose(parent, c),
+        |parent| parent.decompose(c),
+    );
+    match decomposed {
+        Some((first, second)) => {
+            unsafe {
+                *a = first as hb_codepoint_t;
+                *b = second.map_or(0, |second| second as hb_codepoint_t);
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use harfbuzz_bindings::{HB_SCRIPT_ARABIC, HB_SCRIPT_LATIN};
+
+    use super::*;
+    use crate::test_util::font;
+    use crate::{shape, Direction, UnicodeBuffer};
+
+    #[test]
+    fn test_default_funcs() {
+        let funcs = UnicodeFuncs::default_funcs();
+        assert_eq!(
+            funcs.general_category('a'),
+            GeneralCategory::LowercaseLetter
+        );
+        assert_eq!(
+            funcs.general_category('\u{E000}'),
+            GeneralCategory::PrivateUse
+        );
+        assert_eq!(funcs.combining_class('\u{301}'), 230);
+        assert_eq!(funcs.mirroring('('), ')');
+        assert_eq!(funcs.mirroring('a'), 'a');
+        assert_eq!(funcs.script('a'), Script(HB_SCRIPT_LATIN));
+        assert_eq!(funcs.compose('e', '\u{301}'), Some('\u{E9}'));
+        assert_eq!(funcs.compose('x', '\u{301}'), None);
+        assert_eq!(funcs.decompose('\u{E9}'), Some(('e', Some('\u{301}'))));
+        assert_eq!(funcs.decompose('a'), None);
+        assert!(funcs.parent().is_none());
+    }
+
+    struct PrivateUseArabic;
+
+    impl UnicodeFuncsTrait for PrivateUseArabic {
+        fn script(&self, parent: &UnicodeFuncs, c: char) -> Script {
+            match c {
+                '\u{E000}'..='\u{F8FF}' => Script(HB_SCRIPT_ARABIC),
+                _ => parent.script(c),
+            }
+        }
+    }
+
+    #[test]
+    fn test_override_script() {
+        let funcs = UnicodeFuncs::new(PrivateUseArabic);
+        assert_eq!(funcs.script('\u{E000}'), Script(HB_SCRIPT_ARABIC));
+        assert_eq!(funcs.script('a'), Script(HB_SCRIPT_LATIN));
+        // properties that are not overridden come from the parent
+        assert_eq!(
+            funcs.general_category('a'),
+            GeneralCategory::LowercaseLetter
+        );
+        assert!(funcs.parent().is_some());
+
+        let default_props = UnicodeBuffer::new()
+            .add_str("\u{E000}")
+            .guess_segment_properties()
+            .get_segment_properties();
+        assert_ne!(default_props.script, Script(HB_SCRIPT_ARABIC));
+        let buffer = UnicodeBuffer::new()
+            .set_unicode_funcs(&funcs)
+            .add_str("\u{E000}")
+            .guess_segment_properties();
+        assert_eq!(
+            buffer.get_script(),
+            Script(HB_SCRIPT_ARABIC).to_iso15924_tag()
+        );
+        assert_eq!(buffer.get_direction(), Direction::Rtl);
+    }
+
+    struct SwapMirroring;
+
+    impl UnicodeFuncsTrait for SwapMirroring {
+        fn mirroring(&self, parent: &UnicodeFuncs, c: char) -> char {
+            match c {
+                'a' => 'b',
+                _ => parent.mirroring(c),
+            }
+        }
+    }
+
+    #[test]
+    fn test_override_mirroring() {
+        let font = font();
+        let funcs = UnicodeFuncs::new(SwapMirroring);
+        let buffer = UnicodeBuffer::new()
+            .set_unicode_funcs(&funcs)
+            .add_str("a")
+            .set_direction(Direction::Rtl);
+        let output = shape(&font, buffer, &[]);
+        let glyph = output.get_glyph_infos()[0].codepoint;
+        assert_eq!(Some(glyph), font.get_nominal_glyph('b'));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use harfbuzz_bindings::{
+    hb_unicode_funcs_get_default, hb_unicode_general_category,
+    HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK, HB_UNICODE_GENERAL_CATEGORY_FORMAT,
+    HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK, HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK,
+};
+
+use crate::buffer::{BufferFlags, UnicodeBuffer};
+use crate::common::Direction;
+use crate::font::{Font, Glyph, Position};
+use crate::{shape, Feature};
+
+/// How a character is oriented in vertical text, as defined by
+/// [UAX #50][uax50].
+///
+/// [uax50]: https://www.unicode.org/reports/tr50/
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VerticalOrientation {
+    /// Displayed upright, in the same orientation as in the code charts.
+    Upright,
+    /// Displayed rotated 90 degrees clockwise.
+    Rotated,
+    /// Displayed with a vertical alternate glyph, or upright if the font has
+    /// none.
+    TransformedUpright,
+    /// Displayed with a vertical alternate glyph, or rotated if the font has
+    /// none.
+    TransformedRotated,
+}
+
+/// Returns the vertical orientation of `c`.
+///
+/// The data covers the scripts and symbols that are usually set upright in
+/// vertical East Asian text. All other characters are `Rotated`.
+pub fn vertical_orientation(c: char) -> VerticalOrientation {
+    use self::VerticalOrientation::*;
+    match c {
+        // small kana, ideographic comma and full stop
+        '\u{3001}'
+        | '\u{3002}'
+        | '\u{3041}'
+        | '\u{3043}'
+        | '\u{3045}'
+        | '\u{3047}'
+        | '\u{3049}'
+        | '\u{3063}'
+        | '\u{3083}'
+        | '\u{3085}'
+        | '\u{3087}'
+        | '\u{308E}'
+        | '\u{3095}'
+        | '\u{3096}'
+        | '\u{309B}'
+        | '\u{309C}'
+        | '\u{30A1}'
+        | '\u{30A3}'
+        | '\u{30A5}'
+        | '\u{30A7}'
+        | '\u{30A9}'
+        | '\u{30C3}'
+        | '\u{30E3}'
+        | '\u{30E5}'
+        | '\u{30E7}'
+        | '\u{30EE}'
+        | '\u{30F5}'
+        | '\u{30F6}'
+        | '\u{31F0}'..='\u{31FF}'
+        | '\u{FE50}'..='\u{FE52}'
+        | '\u{FF01}'
+        | '\u{FF0C}'
+        | '\u{FF0E}'
+        | '\u{FF1F}' => TransformedUpright,
+        // brackets, dashes and the prolonged sound mark
+        '\u{3008}'..='\u{3011}'
+        | '\u{3014}'..='\u{301F}'
+        | '\u{3030}'
+        | '\u{30A0}'
+        | '\u{30FC}'
+        | '\u{FE59}'..='\u{FE5E}'
+        | '\u{FF08}'
+        | '\u{FF09}'
+        | '\u{FF0D}'
+        | '\u{FF1A}'..='\u{FF1E}'
+        | '\u{FF3B}'
+        | '\u{FF3D}'
+        | '\u{FF3F}'
+        | '\u{FF5B}'..='\u{FF60}'
+        | '\u{FFE3}' => TransformedRotated,
+        '\u{A7}'
+        | '\u{A9}'
+        | '\u{AE}'
+        | '\u{B1}'
+        | '\u{BC}'..='\u{BE}'
+        | '\u{D7}'
+        | '\u{F7}'
+        | '\u{1100}'..='\u{11FF}'
+        | '\u{1401}'..='\u{167F}'
+        | '\u{18B0}'..='\u{18FF}'
+        | '\u{2016}'
+        | '\u{2020}'
+        | '\u{2021}'
+        | '\u{2030}'
+        | '\u{2031}'
+        | '\u{203B}'
+        | '\u{203C}'
+        | '\u{2047}'..='\u{2049}'
+        | '\u{2100}'..='\u{214F}'
+        | '\u{2150}'..='\u{218F}'
+        | '\u{2460}'..='\u{24FF}'
+        | '\u{25A0}'..='\u{2767}'
+        | '\u{2776}'..='\u{2793}'
+        | '\u{2E80}'..='\u{3007}'
+        | '\u{3012}'
+        | '\u{3013}'
+        | '\u{3020}'..='\u{302F}'
+        | '\u{3031}'..='\u{30FF}'
+        | '\u{3100}'..='\u{4DBF}'
+        | '\u{4DC0}'..='\u{9FFF}'
+        | '\u{A000}'..='\u{A4CF}'
+        | '\u{A960}'..='\u{A97F}'
+        | '\u{AC00}'..='\u{D7FF}'
+        | '\u{E000}'..='\u{FAFF}'
+        | '\u{FE10}'..='\u{FE1F}'
+        | '\u{FE30}'..='\u{FE6F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE7}'
+        | '\u{1F000}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}' => Upright,
+        _ => Rotated,
+    }
+}
+
+/// A glyph of a `VerticalLine` together with its absolute position.
+#[derive(Debug, Copy, Clone)]
+pub struct VerticalGlyph<'a> {
+    /// The glyph index in `font`.
+    pub glyph: Glyph,
+    /// The byte offset of the glyph's cluster in the line's text.
+    pub cluster: u32,
+    /// The x coordinate of the glyph's horizontal origin.
+    pub x: Position,
+    /// The y coordinate of the glyph's horizontal origin.
+    pub y: Position,
+    /// How far the next glyph is moved down.
+    pub advance: Position,
+    /// Whether the glyph is drawn rotated 90 degrees clockwise around its
+    /// origin.
+    pub rotated: bool,
+    /// The font the glyph belongs to.
+    pub font: &'a Font<'a>,
+}
+
+/// A sequence of glyphs of a `VerticalLine` that share the same orientation.
+#[derive(Debug, Clone)]
+pub struct VerticalRun<'a> {
+    /// The part of the line's text shown by the run.
+    pub text_range: Range<usize>,
+    /// Whether the run is set upright. The glyphs of other runs are rotated.
+    pub upright: bool,
+    /// The glyphs of the run from top to bottom.
+    pub glyphs: Vec<VerticalGlyph<'a>>,
+}
+
+/// A single line of vertical text.
+///
+/// The text is split into runs of upright and rotated characters according
+/// to their `vertical_orientation`. Characters that are `TransformedRotated`
+/// are set upright if the font has a vertical alternate for them and rotated
+/// otherwise. Combining marks keep the orientation of their base.
+///
+/// Upright runs are shaped with `Direction::Ttb` and the `vert` and `vrt2`
+/// features, so HarfBuzz positions them using the vertical advances and
+/// origins of the font. For OpenType fonts these come from the `vmtx` and
+/// `VORG` tables, with fallbacks derived from the horizontal metrics for
+/// fonts without them. Rotated runs are shaped horizontally and their em box
+/// is centered on the line.
+///
+/// Glyph positions use the coordinate system of HarfBuzz, so y grows upwards.
+/// The center of the line is at `x = 0` and the line starts at `y = 0` and
+/// runs downwards. Every glyph is positioned by its horizontal origin, so
+/// upright glyphs can be drawn as usual and rotated glyphs are drawn rotated
+/// around that point.
+///
+/// # Examples
+///
+/// ```
+/// use harfbuzz_rs::*;
+///
+/// let path = "testfiles/SourceSansVariable-Roman.ttf";
+/// let face = Face::from_file(path, 0).expect("could not load face");
+/// let font = Font::new(face);
+///
+/// let line = VerticalLine::new("\u{65E5}\u{672C}abc", &font, &[]);
+/// assert_eq!(line.runs().len(), 2);
+/// assert!(!line.runs()[1].upright);
+/// ```
+#[derive(Debug)]
+pub struct VerticalLine<'a> {
+    text: &'a str,
+    height: Position,
+    runs: Vec<VerticalRun<'a>>,
+}
+
+impl<'a> VerticalLine<'a> {
+    /// Lays out `text` using `font` in a single vertical line.
+    ///
+    /// The ranges of `features` are byte offsets into `text`.
+    pub fn new(text: &'a str, font: &'a Font<'a>, features: &[Feature]) -> VerticalLine<'a> {
+        let extents = font.get_font_h_extents().unwrap_or_default();
+        let rotated_baseline = -(extents.ascender + extents.descender) / 2;
+        let mut vertical_features = vertical_features();
+        vertical_features.extend_from_slice(features);
+
+        let mut pen = 0;
+        let mut runs = Vec::new();
+        for (text_range, upright) in itemize(text, font) {
+            let mut flags = BufferFlags::empty();
+            if text_range.start == 0 {
+                flags |= BufferFlags::BOT;
+            }
+            if text_range.end == text.len() {
+                flags |= BufferFlags::EOT;
+            }
+            let mut buffer = UnicodeBuffer::new()
+                .add_str_item(text, &text[text_range.clone()])
+                .set_flags(flags);
+            if upright {
+                buffer = buffer.set_direction(Direction::Ttb);
+            }
+            let buffer = buffer.guess_segment_properties();
+            let shaped = if upright {
+                shape(font, buffer, &vertical_features)
+            } else {
+                shape(font, buffer, features)
+            };
+            let glyphs = shaped
+                .get_glyph_infos()
+                .iter()
+                .zip(shaped.get_glyph_positions())
+                .map(|(info, position)| {
+                    let (x, y, advance) = if upright {
+                        let advance = -position.y_advance;
+                        (position.x_offset, pen + position.y_offset, advance)
+                    } else {
+                        let x = rotated_baseline + position.y_offset;
+                        (x, pen - position.x_offset, position.x_advance)
+                    };
+                    pen -= advance;
+                    VerticalGlyph {
+                        glyph: info.codepoint,
+                        cluster: info.cluster,
+                        x,
+                        y,
+                        advance,
+                        rotated: !upright,
+                        font,
+                    }
+                })
+                .collect();
+            runs.push(VerticalRun {
+                text_range,
+                upright,
+                glyphs,
+            });
+        }
+
+        VerticalLine {
+            text,
+            height: -pen,
+            runs,
+        }
+    }
+
+    /// The text of the line.
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// The sum of the advances of all glyphs of the line.
+    pub fn height(&self) -> Position {
+        self.height
+    }
+
+    /// The runs of the line from top to bottom.
+    pub fn runs(&self) -> &[VerticalRun<'a>] {
+        &self.runs
+    }
+
+    /// Returns an iterator over the glyphs of the line from top to bottom.
+    pub fn glyphs(&self) -> impl Iterator<Item = &VerticalGlyph<'a>> {
+        self.runs.iter().flat_map(|run| run.glyphs.iter())
+    }
+}
+
+fn vertical_features() -> Vec<Feature> {
+    vec![Feature::new(b"vert", 1, ..), Feature::new(b"vrt2", 1, ..)]
+}
+
+/// Splits `text` into items that are either upright or rotated.
+fn itemize(text: &str, font: &Font<'_>) -> Vec<(Range<usize>, bool)> {
+    let funcs = unsafe { hb_unicode_funcs_get_default() };
+    let mut vertical_forms = HashMap::new();
+    let mut items: Vec<(Range<usize>, bool)> = Vec::new();
+    for (offset, c) in text.char_indices() {
+        let end = offset + c.len_utf8();
+        let inherits = match unsafe { hb_unicode_general_category(funcs, c as u32) } {
+            HB_UNICODE_GENERAL_CATEGORY_NON_SPACING_MARK
+            | HB_UNICODE_GENERAL_CATEGORY_SPACING_MARK
+            | HB_UNICODE_GENERAL_CATEGORY_ENCLOSING_MARK
+            | HB_UNICODE_GENERAL_CATEGORY_FORMAT => true,
+            _ => false,
+        };
+        let upright = match vertical_orientation(c) {
+            VerticalOrientation::Upright | VerticalOrientation::TransformedUpright => true,
+            VerticalOrientation::Rotated => false,
+            VerticalOrientation::TransformedRotated => *vertical_forms
+                .entry(c)
+                .or_insert_with(|| has_vertical_form(font, c)),
+        };
+        match items.last_mut() {
+            Some((range, item_upright)) if inherits || *item_upright == upright => {
+                range.end = end;
+            }
+            _ => items.push((offset..end, upright)),
+        }
+    }
+    items
+}
+
+/// Whether `font` replaces the glyph of `c` with a vertical alternate when
+/// it is shaped vertically.
+fn has_vertical_form(font: &Font<'_>, c: char) -> bool {
+    let nominal = match font.get_nominal_glyph(c) {
+        Some(glyph) => glyph,
+        None => return false,
+    };
+    let mut bytes = [0; 4];
+    let buffer = UnicodeBuffer::new()
+        .add_str(c.encode_utf8(&mut bytes))
+        .set_direction(Direction::Ttb)
+        .guess_segment_properties();
+    let glyphs = shape(font, buffer, &vertical_features());
+    glyphs
+        .get_glyph_infos()
+        .iter()
+        .any(|info| info.codepoint != nominal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::FontExtents;
+    use crate::font_funcs::FontFuncs;
+    use crate::test_util::font_with_funcs;
+
+    // Maps characters to glyphs with the same value. Glyphs of characters
+    // from U+3000 on are full-width.
+    struct VerticalFont;
+
+    impl VerticalFont {
+        fn h_advance(glyph: Glyph) -> Position {
+            if glyph >= 0x3000 {
+                1000
+            } else {
+                500
+            }
+        }
+    }
+
+    impl FontFuncs for VerticalFont {
+        fn get_font_h_extents(&self, _: &Font<'_>) -> Option<FontExtents> {
+            Some(FontExtents::new(880, -120, 0))
+        }
+
+        fn get_nominal_glyph(&self, _: &Font<'_>, unicode: char) -> Option<Glyph> {
+            Some(unicode as u32)
+        }
+
+        fn get_glyph_h_advance(&self, _: &Font<'_>, glyph: Glyph) -> Position {
+            VerticalFont::h_advance(glyph)
+        }
+
+        fn get_glyph_v_advance(&self, _: &Font<'_>, _: Glyph) -> Position {
+            -1000
+        }
+
+        fn get_glyph_v_origin(&self, _: &Font<'_>, glyph: Glyph) -> Option<(Position, Position)> {
+            Some((VerticalFont::h_advance(glyph) / 2, 880))
+        }
+    }
+
+    fn font() -> crate::Owned<Font<'static>> {
+        font_with_funcs(VerticalFont)
+    }
+
+    fn runs(line: &VerticalLine<'_>) -> Vec<(Range<usize>, bool)> {
+        line.runs()
+            .iter()
+            .map(|run| (run.text_range.clone(), run.upright))
+            .collect()
+    }
+
+    #[test]
+    fn test_vertical_orientation() {
+        use self::VerticalOrientation::*;
+        assert_eq!(vertical_orientation('a'), Rotated);
+        assert_eq!(vertical_orientation('1'), Rotated);
+        assert_eq!(vertical_orientation('\u{65E5}'), Upright);
+        assert_eq!(vertical_orientation('\u{3042}'), Upright);
+        assert_eq!(vertical_orientation('\u{D55C}'), Upright);
+        assert_eq!(vertical_orientation('\u{A9}'), Upright);
+        assert_eq!(vertical_orientation('\u{1F600}'), Upright);
+        assert_eq!(vertical_orientation('\u{3041}'), TransformedUpright);
+        assert_eq!(vertical_orientation('\u{3002}'), TransformedUpright);
+        assert_eq!(vertical_orientation('\u{300C}'), TransformedRotated);
+        assert_eq!(vertical_orientation('\u{30FC}'), TransformedRotated);
+    }
+
+    #[test]
+    fn test_latin_in_japanese() {
+        let font = font();
+        let text = "\u{65E5}\u{672C}abc\u{8A9E}";
+        let line = VerticalLine::new(text, &font, &[]);
+        assert_eq!(
+            runs(&line),
+            vec![(0..6, true), (6..9, false), (9..12, true)]
+        );
+        let glyphs: Vec<_> = line
+            .glyphs()
+            .map(|glyph| {
+                (
+                    glyph.cluster,
+                    glyph.x,
+                    glyph.y,
+                    glyph.advance,
+                    glyph.rotated,
+                )
+            })
+            .collect();
+        assert_eq!(
+            glyphs,
+            vec![
+                (0, -500, -880, 1000, false),
+                (3, -500, -1880, 1000, false),
+                // the em box of rotated glyphs is centered on the line
+                (6, -380, -2000, 500, true),
+                (7, -380, -2500, 500, true),
+                (8, -380, -3000, 500, true),
+                (9, -500, -4380, 1000, false),
+            ]
+        );
+        assert_eq!(line.height(), 4500);
+        assert_eq!(line.text(), text);
+    }
+
+    #[test]
+    fn test_orientation_of_marks_and_brackets() {
+        let font = font();
+        // the font has no vertical alternates, so the brackets are rotated
+        let line = VerticalLine::new("\u{300C}\u{65E5}\u{3002}\u{300D}", &font, &[]);
+        assert_eq!(
+            runs(&line),
+            vec![(0..3, false), (3..9, true), (9..12, false)]
+        );
+        assert!(line.runs()[0].glyphs[0].rotated);
+
+        let line = VerticalLine::new("\u{65E5}a\u{301}\u{65E5}", &font, &[]);
+        assert_eq!(runs(&line), vec![(0..3, true), (3..6, false), (6..9, true)]);
+
+        let line = VerticalLine::new("\u{65E5}\u{3099}", &font, &[]);
+        assert_eq!(runs(&line), vec![(0..6, true)]);
+
+        assert_eq!(VerticalLine::new("", &font, &[]).height(), 0);
+    }
+}